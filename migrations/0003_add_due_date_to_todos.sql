@@ -0,0 +1,2 @@
+-- Optional deadline for a todo
+ALTER TABLE todos ADD COLUMN due_date DATE;
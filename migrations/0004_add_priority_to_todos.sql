@@ -0,0 +1,4 @@
+-- How important a todo is
+CREATE TYPE priority_enum AS ENUM ('low', 'medium', 'high', 'critical');
+
+ALTER TABLE todos ADD COLUMN priority priority_enum NOT NULL DEFAULT 'medium';
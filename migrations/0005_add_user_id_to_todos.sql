@@ -0,0 +1,11 @@
+-- Every todo belongs to the user who created it
+ALTER TABLE todos ADD COLUMN user_id INT REFERENCES "Users"(id) ON DELETE CASCADE;
+
+-- Todos created before ownership existed go to the oldest account; if there
+-- are no accounts at all there is nobody to own them
+UPDATE todos SET user_id = (SELECT MIN(id) FROM "Users");
+DELETE FROM todos WHERE user_id IS NULL;
+
+ALTER TABLE todos ALTER COLUMN user_id SET NOT NULL;
+
+CREATE INDEX todos_user_id_idx ON todos (user_id);
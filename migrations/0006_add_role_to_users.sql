@@ -0,0 +1,4 @@
+-- What a user is allowed to do; everyone registers as a plain user
+CREATE TYPE role_enum AS ENUM ('admin', 'user');
+
+ALTER TABLE "Users" ADD COLUMN role role_enum NOT NULL DEFAULT 'user';
@@ -0,0 +1,2 @@
+-- Soft delete: DELETE /todos/{id} only stamps the row, GET /todos/trash lists them
+ALTER TABLE todos ADD COLUMN deleted_at TIMESTAMPTZ;
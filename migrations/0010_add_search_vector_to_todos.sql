@@ -0,0 +1,5 @@
+-- Full-text search over title and description for GET /todos?q=
+ALTER TABLE todos ADD COLUMN search_vector tsvector
+    GENERATED ALWAYS AS (to_tsvector('english', coalesce(title, '') || ' ' || coalesce(description, ''))) STORED;
+
+CREATE INDEX todos_search_vector_idx ON todos USING GIN (search_vector);
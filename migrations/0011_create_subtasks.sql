@@ -0,0 +1,10 @@
+-- Checklist items inside a todo, listed by position
+CREATE TABLE subtasks (
+    id SERIAL PRIMARY KEY,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    completed BOOLEAN NOT NULL DEFAULT false,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE INDEX subtasks_todo_id_idx ON subtasks (todo_id);
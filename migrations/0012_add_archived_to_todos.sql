@@ -0,0 +1,2 @@
+-- Archived todos are hidden from GET /todos unless include_archived=true
+ALTER TABLE todos ADD COLUMN archived BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,6 @@
+-- Manual order for GET /todos. Fractional so a todo can be moved between two others
+-- without renumbering the rest; existing todos keep their creation order.
+ALTER TABLE todos ADD COLUMN position DOUBLE PRECISION NOT NULL DEFAULT 0;
+UPDATE todos SET position = id;
+
+CREATE INDEX todos_user_id_position_idx ON todos (user_id, position);
@@ -0,0 +1,10 @@
+-- Todos shared with other users, who can view them and edit them with can_edit
+CREATE TABLE todo_shares (
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    shared_with_user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    can_edit BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (todo_id, shared_with_user_id)
+);
+
+CREATE INDEX todo_shares_shared_with_user_id_idx ON todo_shares (shared_with_user_id);
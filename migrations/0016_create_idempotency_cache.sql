@@ -0,0 +1,11 @@
+-- Responses to POST /todos keyed by the client's Idempotency-Key, replayed when a request is retried.
+-- response_status stays NULL while the first request is still being handled.
+CREATE TABLE idempotency_cache (
+    key TEXT PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    response_status SMALLINT,
+    response_body JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idempotency_cache_created_at_idx ON idempotency_cache (created_at);
@@ -0,0 +1,16 @@
+-- What users did, newest first in GET /users/{id}/activity. entity_type/entity_id name what was
+-- acted on ('todo' and its id so far), meta carries details such as the comment or share.
+CREATE TYPE activity_action_enum AS ENUM ('created', 'updated', 'deleted', 'completed', 'shared', 'commented');
+
+CREATE TABLE activity_log (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    action activity_action_enum NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id INTEGER NOT NULL,
+    meta JSONB NOT NULL DEFAULT '{}',
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX activity_log_user_id_idx ON activity_log (user_id, id);
+CREATE INDEX activity_log_entity_idx ON activity_log (entity_type, entity_id);
@@ -0,0 +1,5 @@
+-- Repeating todos: a simplified iCal RRULE (FREQ, INTERVAL, UNTIL) and when the next copy is due
+ALTER TABLE todos ADD COLUMN recurrence_rule TEXT;
+ALTER TABLE todos ADD COLUMN next_occurrence_at TIMESTAMPTZ;
+
+CREATE INDEX idx_todos_next_occurrence_at ON todos (next_occurrence_at) WHERE recurrence_rule IS NOT NULL;
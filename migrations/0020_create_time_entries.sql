@@ -0,0 +1,14 @@
+-- Time users spent on a todo; ended_at is NULL while the timer runs
+CREATE TABLE time_entries (
+    id SERIAL PRIMARY KEY,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMPTZ,
+    CHECK (ended_at IS NULL OR ended_at >= started_at)
+);
+
+CREATE INDEX time_entries_todo_id_idx ON time_entries (todo_id);
+
+-- At most one running timer per user and todo
+CREATE UNIQUE INDEX time_entries_open_idx ON time_entries (todo_id, user_id) WHERE ended_at IS NULL;
@@ -0,0 +1,10 @@
+-- Due-date reminders a user dismissed; GET /notifications hides them for 24 hours after dismissed_at
+CREATE TABLE notifications_dismissed (
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    dismissed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, todo_id)
+);
+
+-- Not used yet, for marking todos whose reminder email went out
+ALTER TABLE todos ADD COLUMN reminder_sent BOOLEAN NOT NULL DEFAULT false;
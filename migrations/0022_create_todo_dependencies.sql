@@ -0,0 +1,10 @@
+-- blocker_id has to be completed before blocked_id can be
+CREATE TABLE todo_dependencies (
+    blocker_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    blocked_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (blocker_id, blocked_id),
+    CHECK (blocker_id <> blocked_id)
+);
+
+CREATE INDEX todo_dependencies_blocked_id_idx ON todo_dependencies (blocked_id);
@@ -0,0 +1,2 @@
+-- Optimistic locking: PATCH /todos/{id} with a version only applies while it is still current
+ALTER TABLE todos ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
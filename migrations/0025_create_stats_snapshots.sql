@@ -0,0 +1,14 @@
+-- One row per user per day, written by the daily snapshot job for GET /users/{id}/stats/history
+CREATE TABLE stats_snapshots (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    snapshot_date DATE NOT NULL,
+    total_todos INTEGER NOT NULL,
+    completed_todos INTEGER NOT NULL,
+    created_todos INTEGER NOT NULL,
+    deleted_todos INTEGER NOT NULL,
+    UNIQUE (user_id, snapshot_date)
+);
+
+-- POST /users/{id}/stats/reset clears the history and counts only what happens after this
+ALTER TABLE "Users" ADD COLUMN stats_reset_at TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+-- TOTP second factor. enabled_at stays NULL between /2fa/setup and a successful /2fa/verify.
+-- backup_codes holds the SHA-256 of each unused code, one is removed every time it's used.
+CREATE TABLE totp_secrets (
+    user_id INTEGER PRIMARY KEY REFERENCES "Users"(id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    enabled_at TIMESTAMPTZ,
+    backup_codes TEXT[] NOT NULL
+);
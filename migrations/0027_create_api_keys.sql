@@ -0,0 +1,15 @@
+-- Long-lived keys for scripts, sent as `Authorization: ApiKey <key>`.
+-- Only the Argon2 hash of a key is kept; key_prefix (its first 8 characters) finds the candidates.
+CREATE TABLE api_keys (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    key_prefix VARCHAR(8) NOT NULL,
+    key_hash TEXT NOT NULL,
+    description TEXT,
+    last_used_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX api_keys_key_prefix_idx ON api_keys (key_prefix);
+CREATE INDEX api_keys_user_id_idx ON api_keys (user_id);
@@ -0,0 +1,13 @@
+-- Reusable todo patterns; the texts may hold `{{name}}` placeholders filled in on instantiation.
+-- subtask_templates is a JSON array of subtask titles.
+CREATE TABLE todo_templates (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    title_template TEXT NOT NULL,
+    description_template TEXT NOT NULL DEFAULT '',
+    priority priority_enum NOT NULL DEFAULT 'medium',
+    subtask_templates JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, name)
+);
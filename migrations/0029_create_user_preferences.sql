@@ -0,0 +1,5 @@
+-- UI settings synced across a user's devices, only known keys are ever stored
+CREATE TABLE user_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES "Users"(id) ON DELETE CASCADE,
+    preferences JSONB NOT NULL DEFAULT '{}'
+);
@@ -0,0 +1,17 @@
+-- A user's category tree; a todo sits in at most one category.
+-- Deleting a category moves its children up to its parent (done by the handler) and leaves its todos uncategorised.
+CREATE TABLE categories (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES "Users"(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    parent_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
+    color TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX categories_user_id_idx ON categories (user_id);
+CREATE INDEX categories_parent_id_idx ON categories (parent_id);
+
+ALTER TABLE todos ADD COLUMN category_id INTEGER REFERENCES categories(id) ON DELETE SET NULL;
+
+CREATE INDEX todos_category_id_idx ON todos (category_id);
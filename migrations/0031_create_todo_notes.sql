@@ -0,0 +1,6 @@
+-- Long-form scratchpad of a todo, at most one per todo
+CREATE TABLE todo_notes (
+    todo_id INTEGER PRIMARY KEY REFERENCES todos(id) ON DELETE CASCADE,
+    content TEXT NOT NULL DEFAULT '',
+    updated_at TIMESTAMPTZ
+);
@@ -0,0 +1,3 @@
+-- Optional contact address, features that send mail require it to be verified
+ALTER TABLE "Users" ADD COLUMN email TEXT;
+ALTER TABLE "Users" ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT false;
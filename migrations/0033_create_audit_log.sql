@@ -0,0 +1,40 @@
+-- Every insert, update and delete on todos and "Users", with the row before and after.
+-- Written by a trigger so no code path can forget it; the acting user comes from the
+-- transaction-local app.actor_user_id setting and is NULL when the change didn't set it.
+CREATE TABLE audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    table_name TEXT NOT NULL,
+    row_id INTEGER,
+    action TEXT NOT NULL,
+    actor_user_id INTEGER,
+    old_data JSONB,
+    new_data JSONB,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_log_table_row_idx ON audit_log (table_name, row_id, id DESC);
+
+CREATE FUNCTION log_change() RETURNS TRIGGER AS $$
+DECLARE
+    -- Password hashes never leave "Users", the search vector is derived from the title
+    old_data JSONB := CASE WHEN TG_OP <> 'INSERT' THEN to_jsonb(OLD) - 'password' - 'search_vector' END;
+    new_data JSONB := CASE WHEN TG_OP <> 'DELETE' THEN to_jsonb(NEW) - 'password' - 'search_vector' END;
+BEGIN
+    INSERT INTO audit_log (table_name, row_id, action, actor_user_id, old_data, new_data)
+    VALUES (
+        TG_TABLE_NAME,
+        (COALESCE(new_data, old_data) ->> 'id')::INTEGER,
+        lower(TG_OP),
+        NULLIF(current_setting('app.actor_user_id', true), '')::INTEGER,
+        old_data,
+        new_data
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_audit AFTER INSERT OR UPDATE OR DELETE ON todos
+    FOR EACH ROW EXECUTE FUNCTION log_change();
+
+CREATE TRIGGER users_audit AFTER INSERT OR UPDATE OR DELETE ON "Users"
+    FOR EACH ROW EXECUTE FUNCTION log_change();
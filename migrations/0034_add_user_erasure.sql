@@ -0,0 +1,25 @@
+-- Erased accounts keep an anonymised "Users" row so references to the id stay valid
+ALTER TABLE "Users" ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- Where a change came from, for the changes that set app.actor_ip
+ALTER TABLE audit_log ADD COLUMN actor_ip TEXT;
+
+CREATE OR REPLACE FUNCTION log_change() RETURNS TRIGGER AS $$
+DECLARE
+    -- Password hashes never leave "Users", the search vector is derived from the title
+    old_data JSONB := CASE WHEN TG_OP <> 'INSERT' THEN to_jsonb(OLD) - 'password' - 'search_vector' END;
+    new_data JSONB := CASE WHEN TG_OP <> 'DELETE' THEN to_jsonb(NEW) - 'password' - 'search_vector' END;
+BEGIN
+    INSERT INTO audit_log (table_name, row_id, action, actor_user_id, actor_ip, old_data, new_data)
+    VALUES (
+        TG_TABLE_NAME,
+        (COALESCE(new_data, old_data) ->> 'id')::INTEGER,
+        lower(TG_OP),
+        NULLIF(current_setting('app.actor_user_id', true), '')::INTEGER,
+        NULLIF(current_setting('app.actor_ip', true), ''),
+        old_data,
+        new_data
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
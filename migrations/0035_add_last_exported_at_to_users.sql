@@ -0,0 +1,2 @@
+-- Data exports are limited to one per hour per user
+ALTER TABLE "Users" ADD COLUMN last_exported_at TIMESTAMPTZ;
@@ -0,0 +1,30 @@
+-- Bulk changes are logged as one entry that describes them instead of one per row
+ALTER TABLE audit_log ADD COLUMN meta JSONB;
+
+CREATE OR REPLACE FUNCTION log_change() RETURNS TRIGGER AS $$
+DECLARE
+    old_data JSONB;
+    new_data JSONB;
+BEGIN
+    -- Set for the rest of a transaction whose code writes that summary entry itself
+    IF current_setting('app.audit_bulk', true) = 'on' THEN
+        RETURN NULL;
+    END IF;
+
+    -- Password hashes never leave "Users", the search vector is derived from the title
+    old_data := CASE WHEN TG_OP <> 'INSERT' THEN to_jsonb(OLD) - 'password' - 'search_vector' END;
+    new_data := CASE WHEN TG_OP <> 'DELETE' THEN to_jsonb(NEW) - 'password' - 'search_vector' END;
+
+    INSERT INTO audit_log (table_name, row_id, action, actor_user_id, actor_ip, old_data, new_data)
+    VALUES (
+        TG_TABLE_NAME,
+        (COALESCE(new_data, old_data) ->> 'id')::INTEGER,
+        lower(TG_OP),
+        NULLIF(current_setting('app.actor_user_id', true), '')::INTEGER,
+        NULLIF(current_setting('app.actor_ip', true), ''),
+        old_data,
+        new_data
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
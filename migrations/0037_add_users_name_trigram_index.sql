@@ -0,0 +1,3 @@
+-- GET /users/search matches names with ILIKE '%q%', which a btree index can't serve
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE INDEX users_name_trgm_idx ON "Users" USING gin (name gin_trgm_ops);
@@ -0,0 +1,2 @@
+-- Profile picture, a link to an image on one of the AVATAR_ALLOWED_DOMAINS
+ALTER TABLE "Users" ADD COLUMN avatar_url TEXT;
@@ -0,0 +1,2 @@
+-- Recorded by POST /todos/{id}/snooze
+ALTER TYPE activity_action_enum ADD VALUE 'snoozed';
@@ -0,0 +1,27 @@
+use serde_json::Value;
+use sqlx::PgExecutor;
+
+use crate::error::AppError;
+use crate::models::ActivityAction;
+
+// Add an entry about a todo to the activity log, in the same transaction as the change when there is one
+pub async fn record_todo_activity<'c>(
+    executor: impl PgExecutor<'c>,
+    user_id: i32,
+    action: ActivityAction,
+    todo_id: i32,
+    meta: Value,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "INSERT INTO activity_log (user_id, action, entity_type, entity_id, meta)
+         VALUES ($1, $2, 'todo', $3, $4)",
+        user_id,
+        action as ActivityAction,
+        todo_id,
+        meta
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,68 @@
+use actix_web::HttpRequest;
+use serde_json::Value;
+use sqlx::{PgConnection, PgExecutor};
+
+use crate::error::AppError;
+
+// Name the user behind the changes of the current transaction, so the audit_log trigger
+// (see migrations/0033_create_audit_log.sql) can record them. Only lasts until the
+// transaction ends; outside of one it has no effect.
+pub async fn set_audit_actor<'c>(executor: impl PgExecutor<'c>, user_id: i32) -> Result<(), AppError> {
+    sqlx::query!("SELECT set_config('app.actor_user_id', $1, true)", user_id.to_string())
+        .fetch_one(executor)
+        .await?;
+
+    Ok(())
+}
+
+// Stop the trigger from logging the rows the current transaction changes from here on, for
+// bulk changes that are logged as one entry with record_bulk_change instead
+pub async fn skip_row_audit<'c>(executor: impl PgExecutor<'c>) -> Result<(), AppError> {
+    sqlx::query!("SELECT set_config('app.audit_bulk', 'on', true)")
+        .fetch_one(executor)
+        .await?;
+
+    Ok(())
+}
+
+// One audit_log entry summing up a change to many rows of a table, `meta` says what it covered.
+// The address comes from set_audit_actor_ip, like for the entries the trigger writes.
+pub async fn record_bulk_change<'c>(
+    executor: impl PgExecutor<'c>,
+    table_name: &str,
+    action: &str,
+    user_id: i32,
+    meta: Value,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "INSERT INTO audit_log (table_name, action, actor_user_id, actor_ip, meta)
+         VALUES ($1, $2, $3, NULLIF(current_setting('app.actor_ip', true), ''), $4)",
+        table_name,
+        action,
+        user_id,
+        meta
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+// Record the client address with the changes of the current transaction, like set_audit_actor
+pub async fn set_audit_actor_ip<'c>(executor: impl PgExecutor<'c>, ip: &str) -> Result<(), AppError> {
+    sqlx::query!("SELECT set_config('app.actor_ip', $1, true)", ip)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(())
+}
+
+// set_audit_actor and set_audit_actor_ip together, for a change a request makes on the user's behalf
+pub async fn set_audit_context(conn: &mut PgConnection, user_id: i32, req: &HttpRequest) -> Result<(), AppError> {
+    set_audit_actor(&mut *conn, user_id).await?;
+    if let Some(addr) = req.peer_addr() {
+        set_audit_actor_ip(&mut *conn, &addr.ip().to_string()).await?;
+    }
+
+    Ok(())
+}
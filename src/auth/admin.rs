@@ -0,0 +1,52 @@
+use sqlx::PgPool;
+use validator::Validate;
+
+use super::password::hash_password;
+use crate::error::AppError;
+use crate::models::{NewUser, Role};
+
+// Create the first admin from ADMIN_NAME and ADMIN_PASSWORD when there is no admin yet.
+// Returns the new user's id, or None when an admin already existed.
+pub async fn seed_admin(pool: &PgPool, name: &str, password: &str) -> Result<Option<i32>, AppError> {
+    let admin = NewUser {
+        name: name.to_string(),
+        password: password.to_string(),
+        email: None,
+    };
+    admin.validate()?;
+
+    let mut tx = pool.begin().await?;
+
+    // Several replicas may start at once, only one of them gets to seed
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext('seed_admin'))")
+        .execute(&mut *tx)
+        .await?;
+
+    let has_admin = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM "Users" WHERE role = $1) AS "exists!""#,
+        Role::Admin as Role
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+    if has_admin {
+        return Ok(None);
+    }
+
+    // An existing account with that name is not promoted, anyone could have registered it
+    let password_hash = hash_password(&admin.password)?;
+    let user_id = sqlx::query_scalar!(
+        r#"INSERT INTO "Users" (name, password, role) VALUES ($1, $2, $3)
+           ON CONFLICT (name) DO NOTHING
+           RETURNING id"#,
+        admin.name,
+        password_hash,
+        Role::Admin as Role
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::Conflict(format!("A user named '{}' already exists and is not an admin", admin.name)))?;
+
+    tx.commit().await?;
+
+    Ok(Some(user_id))
+}
@@ -0,0 +1,42 @@
+use sqlx::PgPool;
+
+use super::password::verify_password;
+use super::refresh::generate_refresh_token;
+use crate::error::AppError;
+use crate::models::Role;
+
+// The first characters of a key, stored in the clear to find it again
+pub const API_KEY_PREFIX_LEN: usize = 8;
+
+// A new key: an 8 character prefix followed by 32 random bytes, all hex
+pub fn generate_api_key() -> String {
+    let prefix = &generate_refresh_token()[..API_KEY_PREFIX_LEN];
+    format!("{}{}", prefix, generate_refresh_token())
+}
+
+// The owner of a key, as (key id, user id, role). None when no unexpired key matches.
+// Prefixes aren't unique, so every key sharing one is tried against the hash.
+pub async fn authenticate_api_key(pool: &PgPool, key: &str) -> Result<Option<(i32, i32, Role)>, AppError> {
+    let Some(prefix) = key.get(..API_KEY_PREFIX_LEN) else {
+        return Ok(None);
+    };
+
+    let candidates = sqlx::query!(
+        r#"SELECT k.id, k.key_hash, u.id AS user_id, u.role AS "role: Role"
+           FROM api_keys k JOIN "Users" u ON u.id = k.user_id
+           WHERE k.key_prefix = $1 AND (k.expires_at IS NULL OR k.expires_at > NOW()) AND u.deleted_at IS NULL"#,
+        prefix
+    )
+        .fetch_all(pool)
+        .await?;
+
+    let Some(found) = candidates.into_iter().find(|candidate| verify_password(key, &candidate.key_hash)) else {
+        return Ok(None);
+    };
+
+    sqlx::query!("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1", found.id)
+        .execute(pool)
+        .await?;
+
+    Ok(Some((found.id, found.user_id, found.role)))
+}
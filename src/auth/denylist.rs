@@ -0,0 +1,34 @@
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+// Ids (`jti`) of tokens revoked by /logout, kept until the token would have expired anyway
+#[derive(Debug, Default)]
+pub struct TokenDenylist {
+    revoked: RwLock<HashMap<String, usize>>,
+}
+
+impl TokenDenylist {
+    pub fn revoke(&self, jti: &str, exp: usize) {
+        self.revoked
+            .write()
+            .expect("Token denylist lock poisoned")
+            .insert(jti.to_string(), exp);
+    }
+
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.revoked
+            .read()
+            .expect("Token denylist lock poisoned")
+            .contains_key(jti)
+    }
+
+    // Expired tokens are rejected on their own, no need to remember them
+    pub fn purge_expired(&self) {
+        let now = Utc::now().timestamp() as usize;
+        self.revoked
+            .write()
+            .expect("Token denylist lock poisoned")
+            .retain(|_, exp| *exp > now);
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use super::jwt::jwt_secret;
+
+// How long a verification token can be confirmed after it was sent
+pub const EMAIL_VERIFICATION_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect()
+}
+
+fn mac(user_id: i32, email: &str, issued_at: i64) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(jwt_secret().as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(format!("{}:{}:{}", user_id, email, issued_at).as_bytes());
+    mac
+}
+
+// `<issued_at>.<hex HMAC-SHA256 of user_id:email:issued_at>`. Nothing is stored: the token is
+// checked against the address on file, so changing the email makes older tokens useless.
+pub fn sign_email_token(user_id: i32, email: &str, issued_at: i64) -> String {
+    format!("{}.{}", issued_at, to_hex(&mac(user_id, email, issued_at).finalize().into_bytes()))
+}
+
+// Whether the token was issued for this user and address and hasn't expired
+pub fn check_email_token(user_id: i32, email: &str, token: &str) -> bool {
+    let Some((issued_at, signature)) = token.split_once('.') else {
+        return false;
+    };
+    let (Ok(issued_at), Some(signature)) = (issued_at.parse::<i64>(), from_hex(signature)) else {
+        return false;
+    };
+    if Utc::now().timestamp() - issued_at > EMAIL_VERIFICATION_TTL_SECONDS {
+        return false;
+    }
+    // verify_slice compares in constant time
+    mac(user_id, email, issued_at).verify_slice(&signature).is_ok()
+}
@@ -0,0 +1,124 @@
+use actix_web::dev::Payload;
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::{web, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use sqlx::PgPool;
+
+use super::api_key::authenticate_api_key;
+use super::denylist::TokenDenylist;
+use super::jwt::validate_token;
+use crate::error::AppError;
+use crate::middleware::error_reporting;
+use crate::models::Role;
+
+// The caller authenticated by a valid `Authorization: Bearer <token>` or
+// `Authorization: ApiKey <key>` header.
+// Add it as a handler argument to make the route require authentication.
+#[derive(Debug)]
+pub struct AuthUser {
+    pub user_id: i32,
+    pub role: Role,
+    pub jti: String, // Empty for API keys, like exp
+    pub exp: usize,
+    pub api_key_id: Option<i32>, // Set when the caller used an API key rather than a JWT
+}
+
+impl FromRequest for AuthUser {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let header = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        if let Some(key) = header.strip_prefix("ApiKey ") {
+            let key = key.trim().to_string();
+            let pool = req.app_data::<web::Data<PgPool>>().cloned();
+
+            return Box::pin(async move {
+                let pool = pool.ok_or_else(|| AppError::InternalError("Database pool is not configured".to_string()))?;
+                let (key_id, user_id, role) = authenticate_api_key(&pool, &key)
+                    .await?
+                    .ok_or(AppError::Unauthorized)?;
+                error_reporting::set_user(user_id);
+
+                Ok(AuthUser {
+                    user_id,
+                    role,
+                    jti: String::new(),
+                    exp: 0,
+                    api_key_id: Some(key_id),
+                })
+            });
+        }
+
+        let Some(denylist) = req.app_data::<web::Data<TokenDenylist>>() else {
+            return Box::pin(async {
+                Err(AppError::InternalError("Token denylist is not configured".to_string()))
+            });
+        };
+
+        let result = match header.strip_prefix("Bearer ") {
+            Some(token) => validate_token(token, denylist)
+                .map(|claims| AuthUser {
+                    user_id: claims.user_id,
+                    role: claims.role,
+                    jti: claims.jti,
+                    exp: claims.exp,
+                    api_key_id: None,
+                })
+                .map_err(|_| AppError::Unauthorized),
+            None => Err(AppError::Unauthorized),
+        };
+        let pool = req.app_data::<web::Data<PgPool>>().cloned();
+
+        Box::pin(async move {
+            let auth = result?;
+            let pool = pool.ok_or_else(|| AppError::InternalError("Database pool is not configured".to_string()))?;
+            if !is_active_user(&pool, auth.user_id).await? {
+                return Err(AppError::Unauthorized);
+            }
+            error_reporting::set_user(auth.user_id);
+
+            Ok(auth)
+        })
+    }
+}
+
+// Tokens are stateless, so one issued before the account was erased or deleted is only
+// refused by looking the user up
+async fn is_active_user(pool: &PgPool, user_id: i32) -> Result<bool, AppError> {
+    let active = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM "Users" WHERE id = $1 AND deleted_at IS NULL) AS "active!""#,
+        user_id
+    )
+        .fetch_one(pool)
+        .await?;
+
+    Ok(active)
+}
+
+// An authenticated caller whose token carries the admin role, anyone else gets 403.
+// Use it in place of AuthUser on admin-only routes.
+#[derive(Debug)]
+pub struct AdminGuard(pub AuthUser);
+
+impl FromRequest for AdminGuard {
+    type Error = AppError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let auth = AuthUser::from_request(req, payload);
+
+        Box::pin(async move {
+            let auth = auth.await?;
+            match auth.role {
+                Role::Admin => Ok(AdminGuard(auth)),
+                Role::User => Err(AppError::Forbidden),
+            }
+        })
+    }
+}
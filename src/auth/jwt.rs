@@ -0,0 +1,80 @@
+use chrono::Utc;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use super::denylist::TokenDenylist;
+use crate::models::Role;
+
+// How long an issued access token stays valid, clients renew it at /refresh
+const TOKEN_TTL_SECONDS: i64 = 15 * 60;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub user_id: i32,
+    pub role: Role,
+    pub jti: String, // Unique token id, used to revoke it
+    pub exp: usize, // Expiry as a unix timestamp
+}
+
+#[derive(Debug)]
+pub enum JwtError {
+    Expired,
+    Invalid,
+    Revoked,
+}
+
+// Set once at startup from the loaded config, which may have read the secret from a file.
+// Falls back to the JWT_SECRET variable, e.g. in the tests.
+static SECRET: OnceLock<String> = OnceLock::new();
+
+pub fn init_secret(secret: String) {
+    let _ = SECRET.set(secret);
+}
+
+pub(crate) fn jwt_secret() -> String {
+    SECRET
+        .get()
+        .cloned()
+        .unwrap_or_else(|| env::var("JWT_SECRET").expect("JWT_SECRET not found in env file"))
+}
+
+// Sign a token for the given user
+pub fn issue_token(user_id: i32, role: Role) -> String {
+    let claims = Claims {
+        user_id,
+        role,
+        jti: Uuid::new_v4().to_string(),
+        exp: (Utc::now().timestamp() + TOKEN_TTL_SECONDS) as usize,
+    };
+
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(jwt_secret().as_bytes()),
+    )
+    .expect("Failed to sign token")
+}
+
+// Check the signature, expiry and revocation of a token and return its claims
+pub fn validate_token(token: &str, denylist: &TokenDenylist) -> Result<Claims, JwtError> {
+    let claims = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(jwt_secret().as_bytes()),
+        &Validation::default(),
+    )
+    .map(|data| data.claims)
+    .map_err(|e| match e.kind() {
+        ErrorKind::ExpiredSignature => JwtError::Expired,
+        _ => JwtError::Invalid,
+    })?;
+
+    if denylist.is_revoked(&claims.jti) {
+        return Err(JwtError::Revoked);
+    }
+
+    Ok(claims)
+}
@@ -0,0 +1,12 @@
+pub mod admin;
+pub mod api_key;
+pub mod denylist;
+pub mod email_verification;
+pub mod extractor;
+pub mod jwt;
+pub mod password;
+pub mod refresh;
+pub mod totp;
+
+pub use denylist::TokenDenylist;
+pub use extractor::{AdminGuard, AuthUser};
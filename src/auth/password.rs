@@ -0,0 +1,25 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+
+use crate::error::AppError;
+
+// Hash a plaintext password with Argon2id and a random salt (PHC string format)
+pub fn hash_password(plain: &str) -> Result<String, AppError> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    Argon2::default()
+        .hash_password(plain.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::InternalError(format!("Failed to hash password: {}", e)))
+}
+
+// Check a plaintext password against a stored hash; malformed hashes never verify
+pub fn verify_password(plain: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(plain.as_bytes(), &parsed)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
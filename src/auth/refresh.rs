@@ -0,0 +1,22 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+// How long a refresh token can be traded for new access tokens
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// A new random 256-bit refresh token, hex encoded
+pub fn generate_refresh_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+// What gets stored and looked up in refresh_tokens.token_hash.
+// The token is already random so a plain SHA-256 is enough, no salt needed.
+pub fn hash_refresh_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,74 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use sqlx::PgPool;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::auth::refresh::hash_refresh_token;
+use crate::error::AppError;
+
+// Shown by authenticator apps next to the account name
+pub const TOTP_ISSUER: &str = "todo_backend";
+// How many single-use backup codes a setup hands out
+pub const BACKUP_CODE_COUNT: usize = 10;
+
+// The usual authenticator app settings: SHA-1, 6 digits, 30 second steps, one step of clock skew.
+// The secret is kept base32 encoded, the way it appears in the otpauth:// URI.
+pub fn build_totp(secret: &str, account_name: &str) -> Result<TOTP, AppError> {
+    let bytes = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::InternalError(format!("Invalid TOTP secret: {:?}", e)))?;
+
+    // A colon would split the label in two, names may contain one
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        bytes,
+        Some(TOTP_ISSUER.to_string()),
+        account_name.replace(':', "_"),
+    )
+        .map_err(|e| AppError::InternalError(format!("Invalid TOTP settings: {:?}", e)))
+}
+
+// A new random 160-bit secret, base32 encoded
+pub fn generate_secret() -> String {
+    match Secret::generate_secret().to_encoded() {
+        Secret::Encoded(secret) => secret,
+        Secret::Raw(_) => unreachable!("to_encoded always returns Secret::Encoded"),
+    }
+}
+
+// Backup codes are 10 hex digits; like refresh tokens only their SHA-256 is stored
+pub fn generate_backup_codes() -> Vec<String> {
+    (0..BACKUP_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; 5];
+            OsRng.fill_bytes(&mut bytes);
+            bytes.iter().map(|b| format!("{:02x}", b)).collect()
+        })
+        .collect()
+}
+
+// Whether the code is the current one (or one step either side of it)
+pub fn check_code(totp: &TOTP, code: &str) -> bool {
+    totp.check_current(code.trim()).unwrap_or(false)
+}
+
+// A current TOTP code, or one of the user's unused backup codes, which is used up on the spot.
+// The removal only goes through once, so two requests can't both spend the same backup code.
+pub async fn verify_second_factor(pool: &PgPool, user_id: i32, secret: &str, code: &str) -> Result<bool, AppError> {
+    if check_code(&build_totp(secret, "")?, code) {
+        return Ok(true);
+    }
+
+    let result = sqlx::query!(
+        "UPDATE totp_secrets SET backup_codes = array_remove(backup_codes, $2)
+         WHERE user_id = $1 AND $2 = ANY(backup_codes)",
+        user_id,
+        hash_refresh_token(code.trim())
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,402 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::middleware::body_limit::{DEFAULT_MAX_BODY_BYTES, MAX_BODY_BYTES_CAP};
+use crate::middleware::rate_limit::RateLimit;
+
+// Used for BATCH_MAX_OPERATIONS when unset, and by POST /batch when the app has no AppConfig
+pub const DEFAULT_BATCH_MAX_OPERATIONS: usize = 50;
+
+// Used for AVATAR_ALLOWED_DOMAINS when unset, and by PATCH /users/{id}/avatar when the app has
+// no AppConfig
+pub const DEFAULT_AVATAR_ALLOWED_DOMAINS: &str = "gravatar.com";
+
+// Read when CONFIG_PATH is unset
+pub const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+
+// Secrets that can also come from a file named by `<KEY>_FILE`, the way Docker and
+// Kubernetes mount them
+const FILE_SECRETS: &[&str] = &["DATABASE_URL", "JWT_SECRET", "DB_PASSWORD"];
+
+const MIN_JWT_SECRET_BYTES: usize = 32;
+
+// Everything the server reads from the config file and the environment, resolved once at startup
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub database_url: String,
+    pub db_password: Option<String>, // Replaces the password in database_url when set
+    pub server_addr: String,
+    pub max_connections: u32,
+    pub min_connections: u32, // Kept open even when idle
+    pub acquire_timeout: Duration, // How long a query waits for a free connection before failing
+    pub idle_timeout: Option<Duration>, // Idle connections above min_connections are closed after this, None keeps them
+    pub max_lifetime: Option<Duration>, // Connections are replaced after this, None keeps them
+    pub test_before_acquire: bool, // Ping a connection before handing it out, catches ones dropped by the network
+    pub db_connect_retries: u32, // Attempts to reach the database on startup, e.g. while it is still booting
+    pub db_connect_retry_delay: Duration,
+    pub jwt_secret: String,
+    pub log_level: String,
+    pub skip_migrations: bool, // Set when migrations are run outside the server
+    pub cors_allowed_origins: Vec<String>,
+    pub avatar_allowed_domains: Vec<String>, // Avatars must be on one of these or a subdomain
+    pub rate_limit: RateLimit,      // Every route except the probes
+    pub auth_rate_limit: RateLimit, // POST /register, /login, /refresh and the password reset routes
+    pub otel_exporter_otlp_endpoint: Option<String>, // Traces are only exported when set
+    pub sentry_dsn: Option<String>, // Panics and 500s are only reported to Sentry when set
+    pub batch_max_operations: usize, // Most operations one POST /batch may carry
+    pub max_request_body_bytes: usize, // Larger bodies are answered with 413
+    // Refuse a second live todo with the same title (ignoring case) for one user, with a 409.
+    // Guards against double submissions, but lists that legitimately repeat titles ("Buy milk"
+    // every week) stop working, and turning it on fails while duplicates exist.
+    pub prevent_duplicate_titles: bool,
+    pub admin_name: Option<String>, // With admin_password, seeds the first admin when there is none
+    pub admin_password: Option<String>,
+    pub hsts_max_age: Option<u64>, // Strict-Transport-Security is only sent when set
+    pub config_file: Option<PathBuf>, // The file the values were merged from, None when there was none
+    pub warnings: Vec<String>, // Questionable but usable settings, logged once tracing is up
+}
+
+// The optional config file (TOML or YAML, picked by extension). Fields mirror AppConfig;
+// anything left out falls back to the environment and then to the defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub database_url: Option<String>,
+    pub db_password: Option<String>,
+    pub server_addr: Option<String>,
+    pub max_connections: Option<u32>,
+    pub min_connections: Option<u32>,
+    pub acquire_timeout_seconds: Option<u64>,
+    pub idle_timeout_seconds: Option<u64>,
+    pub max_lifetime_seconds: Option<u64>,
+    pub test_before_acquire: Option<bool>,
+    pub db_connect_retries: Option<u32>,
+    pub db_connect_retry_delay_ms: Option<u64>,
+    pub jwt_secret: Option<String>,
+    pub log_level: Option<String>,
+    pub skip_migrations: Option<bool>,
+    pub cors_allowed_origins: Option<Vec<String>>,
+    pub avatar_allowed_domains: Option<Vec<String>>,
+    pub rate_limit: FileRateLimit,
+    pub auth_rate_limit: FileRateLimit,
+    pub otel_exporter_otlp_endpoint: Option<String>,
+    pub sentry_dsn: Option<String>,
+    pub batch_max_operations: Option<usize>,
+    pub max_request_body_bytes: Option<usize>,
+    pub prevent_duplicate_titles: Option<bool>,
+    pub admin_name: Option<String>,
+    pub admin_password: Option<String>,
+    pub hsts_max_age: Option<u64>,
+}
+
+// A `[rate_limit]` / `[auth_rate_limit]` table in the config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileRateLimit {
+    pub max_requests: Option<u32>,
+    pub window_seconds: Option<u64>,
+}
+
+impl FileConfig {
+    // None when there is no file at the path, an error when it can't be read or parsed
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        config::Config::builder()
+            .add_source(config::File::from(path))
+            .build()
+            .and_then(|file| file.try_deserialize())
+            .map(Some)
+            .map_err(|e| format!("CONFIG_PATH: {}: {}", path.display(), e))
+    }
+
+    // The file's values under the names of the variables they stand in for
+    fn into_values(self) -> HashMap<&'static str, String> {
+        let entries = [
+            ("DATABASE_URL", self.database_url),
+            ("DB_PASSWORD", self.db_password),
+            ("SERVER_ADDR", self.server_addr),
+            ("DB_MAX_CONNECTIONS", self.max_connections.map(|v| v.to_string())),
+            ("DB_MIN_CONNECTIONS", self.min_connections.map(|v| v.to_string())),
+            ("DB_ACQUIRE_TIMEOUT_SECONDS", self.acquire_timeout_seconds.map(|v| v.to_string())),
+            ("DB_IDLE_TIMEOUT_SECONDS", self.idle_timeout_seconds.map(|v| v.to_string())),
+            ("DB_MAX_LIFETIME_SECONDS", self.max_lifetime_seconds.map(|v| v.to_string())),
+            ("DB_TEST_BEFORE_ACQUIRE", self.test_before_acquire.map(|v| v.to_string())),
+            ("DB_CONNECT_RETRIES", self.db_connect_retries.map(|v| v.to_string())),
+            ("DB_CONNECT_RETRY_DELAY_MS", self.db_connect_retry_delay_ms.map(|v| v.to_string())),
+            ("JWT_SECRET", self.jwt_secret),
+            ("LOG_LEVEL", self.log_level),
+            ("SKIP_MIGRATIONS", self.skip_migrations.map(|v| v.to_string())),
+            ("CORS_ALLOWED_ORIGINS", self.cors_allowed_origins.map(|v| v.join(","))),
+            ("AVATAR_ALLOWED_DOMAINS", self.avatar_allowed_domains.map(|v| v.join(","))),
+            ("RATE_LIMIT_MAX_REQUESTS", self.rate_limit.max_requests.map(|v| v.to_string())),
+            ("RATE_LIMIT_WINDOW_SECONDS", self.rate_limit.window_seconds.map(|v| v.to_string())),
+            ("AUTH_RATE_LIMIT_MAX_REQUESTS", self.auth_rate_limit.max_requests.map(|v| v.to_string())),
+            ("AUTH_RATE_LIMIT_WINDOW_SECONDS", self.auth_rate_limit.window_seconds.map(|v| v.to_string())),
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", self.otel_exporter_otlp_endpoint),
+            ("SENTRY_DSN", self.sentry_dsn),
+            ("BATCH_MAX_OPERATIONS", self.batch_max_operations.map(|v| v.to_string())),
+            ("MAX_REQUEST_BODY_BYTES", self.max_request_body_bytes.map(|v| v.to_string())),
+            ("PREVENT_DUPLICATE_TITLES", self.prevent_duplicate_titles.map(|v| v.to_string())),
+            ("ADMIN_NAME", self.admin_name),
+            ("ADMIN_PASSWORD", self.admin_password),
+            ("STRICT_TRANSPORT_SECURITY_MAX_AGE", self.hsts_max_age.map(|v| v.to_string())),
+        ];
+        entries
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| (key, value)))
+            .collect()
+    }
+}
+
+// Looks a key up in the environment first, then in its secret file, then in the config file
+struct Sources {
+    secrets: HashMap<&'static str, String>,
+    file: HashMap<&'static str, String>,
+}
+
+impl Sources {
+    fn get(&self, key: &str) -> Option<String> {
+        env::var(key)
+            .ok()
+            .or_else(|| self.secrets.get(key).cloned())
+            .or_else(|| self.file.get(key).cloned())
+    }
+}
+
+// The contents of the files named by the `<KEY>_FILE` variables, trimmed. A file that can't be
+// read is a problem; one that is shadowed by the variable itself only a warning.
+fn read_secret_files(problems: &mut Vec<String>, warnings: &mut Vec<String>) -> HashMap<&'static str, String> {
+    let mut secrets = HashMap::new();
+    for &key in FILE_SECRETS {
+        let file_key = format!("{}_FILE", key);
+        let Some(path) = env::var(&file_key).ok().filter(|path| !path.trim().is_empty()) else {
+            continue;
+        };
+        if env::var(key).is_ok() {
+            warnings.push(format!("{} and {} are both set, using {}", key, file_key, key));
+            continue;
+        }
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                secrets.insert(key, contents.trim().to_string());
+            }
+            Err(e) => problems.push(format!("{}: can't read {}: {}", file_key, path, e)),
+        }
+    }
+    secrets
+}
+
+// Every missing or invalid variable found while loading the config
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            writeln!(f, "  ✗ {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl AppConfig {
+    // Read the config file (CONFIG_PATH, or ./config.toml) and the environment, with the
+    // environment winning, then validate everything, reporting every problem at once
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut problems = Vec::new();
+        let mut warnings = Vec::new();
+
+        let path = env::var("CONFIG_PATH")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+        let file = FileConfig::load(&path).unwrap_or_else(|problem| {
+            problems.push(problem);
+            None
+        });
+        let config_file = file.is_some().then_some(path);
+        let sources = Sources {
+            secrets: read_secret_files(&mut problems, &mut warnings),
+            file: file.map(FileConfig::into_values).unwrap_or_default(),
+        };
+
+        let database_url = required(&sources, "DATABASE_URL", &mut problems);
+        let db_password = optional(&sources, "DB_PASSWORD");
+        let server_addr = required(&sources, "SERVER_ADDR", &mut problems);
+        let jwt_secret = required(&sources, "JWT_SECRET", &mut problems);
+        let max_connections = parsed(&sources, "DB_MAX_CONNECTIONS", 10, &mut problems);
+        let min_connections = parsed(&sources, "DB_MIN_CONNECTIONS", 1, &mut problems);
+        let acquire_timeout_seconds: u64 = parsed(&sources, "DB_ACQUIRE_TIMEOUT_SECONDS", 5, &mut problems);
+        // 0 turns the idle timeout and the lifetime limit off
+        let idle_timeout = seconds(parsed(&sources, "DB_IDLE_TIMEOUT_SECONDS", 600, &mut problems));
+        let max_lifetime = seconds(parsed(&sources, "DB_MAX_LIFETIME_SECONDS", 1800, &mut problems));
+        let test_before_acquire = parsed(&sources, "DB_TEST_BEFORE_ACQUIRE", true, &mut problems);
+        let db_connect_retries = parsed(&sources, "DB_CONNECT_RETRIES", 5, &mut problems);
+        let db_connect_retry_delay_ms = parsed(&sources, "DB_CONNECT_RETRY_DELAY_MS", 2000, &mut problems);
+        let log_level = sources.get("LOG_LEVEL").unwrap_or_else(|| "info".to_string());
+        let skip_migrations = parsed(&sources, "SKIP_MIGRATIONS", false, &mut problems);
+        let cors_allowed_origins = list(&sources, "CORS_ALLOWED_ORIGINS", "*");
+        let avatar_allowed_domains = list(&sources, "AVATAR_ALLOWED_DOMAINS", DEFAULT_AVATAR_ALLOWED_DOMAINS);
+        let rate_limit = RateLimit {
+            max_requests: parsed(&sources, "RATE_LIMIT_MAX_REQUESTS", 60, &mut problems),
+            window_seconds: parsed(&sources, "RATE_LIMIT_WINDOW_SECONDS", 60, &mut problems),
+        };
+        let auth_rate_limit = RateLimit {
+            max_requests: parsed(&sources, "AUTH_RATE_LIMIT_MAX_REQUESTS", 5, &mut problems),
+            window_seconds: parsed(&sources, "AUTH_RATE_LIMIT_WINDOW_SECONDS", 60, &mut problems),
+        };
+        let batch_max_operations = parsed(&sources, "BATCH_MAX_OPERATIONS", DEFAULT_BATCH_MAX_OPERATIONS, &mut problems);
+        let mut max_request_body_bytes = parsed(&sources, "MAX_REQUEST_BODY_BYTES", DEFAULT_MAX_BODY_BYTES, &mut problems);
+        let prevent_duplicate_titles = parsed(&sources, "PREVENT_DUPLICATE_TITLES", false, &mut problems);
+        let otel_exporter_otlp_endpoint = optional(&sources, "OTEL_EXPORTER_OTLP_ENDPOINT");
+        let sentry_dsn = optional(&sources, "SENTRY_DSN");
+        let admin_name = optional(&sources, "ADMIN_NAME");
+        let admin_password = optional(&sources, "ADMIN_PASSWORD");
+        let hsts_max_age = optional(&sources, "STRICT_TRANSPORT_SECURITY_MAX_AGE")
+            .map(|_| parsed(&sources, "STRICT_TRANSPORT_SECURITY_MAX_AGE", 0, &mut problems));
+
+        if admin_name.is_some() != admin_password.is_some() {
+            problems.push("ADMIN_NAME and ADMIN_PASSWORD: set both or neither".to_string());
+        }
+
+        if batch_max_operations == 0 {
+            problems.push("BATCH_MAX_OPERATIONS: must be greater than 0".to_string());
+        }
+
+        if max_request_body_bytes == 0 {
+            problems.push("MAX_REQUEST_BODY_BYTES: must be greater than 0".to_string());
+        }
+
+        if max_request_body_bytes > MAX_BODY_BYTES_CAP {
+            warnings.push(format!("MAX_REQUEST_BODY_BYTES: lowered to the maximum of {} bytes", MAX_BODY_BYTES_CAP));
+            max_request_body_bytes = MAX_BODY_BYTES_CAP;
+        }
+
+        if max_connections == 0 {
+            problems.push("DB_MAX_CONNECTIONS: must be greater than 0".to_string());
+        }
+
+        if min_connections > max_connections {
+            problems.push("DB_MIN_CONNECTIONS: must not be greater than DB_MAX_CONNECTIONS".to_string());
+        }
+
+        if db_connect_retries == 0 {
+            problems.push("DB_CONNECT_RETRIES: must be greater than 0".to_string());
+        }
+
+        if acquire_timeout_seconds == 0 {
+            problems.push("DB_ACQUIRE_TIMEOUT_SECONDS: must be greater than 0".to_string());
+        }
+
+        for (key, value) in [
+            ("RATE_LIMIT_MAX_REQUESTS", rate_limit.max_requests as u64),
+            ("RATE_LIMIT_WINDOW_SECONDS", rate_limit.window_seconds),
+            ("AUTH_RATE_LIMIT_MAX_REQUESTS", auth_rate_limit.max_requests as u64),
+            ("AUTH_RATE_LIMIT_WINDOW_SECONDS", auth_rate_limit.window_seconds),
+        ] {
+            if value == 0 {
+                problems.push(format!("{}: must be greater than 0", key));
+            }
+        }
+
+        // Tokens signed with a short secret can be brute-forced offline
+        if !jwt_secret.is_empty() && jwt_secret.len() < MIN_JWT_SECRET_BYTES {
+            problems.push(format!("JWT_SECRET: must be at least {} bytes", MIN_JWT_SECRET_BYTES));
+        }
+
+        if !problems.is_empty() {
+            return Err(ConfigError { problems });
+        }
+
+        Ok(AppConfig {
+            database_url,
+            db_password,
+            server_addr,
+            max_connections,
+            min_connections,
+            acquire_timeout: Duration::from_secs(acquire_timeout_seconds),
+            idle_timeout,
+            max_lifetime,
+            test_before_acquire,
+            db_connect_retries,
+            db_connect_retry_delay: Duration::from_millis(db_connect_retry_delay_ms),
+            jwt_secret,
+            log_level,
+            skip_migrations,
+            cors_allowed_origins,
+            avatar_allowed_domains,
+            rate_limit,
+            auth_rate_limit,
+            otel_exporter_otlp_endpoint,
+            sentry_dsn,
+            batch_max_operations,
+            max_request_body_bytes,
+            prevent_duplicate_titles,
+            admin_name,
+            admin_password,
+            hsts_max_age,
+            config_file,
+            warnings,
+        })
+    }
+}
+
+// A value that must be set and non-empty
+fn required(sources: &Sources, key: &str, problems: &mut Vec<String>) -> String {
+    match sources.get(key) {
+        Some(value) if !value.trim().is_empty() => value,
+        Some(_) => {
+            problems.push(format!("{}: must not be empty", key));
+            String::new()
+        }
+        None => {
+            problems.push(format!("{}: not set", key));
+            String::new()
+        }
+    }
+}
+
+// An optional value, None when unset or empty
+fn optional(sources: &Sources, key: &str) -> Option<String> {
+    sources.get(key).filter(|value| !value.trim().is_empty())
+}
+
+// An optional value parsed into T, falling back to the default when unset
+fn parsed<T: std::str::FromStr>(sources: &Sources, key: &str, default: T, problems: &mut Vec<String>) -> T {
+    match sources.get(key) {
+        Some(value) => value.trim().parse().unwrap_or_else(|_| {
+            problems.push(format!("{}: invalid value '{}'", key, value));
+            default
+        }),
+        None => default,
+    }
+}
+
+// A number of seconds where 0 means "no limit"
+fn seconds(value: u64) -> Option<Duration> {
+    (value > 0).then(|| Duration::from_secs(value))
+}
+
+// A comma-separated list, e.g. "https://a.example,https://b.example"
+fn list(sources: &Sources, key: &str, default: &str) -> Vec<String> {
+    sources
+        .get(key)
+        .unwrap_or_else(|| default.to_string())
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
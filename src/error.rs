@@ -0,0 +1,209 @@
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde::Serialize;
+use std::fmt;
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+
+use crate::middleware::request_id::{current_request_id, current_request_path};
+use crate::models::DependencyTodo;
+use crate::todo_titles::UNIQUE_TITLE_INDEX;
+use crate::validation::{validation_error_response, ValidationErrorResponse};
+
+// Type URIs of the problems this API reports, clients match on these rather than on the text
+pub mod problem_types {
+    pub const BAD_REQUEST: &str = "https://api.example.com/errors/bad-request";
+    pub const UNAUTHORIZED: &str = "https://api.example.com/errors/unauthorized";
+    pub const FORBIDDEN: &str = "https://api.example.com/errors/forbidden";
+    pub const NOT_FOUND: &str = "https://api.example.com/errors/not-found";
+    pub const CONFLICT: &str = "https://api.example.com/errors/conflict";
+    pub const DUPLICATE_TITLE: &str = "https://api.example.com/errors/duplicate-title";
+    pub const PRECONDITION_FAILED: &str = "https://api.example.com/errors/precondition-failed";
+    pub const VALIDATION_FAILED: &str = "https://api.example.com/errors/validation-failed";
+    pub const BLOCKED: &str = "https://api.example.com/errors/blocked";
+    pub const VERSION_CONFLICT: &str = "https://api.example.com/errors/version-conflict";
+    pub const FAILED_DEPENDENCY: &str = "https://api.example.com/errors/failed-dependency";
+    pub const EMAIL_NOT_VERIFIED: &str = "https://api.example.com/errors/email-not-verified";
+    pub const PAYLOAD_TOO_LARGE: &str = "https://api.example.com/errors/payload-too-large";
+    pub const RATE_LIMITED: &str = "https://api.example.com/errors/rate-limited";
+    pub const REQUEST_TIMEOUT: &str = "https://api.example.com/errors/request-timeout";
+    pub const INTERNAL_ERROR: &str = "https://api.example.com/errors/internal-error";
+}
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+// RFC 7807 body returned for every error response, sent as application/problem+json
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    #[schema(format = "uri")]
+    pub problem_type: String, // One of the problem_types URIs
+    pub title: String, // The status text, e.g. "Not Found"
+    pub status: u16,
+    pub detail: String, // What went wrong with this particular request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>, // Path of the request that failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>, // Quote this when reporting a problem
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode, problem_type: &str, detail: &str) -> Self {
+        ProblemDetails {
+            problem_type: problem_type.to_string(),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: detail.to_string(),
+            instance: current_request_path(),
+            request_id: current_request_id(),
+        }
+    }
+
+    pub fn response(&self) -> HttpResponse {
+        problem_response(self.status_code(), self)
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
+// Send a problem, or a body that extends one, with the problem+json content type
+pub fn problem_response<T: Serialize>(status: StatusCode, body: &T) -> HttpResponse {
+    HttpResponse::build(status).content_type(PROBLEM_JSON).json(body)
+}
+
+// Body of the 422 returned when a todo can't be completed yet
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlockedResponse {
+    #[serde(flatten)]
+    pub problem: ProblemDetails,
+    pub blockers: Vec<DependencyTodo>, // The open todos in the way
+}
+
+// The 422 of routes that both validate and complete todos, only used to document either body
+#[derive(ToSchema)]
+#[serde(untagged)]
+pub enum UnprocessableTodoResponse {
+    Blocked(BlockedResponse),
+    Invalid(ValidationErrorResponse),
+}
+
+// Body of the 409 returned when an update carries an outdated version
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VersionConflictResponse {
+    #[serde(flatten)]
+    pub problem: ProblemDetails,
+    pub current_version: i32,
+}
+
+// Application-level error returned by helpers and handlers
+#[derive(Debug)]
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized,
+    Forbidden,
+    EmailNotVerified, // The feature needs a verified email address on the account
+    NotFound(String),
+    Conflict(String),
+    DuplicateTitle, // The user has a live todo with the same title, only with PREVENT_DUPLICATE_TITLES
+    PreconditionFailed(String),
+    ValidationError(ValidationErrors),
+    Blocked(Vec<DependencyTodo>), // Completing a todo whose blockers are still open
+    VersionConflict(i32), // The todo moved past the version the client sent, carries the current one
+    TooManyRequests(u64), // Carries the seconds until the client may try again
+    DatabaseError(sqlx::Error),
+    InternalError(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::BadRequest(message) => write!(f, "{}", message),
+            AppError::Unauthorized => write!(f, "Missing or invalid bearer token"),
+            AppError::Forbidden => write!(f, "You do not have access to this resource"),
+            AppError::EmailNotVerified => write!(f, "Verify your email address to use this feature"),
+            AppError::NotFound(message) => write!(f, "{}", message),
+            AppError::Conflict(message) => write!(f, "{}", message),
+            AppError::DuplicateTitle => write!(f, "You already have a todo with that title"),
+            AppError::PreconditionFailed(message) => write!(f, "{}", message),
+            AppError::ValidationError(errors) => write!(f, "{}", errors),
+            AppError::Blocked(_) => write!(f, "Complete the todos blocking this one first"),
+            AppError::VersionConflict(_) => write!(f, "Todo was changed by someone else, fetch it again"),
+            AppError::TooManyRequests(_) => write!(f, "Too many requests, slow down"),
+            AppError::DatabaseError(e) => write!(f, "Database error: {}", e),
+            AppError::InternalError(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        // Caught here rather than in each handler, any insert or update can set a title
+        if let sqlx::Error::Database(db_error) = &e {
+            if db_error.is_unique_violation() && db_error.constraint() == Some(UNIQUE_TITLE_INDEX) {
+                return AppError::DuplicateTitle;
+            }
+        }
+        AppError::DatabaseError(e)
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::ValidationError(errors)
+    }
+}
+
+impl ResponseError for AppError {
+    fn error_response(&self) -> HttpResponse {
+        let problem = |status, problem_type, detail: &str| ProblemDetails::new(status, problem_type, detail).response();
+
+        match self {
+            AppError::BadRequest(message) => problem(StatusCode::BAD_REQUEST, problem_types::BAD_REQUEST, message),
+            AppError::Unauthorized => problem(StatusCode::UNAUTHORIZED, problem_types::UNAUTHORIZED, &self.to_string()),
+            AppError::Forbidden => problem(StatusCode::FORBIDDEN, problem_types::FORBIDDEN, &self.to_string()),
+            AppError::EmailNotVerified => {
+                problem(StatusCode::FORBIDDEN, problem_types::EMAIL_NOT_VERIFIED, &self.to_string())
+            }
+            AppError::NotFound(message) => problem(StatusCode::NOT_FOUND, problem_types::NOT_FOUND, message),
+            AppError::Conflict(message) => problem(StatusCode::CONFLICT, problem_types::CONFLICT, message),
+            AppError::DuplicateTitle => problem(StatusCode::CONFLICT, problem_types::DUPLICATE_TITLE, &self.to_string()),
+            AppError::PreconditionFailed(message) => {
+                problem(StatusCode::PRECONDITION_FAILED, problem_types::PRECONDITION_FAILED, message)
+            }
+            AppError::ValidationError(errors) => validation_error_response(errors),
+            AppError::Blocked(blockers) => problem_response(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                &BlockedResponse {
+                    problem: ProblemDetails::new(StatusCode::UNPROCESSABLE_ENTITY, problem_types::BLOCKED, &self.to_string()),
+                    blockers: blockers.clone(),
+                },
+            ),
+            AppError::VersionConflict(current_version) => problem_response(
+                StatusCode::CONFLICT,
+                &VersionConflictResponse {
+                    problem: ProblemDetails::new(StatusCode::CONFLICT, problem_types::VERSION_CONFLICT, &self.to_string()),
+                    current_version: *current_version,
+                },
+            ),
+            AppError::TooManyRequests(retry_after) => {
+                let mut response = problem(StatusCode::TOO_MANY_REQUESTS, problem_types::RATE_LIMITED, &self.to_string());
+                response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(*retry_after));
+                response
+            }
+            // The driver message can leak schema details, so it only goes to the log
+            AppError::DatabaseError(e) => {
+                tracing::error!("Database error: {:?}", e);
+                sentry::capture_error(e);
+                problem(StatusCode::INTERNAL_SERVER_ERROR, problem_types::INTERNAL_ERROR, "Database error")
+            }
+            AppError::InternalError(message) => {
+                tracing::error!("Internal error: {}", message);
+                sentry::capture_message(message, sentry::Level::Error);
+                problem(StatusCode::INTERNAL_SERVER_ERROR, problem_types::INTERNAL_ERROR, message)
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use actix_web::{web, HttpRequest};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+// How many events a slow stream may fall behind before it starts missing some
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+// A change to a todo, sent to the owner's GET /todos/events streams
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoEvent {
+    #[serde(skip)]
+    pub user_id: i32, // Whose streams get the event
+    #[serde(rename = "type")]
+    pub kind: TodoEventKind,
+    pub todo_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub todo: Option<Value>, // The todo after the change, absent for deletes
+}
+
+pub fn channel() -> broadcast::Sender<TodoEvent> {
+    broadcast::channel(CHANNEL_CAPACITY).0
+}
+
+// Send the event to whoever is listening; nothing happens when the app has no channel or no listeners
+pub fn publish<T: Serialize>(req: &HttpRequest, user_id: i32, kind: TodoEventKind, todo_id: i32, todo: Option<&T>) {
+    let Some(sender) = req.app_data::<web::Data<broadcast::Sender<TodoEvent>>>() else {
+        return;
+    };
+    let todo = todo.and_then(|todo| serde_json::to_value(todo).ok());
+    let _ = sender.send(TodoEvent { user_id, kind, todo_id, todo });
+}
@@ -0,0 +1,100 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::handlers::TOTAL_COUNT_HEADER;
+use crate::models::{ActivityAction, ActivityEntry, ActivityPage, ActivityQuery, Role};
+
+const DEFAULT_LIMIT: u32 = 20;
+const MAX_LIMIT: u32 = 100;
+
+// Handler for a user's activity feed, newest first: what they did plus what collaborators did
+// on todos they own or that are shared with them. Only the user themselves or an admin may read it.
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/activity",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id"), ActivityQuery),
+    responses(
+        (status = 200, description = "A page of the feed", body = ActivityPage),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_activity(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    query: web::Query<ActivityQuery>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.role != Role::Admin && auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM "Users" WHERE id = $1) AS "exists!""#,
+        user_id
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    // Same snapshot for the page and the count, like the todo lists
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let items = sqlx::query_as!(
+        ActivityEntry,
+        r#"SELECT id, user_id, action AS "action: ActivityAction", entity_type, entity_id, meta, occurred_at
+           FROM activity_log
+           WHERE (user_id = $1
+                  OR (entity_type = 'todo' AND entity_id IN (
+                      SELECT id FROM todos WHERE user_id = $1
+                      UNION
+                      SELECT todo_id FROM todo_shares WHERE shared_with_user_id = $1)))
+             AND ($2::INT IS NULL OR id < $2)
+           ORDER BY id DESC
+           LIMIT $3"#,
+        user_id,
+        query.before_id,
+        limit as i64
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+
+    // The whole feed, not just what's left after the cursor
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!"
+           FROM activity_log
+           WHERE user_id = $1
+              OR (entity_type = 'todo' AND entity_id IN (
+                  SELECT id FROM todos WHERE user_id = $1
+                  UNION
+                  SELECT todo_id FROM todo_shares WHERE shared_with_user_id = $1))"#,
+        user_id
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    // A full page may have more behind it
+    let next_cursor = if items.len() == limit as usize {
+        items.last().map(|entry| entry.id)
+    } else {
+        None
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header((TOTAL_COUNT_HEADER, total.to_string()))
+        .json(ActivityPage { items, next_cursor }))
+}
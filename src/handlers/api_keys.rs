@@ -0,0 +1,109 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::auth::api_key::{generate_api_key, API_KEY_PREFIX_LEN};
+use crate::auth::password::hash_password;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{ApiKey, CreatedApiKey, NewApiKey};
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// Handler for listing the caller's API keys, without the keys themselves
+#[utoipa::path(
+    get,
+    path = "/api-keys",
+    tag = "auth",
+    responses(
+        (status = 200, description = "The caller's API keys", body = Vec<ApiKey>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_api_keys(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let keys = sqlx::query_as!(
+        ApiKey,
+        "SELECT id, key_prefix, description, last_used_at, expires_at, created_at
+         FROM api_keys WHERE user_id = $1 ORDER BY id",
+        auth.user_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+// Handler for creating an API key. The key is in this response only, just its hash is kept.
+#[utoipa::path(
+    post,
+    path = "/api-keys",
+    tag = "auth",
+    request_body = NewApiKey,
+    responses(
+        (status = 201, description = "The created key, shown this once", body = CreatedApiKey),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_api_key(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    new_key: web::Json<NewApiKey>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_key)?;
+
+    let key = generate_api_key();
+
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        "INSERT INTO api_keys (user_id, key_prefix, key_hash, description, expires_at)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id, key_prefix, description, last_used_at, expires_at, created_at",
+        auth.user_id,
+        &key[..API_KEY_PREFIX_LEN],
+        hash_password(&key)?,
+        new_key.description,
+        new_key.expires_at
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Created().json(CreatedApiKey { api_key, key }))
+}
+
+// Handler for revoking one of the caller's API keys
+#[utoipa::path(
+    delete,
+    path = "/api-keys/{key_id}",
+    tag = "auth",
+    params(("key_id" = i32, Path, description = "API key id")),
+    responses(
+        (status = 204, description = "API key deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "API key not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_api_key(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    key_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let result = sqlx::query!(
+        "DELETE FROM api_keys WHERE id = $1 AND user_id = $2",
+        key_id.into_inner(),
+        auth.user_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    // Someone else's key is reported the same as a missing one
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("API key not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
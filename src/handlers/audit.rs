@@ -0,0 +1,65 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::auth::AdminGuard;
+use crate::error::{AppError, ProblemDetails};
+use crate::handlers::TOTAL_COUNT_HEADER;
+use crate::models::{AuditEntry, AuditLogQuery};
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 500;
+
+// Handler for reading the audit log, newest first (admins only)
+#[utoipa::path(
+    get,
+    path = "/admin/audit-log",
+    tag = "admin",
+    params(AuditLogQuery),
+    responses(
+        (status = 200, description = "The matching changes", body = Vec<AuditEntry>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_audit_log(
+    _admin: AdminGuard,
+    pool: web::Data<PgPool>,
+    query: web::Query<AuditLogQuery>,
+) -> Result<HttpResponse, AppError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    // Same snapshot for the page and the count, like the todo lists
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let entries = sqlx::query_as!(
+        AuditEntry,
+        "SELECT id, table_name, row_id, action, actor_user_id, actor_ip, old_data, new_data, meta, occurred_at
+         FROM audit_log
+         WHERE ($1::TEXT IS NULL OR table_name = $1) AND ($2::INT IS NULL OR row_id = $2)
+         ORDER BY id DESC
+         LIMIT $3",
+        query.table.as_deref(),
+        query.row_id,
+        limit as i64
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM audit_log
+           WHERE ($1::TEXT IS NULL OR table_name = $1) AND ($2::INT IS NULL OR row_id = $2)"#,
+        query.table.as_deref(),
+        query.row_id
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((TOTAL_COUNT_HEADER, total.to_string()))
+        .json(entries))
+}
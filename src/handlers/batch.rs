@@ -0,0 +1,267 @@
+use actix_web::body::to_bytes;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::{Connection, PgConnection, PgPool};
+
+use super::todos::{check_todo_access, fetch_todo_response, insert_todo, trash_todo, write_todo_update};
+use crate::audit::set_audit_context;
+use crate::auth::AuthUser;
+use crate::config::{AppConfig, DEFAULT_BATCH_MAX_OPERATIONS};
+use crate::error::{problem_types, AppError, ProblemDetails};
+use crate::events::{self, TodoEvent, TodoEventKind};
+use crate::metrics::{TODOS_CREATED_TOTAL, TODOS_DELETED_TOTAL};
+use crate::models::{BatchOperation, BatchReq, BatchResponse, BatchResult, NewTodo, UpdateTaskReq};
+use crate::todo_cache::invalidate_todo;
+use crate::validation::validate_input;
+use crate::webhooks::{self, WebhookEvent};
+
+// The routes a batch may call. Anything else is refused before a single operation runs,
+// so a batch can never be pointed at other paths or hosts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TodoOperation {
+    Create,      // POST /todos
+    Update(i32), // PATCH /todos/{todo_id}
+    Delete(i32), // DELETE /todos/{todo_id}
+}
+
+impl TodoOperation {
+    fn parse(operation: &BatchOperation) -> Result<Self, String> {
+        let unsupported = || format!("Unsupported operation {} {}", operation.method, operation.path);
+
+        let segments: Vec<&str> = operation
+            .path
+            .strip_prefix('/')
+            .ok_or_else(unsupported)?
+            .split('/')
+            .collect();
+        let method = operation.method.to_ascii_uppercase();
+
+        match (method.as_str(), segments.as_slice()) {
+            ("POST", ["todos"]) => Ok(TodoOperation::Create),
+            (method @ ("PATCH" | "DELETE"), ["todos", id]) if id.bytes().all(|b| b.is_ascii_digit()) => {
+                let id = id.parse().map_err(|_| unsupported())?;
+                Ok(if method == "PATCH" { TodoOperation::Update(id) } else { TodoOperation::Delete(id) })
+            }
+            _ => Err(unsupported()),
+        }
+    }
+}
+
+fn operation_body<T: DeserializeOwned>(body: &Option<Value>) -> Result<T, AppError> {
+    let body = body
+        .clone()
+        .ok_or_else(|| AppError::BadRequest("Operation needs a body".to_string()))?;
+    serde_json::from_value(body).map_err(|e| AppError::BadRequest(format!("Invalid operation body: {}", e)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Value, AppError> {
+    serde_json::to_value(value).map_err(|e| AppError::InternalError(e.to_string()))
+}
+
+// Run one operation on the connection, the caller decides where the transaction ends.
+// Webhook events are collected in `events` and stream events in `changes`, to be sent once
+// the change is committed.
+async fn run_operation(
+    conn: &mut PgConnection,
+    user_id: i32,
+    route: TodoOperation,
+    body: &Option<Value>,
+    events: &mut Vec<WebhookEvent>,
+    changes: &mut Vec<TodoEvent>,
+) -> Result<BatchResult, AppError> {
+    match route {
+        TodoOperation::Create => {
+            let new_todo: NewTodo = operation_body(body)?;
+            validate_input(&new_todo)?;
+
+            let todo_id = insert_todo(conn, user_id, &new_todo).await?;
+            let todo = fetch_todo_response(conn, todo_id)
+                .await?
+                .ok_or_else(|| AppError::InternalError("Created todo could not be read back".to_string()))?;
+
+            let todo = to_json(&todo)?;
+            events.push(WebhookEvent { user_id, event: "todo.created", data: todo.clone() });
+            changes.push(TodoEvent { user_id, kind: TodoEventKind::Created, todo_id, todo: Some(todo.clone()) });
+
+            Ok(BatchResult { status: StatusCode::CREATED.as_u16(), body: Some(todo) })
+        }
+        TodoOperation::Update(todo_id) => {
+            let update: UpdateTaskReq = operation_body(body)?;
+            validate_input(&update)?;
+
+            let owner_id = check_todo_access(&mut *conn, todo_id, user_id, true).await?;
+            let (todo, completed_now) = write_todo_update(conn, todo_id, owner_id, user_id, &update).await?;
+
+            let todo = to_json(&todo)?;
+            events.push(WebhookEvent { user_id: owner_id, event: "todo.updated", data: todo.clone() });
+            if completed_now {
+                events.push(WebhookEvent { user_id: owner_id, event: "todo.completed", data: todo.clone() });
+            }
+            changes.push(TodoEvent {
+                user_id: owner_id,
+                kind: TodoEventKind::Updated,
+                todo_id,
+                todo: Some(todo.clone()),
+            });
+
+            Ok(BatchResult { status: StatusCode::OK.as_u16(), body: Some(todo) })
+        }
+        TodoOperation::Delete(todo_id) => {
+            trash_todo(conn, todo_id, user_id).await?;
+            changes.push(TodoEvent { user_id, kind: TodoEventKind::Deleted, todo_id, todo: None });
+
+            Ok(BatchResult { status: StatusCode::NO_CONTENT.as_u16(), body: None })
+        }
+    }
+}
+
+// The error as the route itself would have answered it
+async fn error_result(error: AppError) -> BatchResult {
+    let response = error.error_response();
+    let status = response.status().as_u16();
+    let body = to_bytes(response.into_body())
+        .await
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+
+    BatchResult { status, body }
+}
+
+fn count_changes(routes: &[TodoOperation], results: &[BatchResult]) {
+    for (route, result) in routes.iter().zip(results) {
+        match route {
+            TodoOperation::Create if result.status == StatusCode::CREATED.as_u16() => TODOS_CREATED_TOTAL.inc(),
+            TodoOperation::Delete(_) if result.status == StatusCode::NO_CONTENT.as_u16() => TODOS_DELETED_TOTAL.inc(),
+            _ => {}
+        }
+    }
+}
+
+// Handler for applying several todo changes in one request, for clients syncing after being offline.
+// Operations run in order; the response has one result per operation with the status and body
+// the single route would have returned. When an atomic batch fails the failed operation keeps
+// its error and every other one is reported as 424, since nothing was applied.
+#[utoipa::path(
+    post,
+    path = "/batch",
+    tag = "todos",
+    request_body = BatchReq,
+    responses(
+        (status = 200, description = "Result of every operation", body = BatchResponse),
+        (status = 400, description = "Too many operations or an unsupported method or path", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 422, description = "Atomic batch rolled back, see the failed operation", body = BatchResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn batch(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    batch: web::Json<BatchReq>,
+) -> Result<HttpResponse, AppError> {
+    let max_operations = req
+        .app_data::<web::Data<AppConfig>>()
+        .map_or(DEFAULT_BATCH_MAX_OPERATIONS, |config| config.batch_max_operations);
+    if batch.operations.len() > max_operations {
+        return Err(AppError::BadRequest(format!("A batch can have at most {} operations", max_operations)));
+    }
+
+    let routes = batch
+        .operations
+        .iter()
+        .map(TodoOperation::parse)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(AppError::BadRequest)?;
+
+    let mut conn = pool.acquire().await?;
+    let mut results = Vec::with_capacity(routes.len());
+    let mut events = Vec::new();
+    let mut changes = Vec::new();
+
+    if batch.atomic {
+        let mut tx = conn.begin().await?;
+        set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+        for (index, (route, operation)) in routes.iter().zip(&batch.operations).enumerate() {
+            match run_operation(&mut tx, auth.user_id, *route, &operation.body, &mut events, &mut changes).await {
+                Ok(result) => results.push(result),
+                Err(error) => {
+                    tx.rollback().await?;
+
+                    let failed = error_result(error).await;
+                    let skipped = json!(ProblemDetails::new(
+                        StatusCode::FAILED_DEPENDENCY,
+                        problem_types::FAILED_DEPENDENCY,
+                        &format!("Not applied, operation {} failed", index)
+                    ));
+                    let results = (0..routes.len())
+                        .map(|i| {
+                            if i == index {
+                                BatchResult { status: failed.status, body: failed.body.clone() }
+                            } else {
+                                BatchResult {
+                                    status: StatusCode::FAILED_DEPENDENCY.as_u16(),
+                                    body: Some(skipped.clone()),
+                                }
+                            }
+                        })
+                        .collect();
+
+                    return Ok(HttpResponse::UnprocessableEntity().json(BatchResponse { results }));
+                }
+            }
+        }
+
+        tx.commit().await?;
+    } else {
+        for (route, operation) in routes.iter().zip(&batch.operations) {
+            let mut tx = conn.begin().await?;
+            set_audit_context(&mut tx, auth.user_id, &req).await?;
+            let mut operation_events = Vec::new();
+            let mut operation_changes = Vec::new();
+            let result = match run_operation(
+                &mut tx,
+                auth.user_id,
+                *route,
+                &operation.body,
+                &mut operation_events,
+                &mut operation_changes,
+            )
+            .await
+            {
+                Ok(result) => {
+                    tx.commit().await?;
+                    events.append(&mut operation_events);
+                    changes.append(&mut operation_changes);
+                    result
+                }
+                Err(error) => {
+                    tx.rollback().await?;
+                    error_result(error).await
+                }
+            };
+            results.push(result);
+        }
+    }
+
+    count_changes(&routes, &results);
+    for (route, result) in routes.iter().zip(&results) {
+        if let TodoOperation::Update(todo_id) | TodoOperation::Delete(todo_id) = route {
+            if StatusCode::from_u16(result.status).is_ok_and(|status| status.is_success()) {
+                invalidate_todo(&req, *todo_id).await;
+            }
+        }
+    }
+    for event in events {
+        webhooks::dispatch(pool.get_ref(), event.user_id, event.event, &event.data);
+    }
+    for change in changes {
+        events::publish(&req, change.user_id, change.kind, change.todo_id, change.todo.as_ref());
+    }
+
+    Ok(HttpResponse::Ok().json(BatchResponse { results }))
+}
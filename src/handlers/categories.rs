@@ -0,0 +1,295 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::{PgConnection, PgPool};
+
+use super::todos::TAG_NAMES_COLUMN;
+use super::TOTAL_COUNT_HEADER;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{CategoryResponse, NewCategory, Todo, UpdateCategoryReq};
+use crate::todo_cache::invalidate_all_todos;
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// One category of the caller's, 404 for anyone else's
+async fn fetch_category(conn: &mut PgConnection, category_id: i32, user_id: i32) -> Result<CategoryResponse, AppError> {
+    sqlx::query_as!(
+        CategoryResponse,
+        "SELECT id, name, parent_id, color FROM categories WHERE id = $1 AND user_id = $2",
+        category_id,
+        user_id
+    )
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Category not found".to_string()))
+}
+
+// A parent has to be one of the caller's categories (400 otherwise)
+async fn ensure_own_parent(conn: &mut PgConnection, parent_id: i32, user_id: i32) -> Result<(), AppError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM categories WHERE id = $1 AND user_id = $2) AS "exists!""#,
+        parent_id,
+        user_id
+    )
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if !exists {
+        return Err(AppError::BadRequest("Unknown parent category id".to_string()));
+    }
+
+    Ok(())
+}
+
+// Handler for listing the caller's categories, the tree is rebuilt from parent_id
+#[utoipa::path(
+    get,
+    path = "/categories",
+    tag = "categories",
+    responses(
+        (status = 200, description = "The caller's categories", body = Vec<CategoryResponse>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_categories(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let categories = sqlx::query_as!(
+        CategoryResponse,
+        "SELECT id, name, parent_id, color FROM categories WHERE user_id = $1 ORDER BY name, id",
+        auth.user_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+// Handler for creating a category, top-level or under one of the caller's categories
+#[utoipa::path(
+    post,
+    path = "/categories",
+    tag = "categories",
+    request_body = NewCategory,
+    responses(
+        (status = 201, description = "The created category", body = CategoryResponse),
+        (status = 400, description = "Unknown parent category", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_category(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    new_category: web::Json<NewCategory>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_category)?;
+
+    let mut conn = pool.acquire().await?;
+    if let Some(parent_id) = new_category.parent_id {
+        ensure_own_parent(&mut conn, parent_id, auth.user_id).await?;
+    }
+
+    let category = sqlx::query_as!(
+        CategoryResponse,
+        "INSERT INTO categories (user_id, name, parent_id, color) VALUES ($1, $2, $3, $4)
+         RETURNING id, name, parent_id, color",
+        auth.user_id,
+        new_category.name,
+        new_category.parent_id,
+        new_category.color
+    )
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(HttpResponse::Created().json(category))
+}
+
+// Handler for one of the caller's categories
+#[utoipa::path(
+    get,
+    path = "/categories/{category_id}",
+    tag = "categories",
+    params(("category_id" = i32, Path, description = "Category id")),
+    responses(
+        (status = 200, description = "The category", body = CategoryResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Category not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_category(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    category_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let mut conn = pool.acquire().await?;
+    let category = fetch_category(&mut conn, category_id.into_inner(), auth.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+// Handler for renaming, recolouring or moving a category. Fields left out are kept, a null
+// parent_id makes it top-level. It can't be moved under itself or one of its descendants.
+#[utoipa::path(
+    patch,
+    path = "/categories/{category_id}",
+    tag = "categories",
+    params(("category_id" = i32, Path, description = "Category id")),
+    request_body = UpdateCategoryReq,
+    responses(
+        (status = 200, description = "The updated category", body = CategoryResponse),
+        (status = 400, description = "Unknown parent, or the move would make a cycle", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Category not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_category(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    category_id: web::Path<i32>,
+    category_data: web::Json<UpdateCategoryReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*category_data)?;
+    let category_id = category_id.into_inner();
+
+    let mut tx = pool.begin().await?;
+
+    // Locking the caller's whole tree keeps two concurrent moves from making a cycle together
+    sqlx::query!("SELECT id FROM categories WHERE user_id = $1 FOR UPDATE", auth.user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let current = fetch_category(&mut tx, category_id, auth.user_id).await?;
+
+    if let Some(Some(parent_id)) = category_data.parent_id {
+        ensure_own_parent(&mut tx, parent_id, auth.user_id).await?;
+
+        let cycle = sqlx::query_scalar!(
+            r#"WITH RECURSIVE subtree AS (
+                   SELECT id FROM categories WHERE id = $1
+                   UNION
+                   SELECT categories.id FROM categories JOIN subtree ON categories.parent_id = subtree.id
+               )
+               SELECT EXISTS (SELECT 1 FROM subtree WHERE id = $2) AS "cycle!""#,
+            category_id,
+            parent_id
+        )
+            .fetch_one(&mut *tx)
+            .await?;
+        if cycle {
+            return Err(AppError::BadRequest(
+                "A category can't be moved under itself or one of its descendants".to_string(),
+            ));
+        }
+    }
+
+    let category = sqlx::query_as!(
+        CategoryResponse,
+        "UPDATE categories SET name = $1, parent_id = $2, color = $3 WHERE id = $4
+         RETURNING id, name, parent_id, color",
+        category_data.name.as_deref().unwrap_or(&current.name),
+        category_data.parent_id.unwrap_or(current.parent_id),
+        category_data.color.clone().unwrap_or(current.color),
+        category_id
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    // Every todo filed here embeds the category, so cached copies go stale
+    invalidate_all_todos(&req);
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+// Handler for removing a category. Its children move up to its parent and its todos are left
+// without a category.
+#[utoipa::path(
+    delete,
+    path = "/categories/{category_id}",
+    tag = "categories",
+    params(("category_id" = i32, Path, description = "Category id")),
+    responses(
+        (status = 204, description = "Category deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Category not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_category(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    category_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let category_id = category_id.into_inner();
+
+    let mut tx = pool.begin().await?;
+    let category = fetch_category(&mut tx, category_id, auth.user_id).await?;
+
+    sqlx::query!(
+        "UPDATE categories SET parent_id = $1 WHERE parent_id = $2",
+        category.parent_id,
+        category_id
+    )
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!("DELETE FROM categories WHERE id = $1", category_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    invalidate_all_todos(&req);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler for the caller's live todos in a category or any category below it
+#[utoipa::path(
+    get,
+    path = "/categories/{category_id}/todos",
+    tag = "categories",
+    params(("category_id" = i32, Path, description = "Category id")),
+    responses(
+        (status = 200, description = "The todos, in their manual order", body = Vec<Todo>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Category not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_category_todos(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    category_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let category_id = category_id.into_inner();
+
+    let mut conn = pool.acquire().await?;
+    fetch_category(&mut conn, category_id, auth.user_id).await?;
+
+    // UNION rather than UNION ALL, so a cycle in the data can't make the walk endless
+    let todos = sqlx::query_as::<_, Todo>(&format!(
+        "WITH RECURSIVE subtree AS (
+             SELECT id FROM categories WHERE id = $1
+             UNION
+             SELECT categories.id FROM categories JOIN subtree ON categories.parent_id = subtree.id
+         )
+         SELECT *, {}, false AS shared FROM todos
+         WHERE user_id = $2 AND category_id IN (SELECT id FROM subtree) AND deleted_at IS NULL
+         ORDER BY position, id",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(category_id)
+        .bind(auth.user_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    // Not paged, so the list is the whole count
+    Ok(HttpResponse::Ok()
+        .insert_header((TOTAL_COUNT_HEADER, todos.len().to_string()))
+        .json(todos))
+}
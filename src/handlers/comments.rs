@@ -0,0 +1,194 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::todos::check_todo_owner;
+use crate::activity::record_todo_activity;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{ActivityAction, Comment, CommentReq, Role};
+use crate::todo_cache::invalidate_todo;
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// Handler for listing the comments on a todo, oldest first
+#[utoipa::path(
+    get,
+    path = "/todos/{todo_id}/comments",
+    tag = "comments",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The todo's comments, oldest first", body = Vec<Comment>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_comments(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let comments = sqlx::query_as!(
+        Comment,
+        "SELECT id, todo_id, user_id, body, created_at FROM comments
+         WHERE todo_id = $1 ORDER BY created_at, id",
+        todo_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(comments))
+}
+
+// Handler for commenting on a todo
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/comments",
+    tag = "comments",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = CommentReq,
+    responses(
+        (status = 201, description = "The created comment", body = Comment),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_comment(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    new_comment: web::Json<CommentReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_comment)?;
+
+    let todo_id = todo_id.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let comment = sqlx::query_as!(
+        Comment,
+        "INSERT INTO comments (todo_id, user_id, body) VALUES ($1, $2, $3)
+         RETURNING id, todo_id, user_id, body, created_at",
+        todo_id,
+        auth.user_id,
+        new_comment.body
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    let meta = json!({ "comment_id": comment.id });
+    record_todo_activity(pool.get_ref(), auth.user_id, ActivityAction::Commented, todo_id, meta).await?;
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::Created().json(comment))
+}
+
+// Author of a comment on the given todo, 404 when there is no such comment
+async fn comment_author(pool: &PgPool, todo_id: i32, comment_id: i32) -> Result<i32, AppError> {
+    sqlx::query_scalar!(
+        "SELECT user_id FROM comments WHERE id = $1 AND todo_id = $2",
+        comment_id,
+        todo_id
+    )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Comment not found".to_string()))
+}
+
+// Handler for editing a comment, only its author may
+#[utoipa::path(
+    patch,
+    path = "/todos/{todo_id}/comments/{comment_id}",
+    tag = "comments",
+    params(("todo_id" = i32, Path, description = "Todo id"), ("comment_id" = i32, Path, description = "Comment id")),
+    request_body = CommentReq,
+    responses(
+        (status = 200, description = "The updated comment", body = Comment),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or comment not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_comment(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, i32)>,
+    comment_data: web::Json<CommentReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*comment_data)?;
+
+    let (todo_id, comment_id) = path.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    if comment_author(pool.get_ref(), todo_id, comment_id).await? != auth.user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let comment = sqlx::query_as!(
+        Comment,
+        "UPDATE comments SET body = $1 WHERE id = $2 AND todo_id = $3
+         RETURNING id, todo_id, user_id, body, created_at",
+        comment_data.body,
+        comment_id,
+        todo_id
+    )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Comment not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(comment))
+}
+
+// Handler for removing a comment, its author or an admin may
+#[utoipa::path(
+    delete,
+    path = "/todos/{todo_id}/comments/{comment_id}",
+    tag = "comments",
+    params(("todo_id" = i32, Path, description = "Todo id"), ("comment_id" = i32, Path, description = "Comment id")),
+    responses(
+        (status = 204, description = "Comment deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or comment not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_comment(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (todo_id, comment_id) = path.into_inner();
+    if auth.role != Role::Admin {
+        check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+        if comment_author(pool.get_ref(), todo_id, comment_id).await? != auth.user_id {
+            return Err(AppError::Forbidden);
+        }
+    }
+
+    let result = sqlx::query!(
+        "DELETE FROM comments WHERE id = $1 AND todo_id = $2",
+        comment_id,
+        todo_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Comment not found".to_string()));
+    }
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
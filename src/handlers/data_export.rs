@@ -0,0 +1,147 @@
+use actix_web::http::header::CONTENT_DISPOSITION;
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use sqlx::PgPool;
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::todos::TAG_NAMES_COLUMN;
+use crate::audit::set_audit_context;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{ActivityAction, ActivityEntry, Comment, Role, Todo};
+
+// How often a user's data can be exported, building the archive reads everything they have
+const EXPORT_INTERVAL_SECONDS: i64 = 60 * 60;
+
+const README: &str = "\
+Export of everything stored for your account.
+
+profile.json   Your account: id, name, role, email and whether it is verified.
+               The password is never exported.
+todos.json     Every todo you own, including the ones in the trash (deleted_at is set).
+comments.json  Every comment you wrote, on your todos and on todos shared with you.
+activity.json  Everything you did, as recorded in your activity feed.
+
+All files are UTF-8 encoded JSON.
+";
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec_pretty(value).map_err(|e| AppError::InternalError(e.to_string()))
+}
+
+// The archive with one entry per (name, contents)
+fn build_zip(files: &[(&str, Vec<u8>)]) -> Result<Vec<u8>, AppError> {
+    let zip_error = |e: zip::result::ZipError| AppError::InternalError(format!("Failed to build the archive: {}", e));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, contents) in files {
+        zip.start_file(*name, options).map_err(zip_error)?;
+        zip.write_all(contents).map_err(|e| AppError::InternalError(e.to_string()))?;
+    }
+    Ok(zip.finish().map_err(zip_error)?.into_inner())
+}
+
+// Handler for downloading all of a user's data as a ZIP of JSON files (data portability).
+// Only the user themselves or an admin, and once an hour per user.
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/export",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "ZIP with profile.json, todos.json, comments.json, activity.json and README.txt", content_type = "application/zip", body = Vec<u8>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 429, description = "Exported less than an hour ago, see Retry-After", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn export_user_data(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.role != Role::Admin && auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    let profile = sqlx::query!(
+        r#"SELECT id, name, role AS "role: Role", email, email_verified, last_exported_at
+           FROM "Users" WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
+        user_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    // The row lock makes concurrent exports wait here and see each other's timestamp
+    if let Some(last_exported_at) = profile.last_exported_at {
+        let elapsed = (Utc::now() - last_exported_at).num_seconds();
+        if elapsed < EXPORT_INTERVAL_SECONDS {
+            return Err(AppError::TooManyRequests((EXPORT_INTERVAL_SECONDS - elapsed) as u64));
+        }
+    }
+
+    let todos = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT *, {}, false AS shared FROM todos WHERE user_id = $1 ORDER BY id",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    let comments = sqlx::query_as!(
+        Comment,
+        "SELECT id, todo_id, user_id, body, created_at FROM comments WHERE user_id = $1 ORDER BY id",
+        user_id
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+    let activity = sqlx::query_as!(
+        ActivityEntry,
+        r#"SELECT id, user_id, action AS "action: ActivityAction", entity_type, entity_id, meta, occurred_at
+           FROM activity_log WHERE user_id = $1 ORDER BY id"#,
+        user_id
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+
+    sqlx::query!(r#"UPDATE "Users" SET last_exported_at = NOW() WHERE id = $1"#, user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let archive = build_zip(&[
+        ("README.txt", README.as_bytes().to_vec()),
+        (
+            "profile.json",
+            to_json(&json!({
+                "id": profile.id,
+                "name": profile.name,
+                "role": profile.role,
+                "email": profile.email,
+                "email_verified": profile.email_verified,
+            }))?,
+        ),
+        ("todos.json", to_json(&todos)?),
+        ("comments.json", to_json(&comments)?),
+        ("activity.json", to_json(&activity)?),
+    ])?;
+
+    // Only counts as an export once the archive could be built
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((CONTENT_DISPOSITION, format!("attachment; filename=\"user-{}-export.zip\"", user_id)))
+        .body(archive))
+}
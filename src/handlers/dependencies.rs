@@ -0,0 +1,198 @@
+use actix_web::{web, HttpResponse};
+use sqlx::{PgConnection, PgPool};
+
+use super::todos::check_todo_access;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{Dependencies, DependencyReq, DependencyTodo};
+
+// Fail with AppError::Blocked when any of the todos still waits for an open, live blocker
+pub(crate) async fn ensure_unblocked(conn: &mut PgConnection, todo_ids: &[i32]) -> Result<(), AppError> {
+    let blockers = sqlx::query_as!(
+        DependencyTodo,
+        "SELECT DISTINCT todos.id, todos.title, todos.completed
+         FROM todo_dependencies
+         JOIN todos ON todos.id = todo_dependencies.blocker_id
+         WHERE todo_dependencies.blocked_id = ANY($1) AND todos.completed = false AND todos.deleted_at IS NULL
+         ORDER BY todos.id",
+        todo_ids
+    )
+        .fetch_all(&mut *conn)
+        .await?;
+
+    if blockers.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Blocked(blockers))
+    }
+}
+
+// Handler for listing what a todo waits for and what waits for it
+#[utoipa::path(
+    get,
+    path = "/todos/{todo_id}/dependencies",
+    tag = "dependencies",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "Blockers and blocked todos", body = Dependencies),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_dependencies(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, false).await?;
+
+    Ok(HttpResponse::Ok().json(load_dependencies(pool.get_ref(), todo_id).await?))
+}
+
+// The live todos on both sides of the todo's dependencies
+async fn load_dependencies(pool: &PgPool, todo_id: i32) -> Result<Dependencies, AppError> {
+    let blockers = sqlx::query_as!(
+        DependencyTodo,
+        "SELECT todos.id, todos.title, todos.completed
+         FROM todo_dependencies JOIN todos ON todos.id = todo_dependencies.blocker_id
+         WHERE todo_dependencies.blocked_id = $1 AND todos.deleted_at IS NULL
+         ORDER BY todos.id",
+        todo_id
+    )
+        .fetch_all(pool)
+        .await?;
+    let blocked = sqlx::query_as!(
+        DependencyTodo,
+        "SELECT todos.id, todos.title, todos.completed
+         FROM todo_dependencies JOIN todos ON todos.id = todo_dependencies.blocked_id
+         WHERE todo_dependencies.blocker_id = $1 AND todos.deleted_at IS NULL
+         ORDER BY todos.id",
+        todo_id
+    )
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Dependencies { blockers, blocked })
+}
+
+// Handler for making a todo wait for another todo of the same owner
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/dependencies",
+    tag = "dependencies",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = DependencyReq,
+    responses(
+        (status = 201, description = "The todo's blockers and blocked todos", body = Dependencies),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 409, description = "Already a dependency, or it would close a cycle", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn add_dependency(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    dependency: web::Json<DependencyReq>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let blocker_id = dependency.blocker_id;
+    if blocker_id == todo_id {
+        return Err(AppError::BadRequest("A todo can't block itself".to_string()));
+    }
+
+    let owner_id = check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+    if check_todo_access(pool.get_ref(), blocker_id, auth.user_id, false).await? != owner_id {
+        return Err(AppError::BadRequest("Both todos must belong to the same user".to_string()));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Only one dependency change at a time per owner, so two requests can't close a cycle together
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext('todo_dependencies'), $1)", owner_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // A cycle would leave every todo in it blocked for good
+    let closes_cycle = sqlx::query_scalar!(
+        r#"WITH RECURSIVE waiting(id) AS (
+               SELECT $1::int4
+               UNION
+               SELECT todo_dependencies.blocked_id
+               FROM todo_dependencies JOIN waiting ON todo_dependencies.blocker_id = waiting.id
+           )
+           SELECT EXISTS (SELECT 1 FROM waiting WHERE id = $2) AS "closes_cycle!""#,
+        todo_id,
+        blocker_id
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+    if closes_cycle {
+        return Err(AppError::Conflict("The blocker already waits for this todo".to_string()));
+    }
+
+    let result = sqlx::query!(
+        "INSERT INTO todo_dependencies (blocker_id, blocked_id) VALUES ($1, $2)",
+        blocker_id,
+        todo_id
+    )
+        .execute(&mut *tx)
+        .await;
+    match result {
+        Ok(_) => {}
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return Err(AppError::Conflict("The todo already waits for this blocker".to_string()));
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    tx.commit().await?;
+
+    Ok(HttpResponse::Created().json(load_dependencies(pool.get_ref(), todo_id).await?))
+}
+
+// Handler for removing a blocker from a todo
+#[utoipa::path(
+    delete,
+    path = "/todos/{todo_id}/dependencies/{blocker_id}",
+    tag = "dependencies",
+    params(
+        ("todo_id" = i32, Path, description = "Todo id"),
+        ("blocker_id" = i32, Path, description = "Id of the blocking todo")
+    ),
+    responses(
+        (status = 204, description = "Dependency removed"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or dependency not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn remove_dependency(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (todo_id, blocker_id) = path.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM todo_dependencies WHERE blocked_id = $1 AND blocker_id = $2",
+        todo_id,
+        blocker_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Dependency not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
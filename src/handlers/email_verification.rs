@@ -0,0 +1,120 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use sqlx::{PgExecutor, PgPool};
+
+use crate::audit::set_audit_context;
+use crate::auth::email_verification::{check_email_token, sign_email_token};
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::EmailVerifyConfirmReq;
+
+// For features that need a way to reach the user: 403 unless their email address is verified
+pub(crate) async fn ensure_email_verified<'c>(executor: impl PgExecutor<'c>, user_id: i32) -> Result<(), AppError> {
+    let verified = sqlx::query_scalar!(r#"SELECT email_verified FROM "Users" WHERE id = $1"#, user_id)
+        .fetch_optional(executor)
+        .await?
+        .unwrap_or(false);
+
+    if !verified {
+        return Err(AppError::EmailNotVerified);
+    }
+
+    Ok(())
+}
+
+// Handler for sending a verification token for the caller's email address
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/email/verify/send",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 202, description = "A verification token was issued"),
+        (status = 400, description = "No email address on the account", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 409, description = "The address is already verified", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn send_email_verification(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let user = sqlx::query!(r#"SELECT email, email_verified FROM "Users" WHERE id = $1"#, user_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let Some(email) = user.email else {
+        return Err(AppError::BadRequest("No email address on the account".to_string()));
+    };
+    if user.email_verified {
+        return Err(AppError::Conflict("Email address is already verified".to_string()));
+    }
+
+    let token = sign_email_token(user_id, &email, Utc::now().timestamp());
+
+    // There is no mail delivery yet, so the token is only written to the log
+    tracing::info!(user_id, token = %token, "email verification token issued");
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+// Handler for confirming the caller's email address with the token that was sent to it
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/email/verify/confirm",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = EmailVerifyConfirmReq,
+    responses(
+        (status = 204, description = "Email address verified"),
+        (status = 400, description = "Token is invalid, expired or for another address", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn confirm_email_verification(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    body: web::Json<EmailVerifyConfirmReq>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let email = sqlx::query_scalar!(r#"SELECT email FROM "Users" WHERE id = $1"#, user_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .flatten();
+
+    let valid = email.as_deref().is_some_and(|email| check_email_token(user_id, email, &body.token));
+    if !valid {
+        return Err(AppError::BadRequest("Invalid or expired verification token".to_string()));
+    }
+
+    // Only if the address is still the one the token was signed for
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    sqlx::query!(
+        r#"UPDATE "Users" SET email_verified = true WHERE id = $1 AND email = $2"#,
+        user_id,
+        email
+    )
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
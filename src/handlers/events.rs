@@ -0,0 +1,103 @@
+use actix_web::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse};
+use futures_util::{stream, StreamExt};
+use serde_json::json;
+use sqlx::PgPool;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{interval_at, Instant, Interval};
+
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::events::TodoEvent;
+use crate::handlers::todos::TAG_NAMES_COLUMN;
+use crate::models::Todo;
+
+// A comment line keeps proxies from dropping a quiet connection
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+// The stream ends after this long without an event, clients reconnect for a fresh snapshot
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+struct EventStream {
+    user_id: i32,
+    receiver: broadcast::Receiver<TodoEvent>,
+    keepalive: Interval,
+    last_event: Instant,
+}
+
+fn data_message(data: &serde_json::Value) -> Bytes {
+    Bytes::from(format!("data: {}\n\n", data))
+}
+
+// Wait for the next message for the user: one of their events, a keepalive, or None to close
+async fn next_message(mut state: EventStream) -> Option<(Result<Bytes, Infallible>, EventStream)> {
+    loop {
+        let idle_deadline = state.last_event + IDLE_TIMEOUT;
+
+        tokio::select! {
+            received = state.receiver.recv() => match received {
+                Ok(event) if event.user_id == state.user_id => {
+                    state.last_event = Instant::now();
+                    let message = data_message(&json!(event));
+                    return Some((Ok(message), state));
+                }
+                Ok(_) => {}
+                // Events were missed, so close and let the client reconnect for a fresh snapshot
+                Err(RecvError::Lagged(_)) | Err(RecvError::Closed) => return None,
+            },
+            _ = state.keepalive.tick() => {
+                return Some((Ok(Bytes::from_static(b": keepalive\n\n")), state));
+            }
+            _ = tokio::time::sleep_until(idle_deadline) => return None,
+        }
+    }
+}
+
+// Handler for following changes to the caller's todos as server-sent events. The first event is
+// a snapshot of the live, unarchived todos, then one event per created, updated or deleted todo.
+#[utoipa::path(
+    get,
+    path = "/todos/events",
+    tag = "todos",
+    responses(
+        (status = 200, description = "An event stream, each `data:` line a JSON object with a `type` of snapshot, created, updated or deleted", content_type = "text/event-stream", body = String),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn todo_events(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    sender: web::Data<broadcast::Sender<TodoEvent>>,
+) -> Result<HttpResponse, AppError> {
+    // Subscribe first so nothing that happens while the snapshot is read gets lost
+    let receiver = sender.subscribe();
+
+    let todos = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT *, {} FROM todos
+         WHERE user_id = $1 AND deleted_at IS NULL AND archived = false
+         ORDER BY position, id",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(auth.user_id)
+        .fetch_all(pool.get_ref())
+        .await?;
+    let snapshot = data_message(&json!({ "type": "snapshot", "todos": todos }));
+
+    let now = Instant::now();
+    let state = EventStream {
+        user_id: auth.user_id,
+        receiver,
+        keepalive: interval_at(now + KEEPALIVE_INTERVAL, KEEPALIVE_INTERVAL),
+        last_event: now,
+    };
+    let events = stream::once(async move { Ok::<_, Infallible>(snapshot) })
+        .chain(stream::unfold(state, next_message));
+
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(events))
+}
@@ -0,0 +1,73 @@
+use actix_web::{web, HttpResponse, Responder};
+use serde_json::json;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::MIGRATOR;
+
+// Probes must answer quickly even when the database hangs
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+async fn database_reachable(pool: &PgPool) -> bool {
+    let ping = sqlx::query("SELECT 1").execute(pool);
+    matches!(timeout(PROBE_TIMEOUT, ping).await, Ok(Ok(_)))
+}
+
+// Every embedded migration has been applied successfully
+async fn migrations_applied(pool: &PgPool) -> bool {
+    let applied = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM _sqlx_migrations WHERE success",
+    )
+    .fetch_one(pool);
+
+    match timeout(PROBE_TIMEOUT, applied).await {
+        Ok(Ok(applied)) => applied as usize >= MIGRATOR.iter().count(),
+        _ => false,
+    }
+}
+
+// Liveness probe: GET /health
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "meta",
+    responses(
+        (status = 200, description = "Database reachable"),
+        (status = 503, description = "Database unreachable")
+    )
+)]
+pub async fn health_check(pool: web::Data<PgPool>) -> impl Responder {
+    if database_reachable(pool.get_ref()).await {
+        HttpResponse::Ok().json(json!({ "status": "ok", "db": "reachable" }))
+    } else {
+        HttpResponse::ServiceUnavailable().json(json!({ "status": "degraded", "db": "unreachable" }))
+    }
+}
+
+// Readiness probe: GET /ready, also requires the schema to be up to date
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "meta",
+    responses(
+        (status = 200, description = "Database reachable and migrations applied"),
+        (status = 503, description = "Not ready")
+    )
+)]
+pub async fn readiness_check(pool: web::Data<PgPool>) -> impl Responder {
+    let db = database_reachable(pool.get_ref()).await;
+    let migrations = db && migrations_applied(pool.get_ref()).await;
+
+    let body = json!({
+        "status": if db && migrations { "ok" } else { "degraded" },
+        "db": if db { "reachable" } else { "unreachable" },
+        "migrations": if migrations { "applied" } else { "pending" },
+    });
+
+    if db && migrations {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
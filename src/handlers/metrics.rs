@@ -0,0 +1,42 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+use std::net::IpAddr;
+
+use crate::error::{AppError, ProblemDetails};
+use crate::metrics;
+
+// Loopback, private and link-local addresses, i.e. the scraper is on our own network
+pub(crate) fn is_internal(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback() || ip.is_unique_local() || ip.is_unicast_link_local(),
+    }
+}
+
+// Handler for Prometheus scrapes, open without a token but only to internal addresses
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "meta",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+        (status = 403, description = "Caller is outside the internal network", body = ProblemDetails)
+    )
+)]
+pub async fn metrics(req: HttpRequest) -> Result<HttpResponse, AppError> {
+    // The socket address, not X-Forwarded-For, which any client can set
+    match req.peer_addr() {
+        Some(addr) if is_internal(addr.ip()) => {}
+        _ => return Err(AppError::Forbidden),
+    }
+
+    if let Some(pool) = req.app_data::<web::Data<PgPool>>() {
+        metrics::record_pool(pool);
+    }
+
+    let body = metrics::render().map_err(|e| AppError::InternalError(e.to_string()))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(body))
+}
@@ -0,0 +1,77 @@
+use actix_web::http::header::LOCATION;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::middleware::api_version::unversioned_path;
+use crate::models::PaginatedResponse;
+
+pub mod activity;
+pub mod api_keys;
+pub mod audit;
+pub mod batch;
+pub mod categories;
+pub mod comments;
+pub mod data_export;
+pub mod dependencies;
+pub mod email_verification;
+pub mod events;
+pub mod health;
+pub mod metrics;
+pub mod notes;
+pub mod notifications;
+pub mod password_reset;
+pub mod preferences;
+pub mod shares;
+pub mod stats;
+pub mod subtasks;
+pub mod tags;
+pub mod templates;
+pub mod time_entries;
+pub mod todos;
+pub mod two_factor;
+pub mod users;
+pub mod webhooks;
+
+const DEFAULT_PER_PAGE: u32 = 20;
+const MAX_PER_PAGE: u32 = 100;
+
+// Carries PaginatedResponse.total for clients that page by headers rather than the envelope
+pub const TOTAL_COUNT_HEADER: &str = "X-Total-Count";
+
+// A page as the JSON envelope, with the total repeated in X-Total-Count
+pub(crate) fn paginated_response<T: Serialize>(page: &PaginatedResponse<T>) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((TOTAL_COUNT_HEADER, page.total.to_string()))
+        .json(page)
+}
+
+// 201 with the new resource and a Location pointing at it. The location keeps the version prefix
+// the request was made with, if any.
+pub(crate) fn created_response<T: Serialize>(req: &HttpRequest, path: &str, resource: &T) -> HttpResponse {
+    let request_path = req.path();
+    let prefix = &request_path[..request_path.len() - unversioned_path(request_path).len()];
+    HttpResponse::Created()
+        .insert_header((LOCATION, format!("{}{}", prefix, path)))
+        .json(resource)
+}
+
+// Resolve optional page/per_page params into (page, per_page, offset)
+pub(crate) fn page_bounds(page: Option<u32>, per_page: Option<u32>) -> (u32, u32, i64) {
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let offset = (page - 1) as i64 * per_page as i64;
+    (page, per_page, offset)
+}
+
+// Home page handler
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "meta",
+    responses(
+        (status = 200, description = "Welcome text", body = String)
+    )
+)]
+pub async fn home_page() -> impl Responder {
+    "Welcome to the Todo API"
+}
@@ -0,0 +1,86 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+use super::todos::check_todo_access;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{TodoNote, TodoNoteReq};
+use crate::todo_cache::invalidate_todo;
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// Handler for reading a todo's notepad, also for the users it is shared with
+#[utoipa::path(
+    get,
+    path = "/todos/{todo_id}/notes",
+    tag = "notes",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The note, empty when nothing was written yet", body = TodoNote),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_notes(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, false).await?;
+
+    let note = sqlx::query_as!(
+        TodoNote,
+        "SELECT content, updated_at FROM todo_notes WHERE todo_id = $1",
+        todo_id
+    )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .unwrap_or(TodoNote { content: String::new(), updated_at: None });
+
+    Ok(HttpResponse::Ok().json(note))
+}
+
+// Handler for saving a todo's notepad, the owner and share editors may write it
+#[utoipa::path(
+    put,
+    path = "/todos/{todo_id}/notes",
+    tag = "notes",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = TodoNoteReq,
+    responses(
+        (status = 200, description = "The saved note", body = TodoNote),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn put_notes(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    note: web::Json<TodoNoteReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*note)?;
+
+    let todo_id = todo_id.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+
+    let note = sqlx::query_as!(
+        TodoNote,
+        "INSERT INTO todo_notes (todo_id, content, updated_at) VALUES ($1, $2, NOW())
+         ON CONFLICT (todo_id) DO UPDATE SET content = EXCLUDED.content, updated_at = EXCLUDED.updated_at
+         RETURNING content, updated_at",
+        todo_id,
+        note.content
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::Ok().json(note))
+}
@@ -0,0 +1,81 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use super::todos::check_todo_owner;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::Notification;
+
+// Handler for listing the caller's open todos due within the next 24 hours, soonest first.
+// A todo counts as due at the end of its due date (UTC); dismissed ones stay hidden for 24 hours.
+#[utoipa::path(
+    get,
+    path = "/notifications",
+    tag = "notifications",
+    responses(
+        (status = 200, description = "Todos coming due", body = Vec<Notification>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_notifications(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let notifications = sqlx::query_as!(
+        Notification,
+        r#"SELECT id AS todo_id, title, due_date AS "due_date!",
+                  (EXTRACT(EPOCH FROM due.at - NOW()) / 60)::int8 AS "minutes_until_due!"
+           FROM todos
+           CROSS JOIN LATERAL (SELECT (due_date + 1)::timestamp AT TIME ZONE 'UTC' AS at) AS due
+           WHERE user_id = $1 AND completed = false AND deleted_at IS NULL
+             AND due.at > NOW() AND due.at <= NOW() + INTERVAL '24 hours'
+             AND NOT EXISTS (
+                 SELECT 1 FROM notifications_dismissed
+                 WHERE notifications_dismissed.user_id = $1
+                   AND notifications_dismissed.todo_id = todos.id
+                   AND notifications_dismissed.dismissed_at > NOW() - INTERVAL '24 hours'
+             )
+           ORDER BY due.at, id"#,
+        auth.user_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(notifications))
+}
+
+// Handler for hiding a todo's reminder from GET /notifications for the next 24 hours
+#[utoipa::path(
+    post,
+    path = "/notifications/{todo_id}/dismiss",
+    tag = "notifications",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 204, description = "Dismissed"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn dismiss_notification(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    // Dismissing again restarts the 24 hours
+    sqlx::query!(
+        "INSERT INTO notifications_dismissed (user_id, todo_id) VALUES ($1, $2)
+         ON CONFLICT (user_id, todo_id) DO UPDATE SET dismissed_at = NOW()",
+        auth.user_id,
+        todo_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,128 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::audit::set_audit_context;
+use crate::auth::password::hash_password;
+use crate::auth::refresh::{generate_refresh_token, hash_refresh_token};
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{PasswordResetConfirmReq, PasswordResetReq};
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// How long a reset token can be used after it was requested
+pub const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+
+// Handler for starting a password reset. Answers 202 whether or not the name exists,
+// so it can't be used to find out which accounts there are.
+#[utoipa::path(
+    post,
+    path = "/password-reset/request",
+    tag = "users",
+    request_body = PasswordResetReq,
+    responses(
+        (status = 202, description = "A reset token was issued if the user exists")
+    )
+)]
+pub async fn request_password_reset(
+    pool: web::Data<PgPool>,
+    body: web::Json<PasswordResetReq>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = sqlx::query_scalar!(r#"SELECT id FROM "Users" WHERE name = $1 AND deleted_at IS NULL"#, body.name)
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    let Some(user_id) = user_id else {
+        return Ok(HttpResponse::Accepted().finish());
+    };
+
+    // Same shape as a refresh token: 32 random bytes, hex encoded, only the hash is stored
+    let token = generate_refresh_token();
+
+    let mut tx = pool.begin().await?;
+
+    // Only the newest token works, asking again cancels the earlier ones
+    sqlx::query!(
+        "DELETE FROM password_reset_tokens WHERE user_id = $1 AND used_at IS NULL",
+        user_id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES ($1, $2, $3)",
+        user_id,
+        hash_refresh_token(&token),
+        Utc::now() + Duration::minutes(PASSWORD_RESET_TTL_MINUTES)
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    // There is no mail delivery yet, so the token is only written to the log
+    tracing::info!(user_id, token = %token, "password reset token issued");
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+// Handler for finishing a password reset: sets the new password and signs out every session
+#[utoipa::path(
+    post,
+    path = "/password-reset/confirm",
+    tag = "users",
+    request_body = PasswordResetConfirmReq,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Token is invalid, expired or already used", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    )
+)]
+pub async fn confirm_password_reset(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    body: web::Json<PasswordResetConfirmReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*body)?;
+
+    let mut tx = pool.begin().await?;
+
+    // Locked so two confirmations with the same token can't both go through
+    let reset = sqlx::query!(
+        "SELECT id, user_id FROM password_reset_tokens
+         WHERE token_hash = $1 AND used_at IS NULL AND expires_at > NOW()
+         FOR UPDATE",
+        hash_refresh_token(&body.token)
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Invalid or expired reset token".to_string()))?;
+    // Holding the token stands in for being signed in as the user
+    set_audit_context(&mut tx, reset.user_id, &req).await?;
+
+    // Erased accounts keep no tokens, but one issued just before the erasure mustn't revive them
+    let updated = sqlx::query!(
+        r#"UPDATE "Users" SET password = $1 WHERE id = $2 AND deleted_at IS NULL"#,
+        hash_password(&body.new_password)?,
+        reset.user_id
+    )
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Err(AppError::BadRequest("Invalid or expired reset token".to_string()));
+    }
+
+    sqlx::query!("UPDATE password_reset_tokens SET used_at = NOW() WHERE id = $1", reset.id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        reset.user_id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,123 @@
+use actix_web::{web, HttpResponse};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use std::str::FromStr;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{SortField, UserPreferences};
+use crate::validation::ValidationErrorResponse;
+
+// Checks one value, the error is the message shown for the key
+type PreferenceCheck = fn(&Value) -> Result<(), &'static str>;
+
+// The keys that are kept, with the check their value has to pass
+const KNOWN_PREFERENCES: &[(&str, PreferenceCheck)] = &[
+    ("default_sort_by", |value| match value.as_str().map(SortField::from_str) {
+        Some(Ok(_)) => Ok(()),
+        _ => Err("must be one of title, completed, created_at, updated_at"),
+    }),
+    ("default_per_page", |value| match value.as_u64() {
+        Some(1..=100) => Ok(()),
+        _ => Err("must be a whole number between 1 and 100"),
+    }),
+    ("show_completed", |value| match value {
+        Value::Bool(_) => Ok(()),
+        _ => Err("must be true or false"),
+    }),
+    ("theme", |value| match value.as_str() {
+        Some("light" | "dark") => Ok(()),
+        _ => Err("must be \"light\" or \"dark\""),
+    }),
+];
+
+// Handler for the caller's stored preferences, an empty object until something is saved
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/preferences",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "The stored preferences", body = UserPreferences),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_preferences(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let preferences = sqlx::query_scalar!("SELECT preferences FROM user_preferences WHERE user_id = $1", user_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .unwrap_or_else(|| Value::Object(Map::new()));
+
+    Ok(HttpResponse::Ok().json(preferences))
+}
+
+// Handler for changing preferences as a JSON merge patch (RFC 7386): keys in the body replace
+// the stored ones, null removes one, keys left out are kept. Unknown keys are dropped silently
+// so older and newer clients can share the same preferences.
+#[utoipa::path(
+    patch,
+    path = "/users/{user_id}/preferences",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body(content = UserPreferences, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "The preferences after the merge", body = UserPreferences),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 422, description = "A known key has a value of the wrong type", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_preferences(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    patch: web::Json<Map<String, Value>>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let mut known = Map::new();
+    let mut errors = ValidationErrors::new();
+    for (key, check) in KNOWN_PREFERENCES {
+        let Some(value) = patch.get(*key) else {
+            continue;
+        };
+        // null is kept, it removes the stored value
+        if let Err(message) = if value.is_null() { Ok(()) } else { check(value) } {
+            errors.add(key, ValidationError::new("invalid").with_message(message.into()));
+            continue;
+        }
+        known.insert(key.to_string(), value.clone());
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    // Every known value is a scalar, so merging the top level and dropping nulls is the whole merge patch
+    let preferences = sqlx::query_scalar!(
+        "INSERT INTO user_preferences (user_id, preferences) VALUES ($1, jsonb_strip_nulls($2))
+         ON CONFLICT (user_id) DO UPDATE SET preferences = jsonb_strip_nulls(user_preferences.preferences || $2)
+         RETURNING preferences",
+        user_id,
+        Value::Object(known)
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(preferences))
+}
@@ -0,0 +1,105 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+
+use super::todos::check_todo_owner;
+use crate::activity::record_todo_activity;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{ActivityAction, ShareEntry, ShareReq};
+use crate::todo_cache::invalidate_todo;
+
+// Handler for sharing a todo with another user, sharing again updates can_edit
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/shares",
+    tag = "shares",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = ShareReq,
+    responses(
+        (status = 201, description = "The share", body = ShareEntry),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or user not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn share_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    share: web::Json<ShareReq>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    if share.user_id == auth.user_id {
+        return Err(AppError::BadRequest("A todo can't be shared with its owner".to_string()));
+    }
+
+    let result = sqlx::query_as!(
+        ShareEntry,
+        "INSERT INTO todo_shares (todo_id, shared_with_user_id, can_edit) VALUES ($1, $2, $3)
+         ON CONFLICT (todo_id, shared_with_user_id) DO UPDATE SET can_edit = EXCLUDED.can_edit
+         RETURNING shared_with_user_id AS user_id, can_edit, created_at",
+        todo_id,
+        share.user_id,
+        share.can_edit
+    )
+        .fetch_one(pool.get_ref())
+        .await;
+
+    match result {
+        Ok(entry) => {
+            let meta = json!({ "shared_with_user_id": entry.user_id, "can_edit": entry.can_edit });
+            record_todo_activity(pool.get_ref(), auth.user_id, ActivityAction::Shared, todo_id, meta).await?;
+            invalidate_todo(&req, todo_id).await;
+            Ok(HttpResponse::Created().json(entry))
+        }
+        Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+            Err(AppError::NotFound("User not found".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Handler for revoking a user's access to a todo
+#[utoipa::path(
+    delete,
+    path = "/todos/{todo_id}/shares/{user_id}",
+    tag = "shares",
+    params(("todo_id" = i32, Path, description = "Todo id"), ("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 204, description = "Access revoked"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or share not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn unshare_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (todo_id, user_id) = path.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM todo_shares WHERE todo_id = $1 AND shared_with_user_id = $2",
+        todo_id,
+        user_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Todo is not shared with this user".to_string()));
+    }
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,206 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use moka::sync::Cache;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::audit::set_audit_context;
+use crate::auth::{AdminGuard, AuthUser};
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{PriorityCounts, Role, StatsHistoryQuery, StatsSnapshot, TagCount, TodoStats};
+
+// Stats are a minute old at most, dashboards polling them don't each cost a full scan
+static STATS_CACHE: LazyLock<Cache<i32, TodoStats>> = LazyLock::new(|| {
+    Cache::builder()
+        .max_capacity(10_000)
+        .time_to_live(Duration::from_secs(60))
+        .build()
+});
+
+// Every number in one pass over the user's todos
+async fn compute_stats(pool: &PgPool, user_id: i32) -> Result<TodoStats, AppError> {
+    let row = sqlx::query!(
+        r#"WITH mine AS (
+               SELECT * FROM todos WHERE user_id = $1 AND deleted_at IS NULL
+           )
+           SELECT COUNT(*) AS "total!",
+                  COALESCE(SUM(CASE WHEN completed THEN 1 ELSE 0 END), 0) AS "completed!",
+                  COALESCE(SUM(CASE WHEN NOT completed AND due_date < CURRENT_DATE THEN 1 ELSE 0 END), 0) AS "overdue!",
+                  COALESCE(SUM(CASE WHEN NOT completed AND due_date = CURRENT_DATE THEN 1 ELSE 0 END), 0) AS "due_today!",
+                  AVG(CASE WHEN completed THEN EXTRACT(EPOCH FROM updated_at - created_at) / 86400 END)::float8
+                      AS avg_completion_days,
+                  COALESCE(SUM(CASE WHEN priority = 'low' THEN 1 ELSE 0 END), 0) AS "low!",
+                  COALESCE(SUM(CASE WHEN priority = 'medium' THEN 1 ELSE 0 END), 0) AS "medium!",
+                  COALESCE(SUM(CASE WHEN priority = 'high' THEN 1 ELSE 0 END), 0) AS "high!",
+                  COALESCE(SUM(CASE WHEN priority = 'critical' THEN 1 ELSE 0 END), 0) AS "critical!",
+                  (SELECT COALESCE(jsonb_agg(jsonb_build_object('tag', name, 'count', count) ORDER BY count DESC, name), '[]')
+                   FROM (SELECT tags.name, COUNT(*) AS count
+                         FROM mine
+                         JOIN todo_tags ON todo_tags.todo_id = mine.id
+                         JOIN tags ON tags.id = todo_tags.tag_id
+                         GROUP BY tags.name) AS tag_counts) AS "by_tag!: Json<Vec<TagCount>>"
+           FROM mine"#,
+        user_id
+    )
+        .fetch_one(pool)
+        .await?;
+
+    let total = row.total;
+    let completed = row.completed;
+
+    Ok(TodoStats {
+        total,
+        completed,
+        overdue: row.overdue,
+        due_today: row.due_today,
+        completion_rate: if total == 0 { 0.0 } else { completed as f64 / total as f64 },
+        avg_completion_days: row.avg_completion_days.unwrap_or(0.0),
+        by_priority: PriorityCounts {
+            low: row.low,
+            medium: row.medium,
+            high: row.high,
+            critical: row.critical,
+        },
+        by_tag: row.by_tag.0,
+    })
+}
+
+// Handler for summary numbers about the caller's todos, cached for a minute per user
+#[utoipa::path(
+    get,
+    path = "/todos/stats",
+    tag = "todos",
+    responses(
+        (status = 200, description = "Counts and rates over the caller's todos", body = TodoStats),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_stats(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    if let Some(stats) = STATS_CACHE.get(&auth.user_id) {
+        return Ok(HttpResponse::Ok().json(stats));
+    }
+
+    let stats = compute_stats(pool.get_ref(), auth.user_id).await?;
+    STATS_CACHE.insert(auth.user_id, stats.clone());
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+// Longest range one history request may cover
+const MAX_HISTORY_DAYS: i64 = 366;
+
+// Handler for a user's daily snapshots between two dates, oldest first.
+// Only the user themselves or an admin may read it.
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/stats/history",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id"), StatsHistoryQuery),
+    responses(
+        (status = 200, description = "One snapshot per day that has one", body = [StatsSnapshot]),
+        (status = 400, description = "start is after end, or the range is too long", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_stats_history(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    query: web::Query<StatsHistoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.role != Role::Admin && auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let end = query.end.unwrap_or_else(|| Utc::now().date_naive());
+    let start = query.start.unwrap_or(end - chrono::Duration::days(30));
+    if start > end {
+        return Err(AppError::BadRequest("start must not be after end".to_string()));
+    }
+    if (end - start).num_days() >= MAX_HISTORY_DAYS {
+        return Err(AppError::BadRequest(format!("The range may span at most {} days", MAX_HISTORY_DAYS)));
+    }
+
+    ensure_user_exists(pool.get_ref(), user_id).await?;
+
+    let snapshots = sqlx::query_as!(
+        StatsSnapshot,
+        "SELECT snapshot_date, total_todos, completed_todos, created_todos, deleted_todos
+         FROM stats_snapshots
+         WHERE user_id = $1 AND snapshot_date BETWEEN $2 AND $3
+         ORDER BY snapshot_date",
+        user_id,
+        start,
+        end
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(snapshots))
+}
+
+// Handler for starting a user's statistics over (admins only): drops the snapshot history and
+// from now on only counts what happens after the reset. The todos themselves are left alone.
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/stats/reset",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 204, description = "Statistics reset"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn reset_stats(
+    admin: AdminGuard,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, admin.0.user_id, &req).await?;
+
+    let result = sqlx::query!(r#"UPDATE "Users" SET stats_reset_at = NOW() WHERE id = $1"#, user_id)
+        .execute(&mut *tx)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    sqlx::query!("DELETE FROM stats_snapshots WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn ensure_user_exists(pool: &PgPool, user_id: i32) -> Result<(), AppError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM "Users" WHERE id = $1) AS "exists!""#,
+        user_id
+    )
+        .fetch_one(pool)
+        .await?;
+
+    if exists {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("User not found".to_string()))
+    }
+}
@@ -0,0 +1,175 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+use super::todos::check_todo_owner;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{NewSubtask, Subtask, UpdateSubtaskReq};
+use crate::todo_cache::invalidate_todo;
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// Handler for listing the checklist of a todo
+#[utoipa::path(
+    get,
+    path = "/todos/{todo_id}/subtasks",
+    tag = "subtasks",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The todo's checklist", body = Vec<Subtask>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_subtasks(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let subtasks = sqlx::query_as!(
+        Subtask,
+        "SELECT id, todo_id, title, completed, position FROM subtasks
+         WHERE todo_id = $1 ORDER BY position, id",
+        todo_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(subtasks))
+}
+
+// Handler for adding a checklist item to a todo
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/subtasks",
+    tag = "subtasks",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = NewSubtask,
+    responses(
+        (status = 201, description = "The created subtask", body = Subtask),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_subtask(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    new_subtask: web::Json<NewSubtask>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_subtask)?;
+
+    let todo_id = todo_id.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let subtask = sqlx::query_as!(
+        Subtask,
+        r#"INSERT INTO subtasks (todo_id, title, completed, position)
+           VALUES ($1, $2, $3, COALESCE($4, (SELECT COALESCE(MAX(position) + 1, 0) FROM subtasks WHERE todo_id = $1)))
+           RETURNING id, todo_id, title, completed, position"#,
+        todo_id,
+        new_subtask.title,
+        new_subtask.completed.unwrap_or(false),
+        new_subtask.position
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::Created().json(subtask))
+}
+
+// Handler for editing a checklist item, only the fields present in the body change
+#[utoipa::path(
+    patch,
+    path = "/todos/{todo_id}/subtasks/{subtask_id}",
+    tag = "subtasks",
+    params(("todo_id" = i32, Path, description = "Todo id"), ("subtask_id" = i32, Path, description = "Subtask id")),
+    request_body = UpdateSubtaskReq,
+    responses(
+        (status = 200, description = "The updated subtask", body = Subtask),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or subtask not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_subtask(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, i32)>,
+    subtask_data: web::Json<UpdateSubtaskReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*subtask_data)?;
+
+    let (todo_id, subtask_id) = path.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let subtask = sqlx::query_as!(
+        Subtask,
+        "UPDATE subtasks SET title = COALESCE($1, title), completed = COALESCE($2, completed),
+                position = COALESCE($3, position)
+         WHERE id = $4 AND todo_id = $5
+         RETURNING id, todo_id, title, completed, position",
+        subtask_data.title.as_deref(),
+        subtask_data.completed,
+        subtask_data.position,
+        subtask_id,
+        todo_id
+    )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Subtask not found".to_string()))?;
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::Ok().json(subtask))
+}
+
+// Handler for removing a checklist item
+#[utoipa::path(
+    delete,
+    path = "/todos/{todo_id}/subtasks/{subtask_id}",
+    tag = "subtasks",
+    params(("todo_id" = i32, Path, description = "Todo id"), ("subtask_id" = i32, Path, description = "Subtask id")),
+    responses(
+        (status = 204, description = "Subtask deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or subtask not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_subtask(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    path: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (todo_id, subtask_id) = path.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM subtasks WHERE id = $1 AND todo_id = $2",
+        subtask_id,
+        todo_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Subtask not found".to_string()));
+    }
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
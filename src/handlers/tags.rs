@@ -0,0 +1,73 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{NewTag, Tag};
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// Handler for listing the caller's tags
+#[utoipa::path(
+    get,
+    path = "/tags",
+    tag = "tags",
+    responses(
+        (status = 200, description = "The caller's tags", body = Vec<Tag>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_tags(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let tags = sqlx::query_as!(
+        Tag,
+        "SELECT id, name, color FROM tags WHERE user_id = $1 ORDER BY name",
+        auth.user_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+// Handler for creating a tag, names are unique per user
+#[utoipa::path(
+    post,
+    path = "/tags",
+    tag = "tags",
+    request_body = NewTag,
+    responses(
+        (status = 201, description = "The created tag", body = Tag),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 409, description = "A tag with this name already exists", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_tag(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    new_tag: web::Json<NewTag>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_tag)?;
+
+    let result = sqlx::query_as!(
+        Tag,
+        "INSERT INTO tags (user_id, name, color) VALUES ($1, $2, $3) RETURNING id, name, color",
+        auth.user_id,
+        new_tag.name,
+        new_tag.color
+    )
+        .fetch_one(pool.get_ref())
+        .await;
+
+    match result {
+        Ok(tag) => Ok(HttpResponse::Created().json(tag)),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::Conflict("A tag with this name already exists".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,298 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::types::Json;
+use sqlx::PgPool;
+use validator::{ValidationError, ValidationErrors};
+
+use super::created_response;
+use super::todos::{fetch_todo_response, insert_todo};
+use crate::audit::set_audit_context;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::events::{self, TodoEventKind};
+use crate::metrics::TODOS_CREATED_TOTAL;
+use crate::models::{
+    InstantiateTemplateReq, NewTodo, NewTodoTemplate, Priority, TodoResponse, TodoTemplate, UpdateTodoTemplateReq,
+};
+use crate::templates::{placeholders, render};
+use crate::validation::{validate_input, ValidationErrorResponse};
+use crate::webhooks;
+
+// One template of the caller's, 404 for anyone else's
+async fn fetch_template(pool: &PgPool, template_id: i32, user_id: i32) -> Result<TodoTemplate, AppError> {
+    sqlx::query_as!(
+        TodoTemplate,
+        r#"SELECT id, name, title_template, description_template, priority AS "priority: Priority",
+                  subtask_templates AS "subtask_templates: Json<Vec<String>>", created_at
+           FROM todo_templates WHERE id = $1 AND user_id = $2"#,
+        template_id,
+        user_id
+    )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Template not found".to_string()))
+}
+
+fn name_taken(e: sqlx::Error) -> AppError {
+    match e {
+        sqlx::Error::Database(e) if e.is_unique_violation() => {
+            AppError::Conflict("A template with this name already exists".to_string())
+        }
+        e => e.into(),
+    }
+}
+
+// Handler for listing the caller's templates
+#[utoipa::path(
+    get,
+    path = "/templates",
+    tag = "templates",
+    responses(
+        (status = 200, description = "The caller's templates", body = Vec<TodoTemplate>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_templates(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let templates = sqlx::query_as!(
+        TodoTemplate,
+        r#"SELECT id, name, title_template, description_template, priority AS "priority: Priority",
+                  subtask_templates AS "subtask_templates: Json<Vec<String>>", created_at
+           FROM todo_templates WHERE user_id = $1 ORDER BY name"#,
+        auth.user_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+// Handler for saving a new template
+#[utoipa::path(
+    post,
+    path = "/templates",
+    tag = "templates",
+    request_body = NewTodoTemplate,
+    responses(
+        (status = 201, description = "The created template", body = TodoTemplate),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 409, description = "The caller has a template with this name", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_template(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    new_template: web::Json<NewTodoTemplate>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_template)?;
+
+    let template = sqlx::query_as!(
+        TodoTemplate,
+        r#"INSERT INTO todo_templates (user_id, name, title_template, description_template, priority, subtask_templates)
+           VALUES ($1, $2, $3, $4, $5, $6)
+           RETURNING id, name, title_template, description_template, priority AS "priority: Priority",
+                     subtask_templates AS "subtask_templates: Json<Vec<String>>", created_at"#,
+        auth.user_id,
+        new_template.name,
+        new_template.title_template,
+        new_template.description_template.clone().unwrap_or_default(),
+        new_template.priority.unwrap_or(Priority::Medium) as Priority,
+        Json(new_template.subtask_templates.clone().unwrap_or_default()) as _
+    )
+        .fetch_one(pool.get_ref())
+        .await
+        .map_err(name_taken)?;
+
+    Ok(HttpResponse::Created().json(template))
+}
+
+// Handler for one of the caller's templates
+#[utoipa::path(
+    get,
+    path = "/templates/{template_id}",
+    tag = "templates",
+    params(("template_id" = i32, Path, description = "Template id")),
+    responses(
+        (status = 200, description = "The template", body = TodoTemplate),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Template not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_template(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    template_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let template = fetch_template(pool.get_ref(), template_id.into_inner(), auth.user_id).await?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+// Handler for editing a template, only the fields present in the body change
+#[utoipa::path(
+    patch,
+    path = "/templates/{template_id}",
+    tag = "templates",
+    params(("template_id" = i32, Path, description = "Template id")),
+    request_body = UpdateTodoTemplateReq,
+    responses(
+        (status = 200, description = "The updated template", body = TodoTemplate),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Template not found", body = ProblemDetails),
+        (status = 409, description = "The caller has a template with this name", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_template(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    template_id: web::Path<i32>,
+    template_data: web::Json<UpdateTodoTemplateReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*template_data)?;
+
+    let template = sqlx::query_as!(
+        TodoTemplate,
+        r#"UPDATE todo_templates
+           SET name = COALESCE($1, name), title_template = COALESCE($2, title_template),
+               description_template = COALESCE($3, description_template), priority = COALESCE($4, priority),
+               subtask_templates = COALESCE($5, subtask_templates)
+           WHERE id = $6 AND user_id = $7
+           RETURNING id, name, title_template, description_template, priority AS "priority: Priority",
+                     subtask_templates AS "subtask_templates: Json<Vec<String>>", created_at"#,
+        template_data.name.as_deref(),
+        template_data.title_template.as_deref(),
+        template_data.description_template.as_deref(),
+        template_data.priority as Option<Priority>,
+        template_data.subtask_templates.clone().map(Json) as _,
+        template_id.into_inner(),
+        auth.user_id
+    )
+        .fetch_optional(pool.get_ref())
+        .await
+        .map_err(name_taken)?
+        .ok_or_else(|| AppError::NotFound("Template not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+// Handler for removing a template, todos created from it stay
+#[utoipa::path(
+    delete,
+    path = "/templates/{template_id}",
+    tag = "templates",
+    params(("template_id" = i32, Path, description = "Template id")),
+    responses(
+        (status = 204, description = "Template deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Template not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_template(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    template_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let result = sqlx::query!(
+        "DELETE FROM todo_templates WHERE id = $1 AND user_id = $2",
+        template_id.into_inner(),
+        auth.user_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Template not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler for creating a todo, with its subtasks, from a template. Every `{{name}}` placeholder
+// is replaced by variables[name]; leaving one out fails the request with 422.
+#[utoipa::path(
+    post,
+    path = "/templates/{template_id}/instantiate",
+    tag = "templates",
+    params(("template_id" = i32, Path, description = "Template id")),
+    request_body(content = Option<InstantiateTemplateReq>, description = "Optional when the template has no placeholders"),
+    responses(
+        (status = 201, description = "The created todo", body = TodoResponse, headers(("Location" = String, description = "Path of the new todo"))),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Template not found", body = ProblemDetails),
+        (status = 422, description = "Variables are missing or the result isn't a valid todo", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn instantiate_template(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    template_id: web::Path<i32>,
+    body: Option<web::Json<InstantiateTemplateReq>>,
+) -> Result<HttpResponse, AppError> {
+    let template = fetch_template(pool.get_ref(), template_id.into_inner(), auth.user_id).await?;
+    let variables = body.map(|body| body.into_inner().variables).unwrap_or_default();
+
+    let texts = [template.title_template.as_str(), template.description_template.as_str()]
+        .into_iter()
+        .chain(template.subtask_templates.iter().map(String::as_str));
+    let missing: Vec<&str> = placeholders(texts)
+        .into_iter()
+        .filter(|name| !variables.contains_key(*name))
+        .collect();
+    if !missing.is_empty() {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "variables",
+            ValidationError::new("missing").with_message(format!("missing {}", missing.join(", ")).into()),
+        );
+        return Err(errors.into());
+    }
+
+    let new_todo = NewTodo {
+        title: Some(render(&template.title_template, &variables)),
+        completed: Some(false),
+        description: Some(render(&template.description_template, &variables)),
+        due_date: None,
+        priority: Some(template.priority),
+        tag_ids: None,
+        category_id: None,
+    };
+    // The variables may make the texts too long (or the title empty)
+    validate_input(&new_todo)?;
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    let todo_id = insert_todo(&mut tx, auth.user_id, &new_todo).await?;
+
+    for (position, title) in template.subtask_templates.iter().enumerate() {
+        sqlx::query!(
+            "INSERT INTO subtasks (todo_id, title, completed, position) VALUES ($1, $2, false, $3)",
+            todo_id,
+            render(title, &variables),
+            position as i32
+        )
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let response = fetch_todo_response(&mut tx, todo_id)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Created todo could not be read back".to_string()))?;
+
+    tx.commit().await?;
+
+    TODOS_CREATED_TOTAL.inc();
+    webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.created", &response);
+    events::publish(&req, auth.user_id, TodoEventKind::Created, todo_id, Some(&response));
+
+    Ok(created_response(&req, &format!("/todos/{}", response.id), &response))
+}
@@ -0,0 +1,129 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use sqlx::PgPool;
+
+use super::todos::check_todo_access;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{StoppedTimer, TimeEntry, TimeReport};
+use crate::todo_cache::invalidate_todo;
+
+// Handler for starting the caller's timer on a todo
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/time/start",
+    tag = "time",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 201, description = "The running entry", body = TimeEntry),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 409, description = "The caller's timer is already running on this todo", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn start_timer(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+
+    // The partial unique index allows one open entry per user and todo
+    let result = sqlx::query_as!(
+        TimeEntry,
+        r#"INSERT INTO time_entries (todo_id, user_id) VALUES ($1, $2)
+           RETURNING id, todo_id, user_id, started_at, ended_at, 0::int8 AS "duration_seconds!""#,
+        todo_id,
+        auth.user_id
+    )
+        .fetch_one(pool.get_ref())
+        .await;
+
+    match result {
+        Ok(entry) => {
+            invalidate_todo(&req, todo_id).await;
+            Ok(HttpResponse::Created().json(entry))
+        }
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            Err(AppError::Conflict("A timer is already running on this todo".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Handler for stopping the caller's running timer on a todo
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/time/stop",
+    tag = "time",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "How long the timer ran", body = StoppedTimer),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found or no timer running", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn stop_timer(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+
+    let duration_seconds = sqlx::query_scalar!(
+        r#"UPDATE time_entries SET ended_at = NOW()
+           WHERE todo_id = $1 AND user_id = $2 AND ended_at IS NULL
+           RETURNING EXTRACT(EPOCH FROM ended_at - started_at)::int8 AS "duration_seconds!""#,
+        todo_id,
+        auth.user_id
+    )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("No timer is running on this todo".to_string()))?;
+    invalidate_todo(&req, todo_id).await;
+
+    Ok(HttpResponse::Ok().json(StoppedTimer { duration_seconds }))
+}
+
+// Handler for listing the time everyone with access logged on a todo
+#[utoipa::path(
+    get,
+    path = "/todos/{todo_id}/time",
+    tag = "time",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "Entries oldest first, with their total", body = TimeReport),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_time_entries(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, false).await?;
+
+    let entries = sqlx::query_as!(
+        TimeEntry,
+        r#"SELECT id, todo_id, user_id, started_at, ended_at,
+                  EXTRACT(EPOCH FROM COALESCE(ended_at, NOW()) - started_at)::int8 AS "duration_seconds!"
+           FROM time_entries WHERE todo_id = $1 ORDER BY started_at, id"#,
+        todo_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+    let total_seconds = entries.iter().map(|entry| entry.duration_seconds).sum();
+
+    Ok(HttpResponse::Ok().json(TimeReport { entries, total_seconds }))
+}
@@ -0,0 +1,2116 @@
+use actix_web::http::header::{
+    AsHeaderName, HeaderMap, HeaderValue, ACCEPT, CONTENT_DISPOSITION, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH,
+};
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use chrono::{Days, Utc};
+use icalendar::{Calendar, Component, Event, EventLike};
+use serde_json::json;
+use sqlx::{Connection, PgConnection, PgExecutor, PgPool, Postgres, QueryBuilder, Transaction};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use tracing::Instrument;
+
+use super::dependencies::ensure_unblocked;
+use super::{created_response, page_bounds, paginated_response};
+use crate::activity::record_todo_activity;
+use crate::audit::{record_bulk_change, set_audit_actor, set_audit_context, skip_row_audit};
+use crate::auth::{AdminGuard, AuthUser};
+use crate::error::{AppError, BlockedResponse, ProblemDetails, UnprocessableTodoResponse, VersionConflictResponse};
+use crate::events::{self, TodoEventKind};
+use crate::idempotency::{self, idempotency_key, Claim};
+use crate::import::{detect_format, multipart_file, parse_rows};
+use crate::jsonapi::JsonApiResponder;
+use crate::metrics::{TODOS_CREATED_TOTAL, TODOS_DELETED_TOTAL};
+use crate::models::{
+    ActivityAction, BulkUpdateReq, CategoryResponse, BulkUpdateResponse, ClearCompletedQuery, ClearCompletedResponse, DuplicateTodoReq, ExportFormat, ExportQuery, ImportQuery, ImportReport, ImportRowError, MoveTodoReq, MoveToUserReq, NewTodo, SnoozeReq,
+    PaginatedResponse, Priority, RecurrenceReq, Role, ShareEntry, SortDir, SortField, Todo, TodoQuery, TodoResponse,
+    UpdateTaskReq,
+};
+use crate::recurrence::RecurrenceRule;
+use crate::telemetry::db_query_span;
+use crate::todo_cache::{invalidate_todo, todo_cache};
+use crate::validation::{validate_input, ValidationErrorResponse};
+use crate::webhooks;
+
+// Percentage of a todo's subtasks that are done, 0 when it has none
+fn completion_percent(completed: i64, total: i64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        completed as f64 / total as f64 * 100.0
+    }
+}
+
+// Strong ETag for a todo: the SipHash of its JSON representation, hex-encoded and quoted
+fn todo_etag(todo: &TodoResponse) -> Result<String, AppError> {
+    let body = serde_json::to_vec(todo).map_err(|e| AppError::InternalError(e.to_string()))?;
+    let mut hasher = DefaultHasher::new();
+    hasher.write(&body);
+    Ok(format!("\"{:016x}\"", hasher.finish()))
+}
+
+// Whether a conditional header (If-Match / If-None-Match) lists the given ETag or `*`.
+// None when the request doesn't carry the header at all.
+fn etag_header_matches(headers: &HeaderMap, name: impl AsHeaderName, etag: &str) -> Option<bool> {
+    let value = headers.get(name)?.to_str().unwrap_or_default();
+    Some(value.split(',').map(str::trim).any(|tag| {
+        tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag
+    }))
+}
+
+// Select-list entry with the names of a todo's tags, for queries that read from `todos`
+pub(crate) const TAG_NAMES_COLUMN: &str = "ARRAY(SELECT tags.name FROM todo_tags JOIN tags ON tags.id = todo_tags.tag_id \
+     WHERE todo_tags.todo_id = todos.id ORDER BY tags.name) AS tags";
+
+// Append the WHERE clause for the caller's todos (live ones and the ones shared with them,
+// or their own trashed ones when `trashed`) and the filters set in the query string.
+// Every value goes through push_bind so nothing is interpolated into the SQL.
+fn push_todo_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    user_id: i32,
+    query: &TodoQuery,
+    trashed: bool,
+) -> Result<(), AppError> {
+    if trashed {
+        builder
+            .push(" WHERE user_id = ")
+            .push_bind(user_id)
+            .push(" AND deleted_at IS NOT NULL");
+    } else {
+        builder
+            .push(" WHERE (user_id = ")
+            .push_bind(user_id)
+            .push(" OR EXISTS (SELECT 1 FROM todo_shares WHERE todo_shares.todo_id = todos.id AND todo_shares.shared_with_user_id = ")
+            .push_bind(user_id)
+            .push(")) AND deleted_at IS NULL");
+    }
+
+    if let Some(completed) = query.completed {
+        builder.push(" AND completed = ").push_bind(completed);
+    }
+
+    // The trash lists everything deleted, archived or not
+    if !trashed && query.include_archived != Some(true) {
+        builder.push(" AND archived = false");
+    }
+
+    if query.overdue == Some(true) {
+        builder.push(" AND due_date < CURRENT_DATE AND completed = false AND archived = false");
+    }
+
+    if let Some(priority) = query.priority.as_deref() {
+        let priority: Priority = priority.parse().map_err(AppError::BadRequest)?;
+        builder.push(" AND priority = ").push_bind(priority);
+    }
+
+    if let Some(q) = &query.q {
+        builder
+            .push(" AND search_vector @@ plainto_tsquery('english', ")
+            .push_bind(q.clone())
+            .push(")");
+    }
+
+    if let Some(tag_id) = query.tag_id {
+        builder
+            .push(" AND EXISTS (SELECT 1 FROM todo_tags WHERE todo_tags.todo_id = todos.id AND todo_tags.tag_id = ")
+            .push_bind(tag_id)
+            .push(")");
+    }
+
+    Ok(())
+}
+
+// Append the ORDER BY clause from the validated sort params (default: best search match
+// first when searching, the manual position otherwise). id is always the last key so the page boundaries are stable.
+fn push_todo_order_by(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    query: &TodoQuery,
+) -> Result<(), AppError> {
+    let field = query
+        .sort_by
+        .as_deref()
+        .map(str::parse::<SortField>)
+        .transpose()
+        .map_err(AppError::BadRequest)?;
+    let dir = query
+        .sort_dir
+        .as_deref()
+        .map(str::parse::<SortDir>)
+        .transpose()
+        .map_err(AppError::BadRequest)?
+        .unwrap_or(SortDir::Asc);
+
+    match (field, &query.q) {
+        (Some(field), _) => {
+            builder.push(format!(" ORDER BY {} {}, id ASC", field.column(), dir.keyword()));
+        }
+        (None, Some(q)) => {
+            builder
+                .push(" ORDER BY ts_rank(search_vector, plainto_tsquery('english', ")
+                .push_bind(q.clone())
+                .push(")) DESC, id ASC");
+        }
+        (None, None) => {
+            builder.push(format!(" ORDER BY position {0}, id {0}", dir.keyword()));
+        }
+    }
+
+    Ok(())
+}
+
+// Make sure the todo exists and isn't trashed (404) and belongs to the caller (403)
+pub(crate) async fn check_todo_owner<'c>(
+    executor: impl PgExecutor<'c>,
+    todo_id: i32,
+    user_id: i32,
+) -> Result<(), AppError> {
+    let owner = sqlx::query_scalar!(
+        "SELECT user_id FROM todos WHERE id = $1 AND deleted_at IS NULL",
+        todo_id
+    )
+        .fetch_optional(executor)
+        .await?;
+
+    match owner {
+        None => Err(AppError::NotFound("Todo not found".to_string())),
+        Some(owner) if owner != user_id => Err(AppError::Forbidden),
+        Some(_) => Ok(()),
+    }
+}
+
+// Make sure the todo exists and isn't trashed (404) and the caller owns it or it is shared
+// with them, with edit rights when `edit` (403). Returns the owner's id.
+pub(crate) async fn check_todo_access<'c>(
+    executor: impl PgExecutor<'c>,
+    todo_id: i32,
+    user_id: i32,
+    edit: bool,
+) -> Result<i32, AppError> {
+    let access = sqlx::query!(
+        r#"SELECT todos.user_id AS "owner!", todo_shares.can_edit AS "can_edit?"
+           FROM todos
+           LEFT JOIN todo_shares ON todo_shares.todo_id = todos.id
+                                AND todo_shares.shared_with_user_id = $2
+           WHERE todos.id = $1 AND todos.deleted_at IS NULL"#,
+        todo_id,
+        user_id
+    )
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+
+    match access.can_edit {
+        _ if access.owner == user_id => Ok(access.owner),
+        Some(can_edit) if can_edit || !edit => Ok(access.owner),
+        _ => Err(AppError::Forbidden),
+    }
+}
+
+// Replace the tags attached to a todo. Every id must be one of the caller's tags (400 otherwise).
+async fn set_todo_tags(
+    conn: &mut PgConnection,
+    todo_id: i32,
+    user_id: i32,
+    tag_ids: &[i32],
+) -> Result<(), AppError> {
+    let mut tag_ids = tag_ids.to_vec();
+    tag_ids.sort_unstable();
+    tag_ids.dedup();
+
+    sqlx::query!("DELETE FROM todo_tags WHERE todo_id = $1", todo_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let linked = sqlx::query!(
+        "INSERT INTO todo_tags (todo_id, tag_id)
+         SELECT $1, id FROM tags WHERE id = ANY($2) AND user_id = $3",
+        todo_id,
+        &tag_ids,
+        user_id
+    )
+        .execute(&mut *conn)
+        .await?;
+
+    if linked.rows_affected() != tag_ids.len() as u64 {
+        return Err(AppError::BadRequest("Unknown tag id in tag_ids".to_string()));
+    }
+
+    Ok(())
+}
+
+// A todo's category has to be one of its owner's categories (400 otherwise)
+async fn ensure_own_category(conn: &mut PgConnection, category_id: i32, owner_id: i32) -> Result<(), AppError> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM categories WHERE id = $1 AND user_id = $2) AS "exists!""#,
+        category_id,
+        owner_id
+    )
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if !exists {
+        return Err(AppError::BadRequest("Unknown category id".to_string()));
+    }
+
+    Ok(())
+}
+
+// SELECT over the caller's todos matching the query, ready for an ORDER BY
+fn todo_select(
+    user_id: i32,
+    query: &TodoQuery,
+    trashed: bool,
+) -> Result<QueryBuilder<'static, Postgres>, AppError> {
+    let mut select = QueryBuilder::new(format!("SELECT *, {}", TAG_NAMES_COLUMN));
+    select.push(", user_id <> ").push_bind(user_id).push(" AS shared");
+    if let Some(q) = &query.q {
+        select
+            .push(", ts_headline('english', coalesce(title, '') || ' ' || coalesce(description, ''), plainto_tsquery('english', ")
+            .push_bind(q.clone())
+            .push(")) AS highlight");
+    }
+    select.push(" FROM todos");
+    push_todo_filters(&mut select, user_id, query, trashed)?;
+    Ok(select)
+}
+
+// One page of the caller's todos matching the query, plus the total count.
+// With after_id the page is the next per_page todos by id instead of an offset page.
+async fn query_todos(
+    pool: &PgPool,
+    user_id: i32,
+    query: &TodoQuery,
+    trashed: bool,
+) -> Result<PaginatedResponse<Todo>, AppError> {
+    if query.page.is_some() && query.after_id.is_some() {
+        return Err(AppError::BadRequest("page and after_id cannot be used together".to_string()));
+    }
+    let (page, per_page, offset) = page_bounds(query.page, query.per_page);
+
+    // One snapshot for the page and the count, so a concurrent write can't make them disagree
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let mut select = todo_select(user_id, query, trashed)?;
+    match query.after_id {
+        Some(after_id) => {
+            select
+                .push(" AND id > ")
+                .push_bind(after_id)
+                .push(" ORDER BY id ASC LIMIT ")
+                .push_bind(per_page as i64);
+        }
+        None => {
+            push_todo_order_by(&mut select, query)?;
+            select
+                .push(" LIMIT ")
+                .push_bind(per_page as i64)
+                .push(" OFFSET ")
+                .push_bind(offset);
+        }
+    }
+
+    let span = db_query_span(select.sql());
+    let todos = select
+        .build_query_as::<Todo>()
+        .fetch_all(&mut *tx)
+        .instrument(span.clone())
+        .await?;
+    span.record("db.rows_affected", todos.len());
+
+    let mut count = QueryBuilder::new("SELECT COUNT(*) FROM todos");
+    push_todo_filters(&mut count, user_id, query, trashed)?;
+
+    let span = db_query_span(count.sql());
+    let total: i64 = count
+        .build_query_scalar()
+        .fetch_one(&mut *tx)
+        .instrument(span.clone())
+        .await?;
+    span.record("db.rows_affected", 1);
+    tx.commit().await?;
+
+    let next_cursor = match query.after_id {
+        Some(_) if todos.len() == per_page as usize => todos.last().and_then(|todo| todo.id),
+        _ => None,
+    };
+
+    Ok(PaginatedResponse {
+        items: todos,
+        total,
+        page,
+        per_page,
+        next_cursor,
+    })
+}
+
+// Handler for fetching todos, one page at a time
+#[utoipa::path(
+    get,
+    path = "/todos",
+    tag = "todos",
+    params(TodoQuery),
+    responses(
+        (status = 200, description = "A page of the caller's todos", body = PaginatedResponse<Todo>),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_todos(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<TodoQuery>,
+) -> Result<HttpResponse, AppError> {
+    let page = query_todos(pool.get_ref(), auth.user_id, &query, false).await?;
+    Ok(paginated_response(&page))
+}
+
+// Handler for fetching another user's live todos, for admins (or the user themselves)
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/todos",
+    tag = "todos",
+    params(("user_id" = i32, Path, description = "User id"), TodoQuery),
+    responses(
+        (status = 200, description = "A page of the user's todos", body = PaginatedResponse<Todo>),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_user_todos(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    query: web::Query<TodoQuery>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.role != Role::Admin && auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM "Users" WHERE id = $1) AS "exists!""#,
+        user_id
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+
+    let page = query_todos(pool.get_ref(), user_id, &query, false).await?;
+    Ok(paginated_response(&page))
+}
+
+// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// The todos as CSV with a header row, optional values are left empty
+fn todos_csv(todos: &[Todo]) -> String {
+    let mut csv = String::from("id,title,description,completed,priority,due_date,created_at\r\n");
+    for todo in todos {
+        let fields = [
+            todo.id.map(|id| id.to_string()).unwrap_or_default(),
+            csv_field(todo.title.as_deref().unwrap_or_default()),
+            csv_field(todo.description.as_deref().unwrap_or_default()),
+            todo.completed.map(|completed| completed.to_string()).unwrap_or_default(),
+            todo.priority.map(|priority| priority.as_str().to_string()).unwrap_or_default(),
+            todo.due_date.map(|date| date.to_string()).unwrap_or_default(),
+            todo.created_at.map(|at| at.to_string()).unwrap_or_default(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+// Handler for downloading every todo matching the GET /todos filters, as CSV or JSON.
+// ?format= picks the format, otherwise the Accept header does, CSV by default.
+#[utoipa::path(
+    get,
+    path = "/todos/export",
+    tag = "todos",
+    params(TodoQuery, ExportQuery),
+    responses(
+        (status = 200, description = "Every matching todo", content((String = "text/csv"), (Vec<Todo> = "application/json"))),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn export_todos(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<TodoQuery>,
+    export: web::Query<ExportQuery>,
+) -> Result<HttpResponse, AppError> {
+    let format = match export.format.as_deref() {
+        Some(format) => format.parse::<ExportFormat>().map_err(AppError::BadRequest)?,
+        None => {
+            let accept = req.headers().get(ACCEPT).and_then(|value| value.to_str().ok()).unwrap_or_default();
+            if accept.contains("application/json") && !accept.contains("text/csv") {
+                ExportFormat::Json
+            } else {
+                ExportFormat::Csv
+            }
+        }
+    };
+
+    let mut select = todo_select(auth.user_id, &query, false)?;
+    push_todo_order_by(&mut select, &query)?;
+    let span = db_query_span(select.sql());
+    let todos = select
+        .build_query_as::<Todo>()
+        .fetch_all(pool.get_ref())
+        .instrument(span.clone())
+        .await?;
+    span.record("db.rows_affected", todos.len());
+
+    Ok(match format {
+        ExportFormat::Json => HttpResponse::Ok().json(todos),
+        ExportFormat::Csv => HttpResponse::Ok()
+            .content_type("text/csv")
+            .insert_header((CONTENT_DISPOSITION, "attachment; filename=\"todos.csv\""))
+            .body(todos_csv(&todos)),
+    })
+}
+
+// The todos with a due date as all-day calendar events. The UID is stable per todo so
+// importing the file again updates the events instead of duplicating them.
+fn todos_ics(todos: &[Todo]) -> String {
+    let mut calendar = Calendar::new();
+    calendar.name("Todos");
+    for todo in todos {
+        let (Some(id), Some(due_date)) = (todo.id, todo.due_date) else {
+            continue;
+        };
+        let mut event = Event::new();
+        event
+            .uid(&format!("todo-{}@todo-api", id))
+            .summary(todo.title.as_deref().unwrap_or_default())
+            .description(todo.description.as_deref().unwrap_or_default())
+            .starts(due_date)
+            .ends(due_date + Days::new(1)) // DTEND of an all-day event is exclusive
+            .add_property("STATUS", if todo.completed == Some(true) { "COMPLETED" } else { "NEEDS-ACTION" });
+        // RFC 5545 priorities run from 1 (highest) to 9 (lowest)
+        if let Some(priority) = todo.priority {
+            event.priority(match priority {
+                Priority::Critical => 1,
+                Priority::High => 3,
+                Priority::Medium => 5,
+                Priority::Low => 9,
+            });
+        }
+        calendar.push(event.done());
+    }
+    calendar.done().to_string()
+}
+
+// Handler for subscribing to due dates from a calendar app: every todo matching the GET /todos
+// filters that has a due date, as an iCalendar file
+#[utoipa::path(
+    get,
+    path = "/todos/export.ics",
+    tag = "todos",
+    params(TodoQuery),
+    responses(
+        (status = 200, description = "One VEVENT per matching todo with a due date", content_type = "text/calendar", body = String),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn export_todos_ics(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<TodoQuery>,
+) -> Result<HttpResponse, AppError> {
+    let mut select = todo_select(auth.user_id, &query, false)?;
+    push_todo_order_by(&mut select, &query)?;
+    let span = db_query_span(select.sql());
+    let todos = select
+        .build_query_as::<Todo>()
+        .fetch_all(pool.get_ref())
+        .instrument(span.clone())
+        .await?;
+    span.record("db.rows_affected", todos.len());
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .insert_header((CONTENT_DISPOSITION, "attachment; filename=\"todos.ics\""))
+        .body(todos_ics(&todos)))
+}
+
+// Handler for listing the caller's soft-deleted todos
+#[utoipa::path(
+    get,
+    path = "/todos/trash",
+    tag = "todos",
+    params(TodoQuery),
+    responses(
+        (status = 200, description = "A page of the caller's trashed todos", body = PaginatedResponse<Todo>),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_trash(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    query: web::Query<TodoQuery>,
+) -> Result<HttpResponse, AppError> {
+    let page = query_todos(pool.get_ref(), auth.user_id, &query, true).await?;
+    Ok(paginated_response(&page))
+}
+
+// Load a live todo with its tags, shares and subtask counts, None when it doesn't exist or is trashed
+pub(crate) async fn fetch_todo_response(
+    conn: &mut PgConnection,
+    todo_id: i32,
+) -> Result<Option<TodoResponse>, AppError> {
+    let row = sqlx::query!(
+        r#"SELECT todos.id, todos.user_id, todos.title, todos.completed, todos.description, todos.created_at,
+                  todos.updated_at, todos.due_date, todos.priority AS "priority: Priority", todos.archived,
+                  todos.position, todos.version, todos.recurrence_rule, todos.next_occurrence_at,
+                  categories.id AS "category_id?", categories.name AS "category_name?",
+                  categories.parent_id AS category_parent_id, categories.color AS category_color,
+                  (SELECT COUNT(*) FROM comments WHERE comments.todo_id = todos.id) AS "comment_count!",
+                  ARRAY(SELECT tags.name FROM todo_tags JOIN tags ON tags.id = todo_tags.tag_id
+                        WHERE todo_tags.todo_id = todos.id ORDER BY tags.name) AS "tags!",
+                  COALESCE(counts.total, 0) AS "subtask_count!",
+                  COALESCE(counts.completed, 0) AS "completed_subtask_count!",
+                  (SELECT COALESCE(SUM(EXTRACT(EPOCH FROM COALESCE(ended_at, NOW()) - started_at)), 0)::int8
+                   FROM time_entries WHERE time_entries.todo_id = todos.id) AS "total_tracked_seconds!",
+                  EXISTS (SELECT 1 FROM todo_notes WHERE todo_notes.todo_id = todos.id AND content <> '') AS "has_notes!"
+           FROM todos
+           LEFT JOIN (
+               SELECT todo_id, COUNT(*) AS total, COUNT(*) FILTER (WHERE completed) AS completed
+               FROM subtasks GROUP BY todo_id
+           ) AS counts ON counts.todo_id = todos.id
+           LEFT JOIN categories ON categories.id = todos.category_id
+           WHERE todos.id = $1 AND todos.deleted_at IS NULL"#,
+        todo_id
+    )
+        .fetch_optional(&mut *conn)
+        .await?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let shared_with = sqlx::query_as!(
+        ShareEntry,
+        "SELECT shared_with_user_id AS user_id, can_edit, created_at FROM todo_shares
+         WHERE todo_id = $1 ORDER BY created_at, shared_with_user_id",
+        todo_id
+    )
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(Some(TodoResponse {
+        id: row.id,
+        user_id: row.user_id,
+        title: row.title,
+        completed: row.completed,
+        description: row.description.unwrap_or_default(),
+        created_at: Some(row.created_at),
+        updated_at: Some(row.updated_at),
+        due_date: row.due_date,
+        priority: Some(row.priority),
+        tags: row.tags,
+        archived: row.archived,
+        position: row.position,
+        version: row.version,
+        recurrence_rule: row.recurrence_rule,
+        next_occurrence_at: row.next_occurrence_at,
+        comment_count: row.comment_count,
+        shared_with,
+        subtask_count: row.subtask_count,
+        completed_subtask_count: row.completed_subtask_count,
+        completion_percent: completion_percent(row.completed_subtask_count, row.subtask_count),
+        total_tracked_seconds: row.total_tracked_seconds,
+        category: row.category_id.zip(row.category_name).map(|(id, name)| CategoryResponse {
+            id,
+            name,
+            parent_id: row.category_parent_id,
+            color: row.category_color,
+        }),
+        has_notes: row.has_notes,
+    }))
+}
+
+// Handler for fetching a single todo, answers 304 when If-None-Match has the current ETag.
+// Sent as JSON:API when the client asks for application/vnd.api+json.
+#[utoipa::path(
+    get,
+    path = "/todos/{todo_id}",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The todo, with its ETag header", body = TodoResponse),
+        (status = 304, description = "Unchanged since the If-None-Match ETag"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_todo_by_id(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    check_todo_access(pool.get_ref(), todo_id, auth.user_id, false).await?;
+
+    let load = || async {
+        let mut conn = pool.acquire().await?;
+        fetch_todo_response(&mut conn, todo_id).await
+    };
+    let todo = match todo_cache(&req) {
+        Some(cache) => cache.get_or_load(todo_id, load).await?,
+        None => load().await?,
+    };
+    let todo = todo.ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+
+    let etag = todo_etag(&todo)?;
+    if etag_header_matches(req.headers(), IF_NONE_MATCH, &etag) == Some(true) {
+        return Ok(HttpResponse::NotModified().insert_header((ETAG, etag)).finish());
+    }
+    // Both representations share the ETag so If-Match works the same for either kind of client
+    let mut response = JsonApiResponder(todo).respond_to(&req);
+    response
+        .headers_mut()
+        .insert(ETAG, HeaderValue::from_str(&etag).expect("ETags are quoted hex"));
+    Ok(response)
+}
+
+// Handler for updating a todo, by its owner or a user it is shared with for editing. Only the
+// fields in the body change, 400 when it has none. Refused with 412 when If-Match doesn't have
+// the current ETag.
+#[utoipa::path(
+    patch,
+    path = "/todos/{todo_id}",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = UpdateTaskReq,
+    responses(
+        (status = 200, description = "The updated todo", body = Todo),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 409, description = "The version sent is no longer current", body = VersionConflictResponse),
+        (status = 412, description = "If-Match doesn't have the current ETag", body = ProblemDetails),
+        (status = 422, description = "Validation failed (ValidationErrorResponse), or completing a todo whose blockers are still open (BlockedResponse)", body = UnprocessableTodoResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_data: web::Json<UpdateTaskReq>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*todo_data)?;
+    let todo_id = todo_id.into_inner();
+    let owner_id = check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    if req.headers().contains_key(IF_MATCH) {
+        // Lock the row so nobody changes it between the comparison and our update
+        sqlx::query!("SELECT id FROM todos WHERE id = $1 FOR UPDATE", todo_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let current = fetch_todo_response(&mut tx, todo_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+        if etag_header_matches(req.headers(), IF_MATCH, &todo_etag(&current)?) == Some(false) {
+            return Err(AppError::PreconditionFailed("Todo has changed since it was fetched".to_string()));
+        }
+    }
+
+    let (updated_todo, completed_now) = write_todo_update(&mut tx, todo_id, owner_id, auth.user_id, &todo_data).await?;
+
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+
+    // The owner's webhooks hear about the change, also when a share editor made it
+    webhooks::dispatch(pool.get_ref(), owner_id, "todo.updated", &updated_todo);
+    if completed_now {
+        webhooks::dispatch(pool.get_ref(), owner_id, "todo.completed", &updated_todo);
+    }
+    events::publish(&req, owner_id, TodoEventKind::Updated, todo_id, Some(&updated_todo));
+
+    Ok(HttpResponse::Ok().json(updated_todo)) // Return updated todo
+}
+
+// Set only the completed flag of a todo the caller may edit, with the same checks, activity,
+// webhooks, events and cache invalidation as a full update
+async fn set_completed(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: i32,
+    completed: bool,
+) -> Result<HttpResponse, AppError> {
+    let owner_id = check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let was_completed = sqlx::query_scalar!(
+        "SELECT completed FROM todos WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        todo_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+    let completed_now = completed && !was_completed;
+    if completed_now {
+        ensure_unblocked(&mut tx, &[todo_id]).await?;
+    }
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    let updated_todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET completed = $1, version = version + 1
+         WHERE id = $2 AND user_id = $3
+         RETURNING *, {}, user_id <> $4 AS shared",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(completed)
+        .bind(todo_id)
+        .bind(owner_id)
+        .bind(auth.user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    record_todo_activity(&mut *tx, auth.user_id, ActivityAction::Updated, todo_id, json!({})).await?;
+    if completed_now {
+        record_todo_activity(&mut *tx, auth.user_id, ActivityAction::Completed, todo_id, json!({})).await?;
+    }
+
+    let response = fetch_todo_response(&mut tx, todo_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+
+    webhooks::dispatch(pool.get_ref(), owner_id, "todo.updated", &updated_todo);
+    if completed_now {
+        webhooks::dispatch(pool.get_ref(), owner_id, "todo.completed", &updated_todo);
+    }
+    events::publish(&req, owner_id, TodoEventKind::Updated, todo_id, Some(&updated_todo));
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Handler for completing a todo without sending a body
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/complete",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The completed todo", body = TodoResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 422, description = "The todo's blockers are still open", body = BlockedResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn complete_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    set_completed(auth, req, pool, todo_id.into_inner(), true).await
+}
+
+// Handler for reopening a todo without sending a body
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/incomplete",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The reopened todo", body = TodoResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn incomplete_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    set_completed(auth, req, pool, todo_id.into_inner(), false).await
+}
+
+// Handler for putting a todo off: the due date moves back by the duration, counted from today
+// when the todo has no due date
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/snooze",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = SnoozeReq,
+    responses(
+        (status = 200, description = "The todo with its new due date", body = TodoResponse),
+        (status = 400, description = "Unknown duration", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn snooze_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    body: web::Json<SnoozeReq>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let owner_id = check_todo_access(pool.get_ref(), todo_id, auth.user_id, true).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let due_date = sqlx::query_scalar!(
+        "SELECT due_date FROM todos WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        todo_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+    let snoozed_until = body
+        .duration
+        .after(due_date.unwrap_or_else(|| Utc::now().date_naive()))
+        .ok_or_else(|| AppError::BadRequest("The due date can't be moved that far".to_string()))?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    let updated_todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET due_date = $1, version = version + 1
+         WHERE id = $2
+         RETURNING *, {}, user_id <> $3 AS shared",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(snoozed_until)
+        .bind(todo_id)
+        .bind(auth.user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    record_todo_activity(
+        &mut *tx,
+        auth.user_id,
+        ActivityAction::Snoozed,
+        todo_id,
+        json!({ "duration": body.duration, "from": due_date, "to": snoozed_until }),
+    )
+    .await?;
+
+    let response = fetch_todo_response(&mut tx, todo_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+
+    webhooks::dispatch(pool.get_ref(), owner_id, "todo.updated", &updated_todo);
+    events::publish(&req, owner_id, TodoEventKind::Updated, todo_id, Some(&updated_todo));
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// Apply an update to a todo of `owner_id` on behalf of `user_id` (the owner or a share editor)
+// and return it as that user sees it, with whether this update completed it.
+// Access has to be checked by the caller.
+pub(crate) async fn write_todo_update(
+    conn: &mut PgConnection,
+    todo_id: i32,
+    owner_id: i32,
+    user_id: i32,
+    todo_data: &UpdateTaskReq,
+) -> Result<(Todo, bool), AppError> {
+    let nothing_to_update = todo_data.title.is_none()
+        && todo_data.completed.is_none()
+        && todo_data.description.is_none()
+        && todo_data.due_date.is_none()
+        && todo_data.priority.is_none()
+        && todo_data.tag_ids.is_none()
+        && todo_data.category_id.is_none();
+    if nothing_to_update {
+        return Err(AppError::BadRequest("Nothing to update, the body has none of the todo's fields".to_string()));
+    }
+
+    let was_completed = sqlx::query_scalar!("SELECT completed FROM todos WHERE id = $1", todo_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .unwrap_or(false);
+    if todo_data.completed == Some(true) && !was_completed {
+        ensure_unblocked(conn, &[todo_id]).await?;
+    }
+
+    // Categories belong to the owner too, like tags
+    if let Some(Some(category_id)) = todo_data.category_id {
+        ensure_own_category(conn, category_id, owner_id).await?;
+    }
+    set_audit_actor(&mut *conn, user_id).await?;
+
+    // Only the columns the body has, the others keep their values
+    let mut update = QueryBuilder::<Postgres>::new("UPDATE todos SET version = version + 1");
+    if let Some(title) = &todo_data.title {
+        update.push(", title = ").push_bind(title.clone());
+    }
+    if let Some(completed) = todo_data.completed {
+        update.push(", completed = ").push_bind(completed);
+    }
+    if let Some(description) = &todo_data.description {
+        update.push(", description = ").push_bind(description.clone());
+    }
+    if let Some(due_date) = todo_data.due_date {
+        update.push(", due_date = ").push_bind(due_date);
+    }
+    if let Some(priority) = todo_data.priority {
+        update.push(", priority = ").push_bind(priority);
+    }
+    if let Some(category_id) = todo_data.category_id {
+        update.push(", category_id = ").push_bind(category_id);
+    }
+    update
+        .push(" WHERE id = ")
+        .push_bind(todo_id)
+        .push(" AND user_id = ")
+        .push_bind(owner_id)
+        .push(" AND deleted_at IS NULL");
+    if let Some(version) = todo_data.version {
+        // Still the version the client read
+        update.push(" AND version = ").push_bind(version);
+    }
+    let result = update.build().execute(&mut *conn).await?;
+
+    if result.rows_affected() == 0 {
+        let current_version = sqlx::query_scalar!(
+            "SELECT version FROM todos WHERE id = $1 AND deleted_at IS NULL",
+            todo_id
+        )
+            .fetch_optional(&mut *conn)
+            .await?;
+        return Err(match current_version {
+            Some(current_version) => AppError::VersionConflict(current_version),
+            None => AppError::NotFound("Todo not found".to_string()),
+        });
+    }
+
+    // Tags belong to the owner, also when a share recipient edits the todo
+    if let Some(tag_ids) = &todo_data.tag_ids {
+        set_todo_tags(conn, todo_id, owner_id, tag_ids).await?;
+    }
+
+    // Fetch the updated todo to return it in the response
+    let updated_todo = sqlx::query_as::<_, Todo>(&format!(
+        "SELECT *, {}, user_id <> $2 AS shared FROM todos WHERE id = $1",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(todo_id)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    record_todo_activity(&mut *conn, user_id, ActivityAction::Updated, todo_id, json!({})).await?;
+    let completed_now = updated_todo.completed == Some(true) && !was_completed;
+    if completed_now {
+        record_todo_activity(&mut *conn, user_id, ActivityAction::Completed, todo_id, json!({})).await?;
+    }
+
+    Ok((updated_todo, completed_now))
+}
+
+// Handler for changing the status of many of the caller's todos at once, e.g. completing
+// everything on screen. Either every id is updated or none: 403 if any belongs to someone else,
+// 404 if any doesn't exist or is in the trash.
+#[utoipa::path(
+    patch,
+    path = "/todos/bulk",
+    tag = "todos",
+    request_body = BulkUpdateReq,
+    responses(
+        (status = 200, description = "How many todos were updated", body = BulkUpdateResponse),
+        (status = 400, description = "Nothing to update", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Some todo belongs to another user", body = ProblemDetails),
+        (status = 404, description = "Some todo doesn't exist", body = ProblemDetails),
+        (status = 422, description = "Validation failed (ValidationErrorResponse), or some todo's blockers are still open (BlockedResponse)", body = UnprocessableTodoResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn bulk_update_todos(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    bulk: web::Json<BulkUpdateReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*bulk)?;
+    if bulk.update.completed.is_none() && bulk.update.archived.is_none() {
+        return Err(AppError::BadRequest("Set completed or archived in update".to_string()));
+    }
+
+    let mut ids = bulk.ids.clone();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    // Lock the rows, and remember which were open to tell completions apart
+    let current = sqlx::query!(
+        "SELECT id, user_id, completed FROM todos WHERE id = ANY($1) AND deleted_at IS NULL FOR UPDATE",
+        &ids
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+    if current.iter().any(|todo| todo.user_id != auth.user_id) {
+        return Err(AppError::Forbidden);
+    }
+    if current.len() < ids.len() {
+        return Err(AppError::NotFound("Todo not found".to_string()));
+    }
+    if bulk.update.completed == Some(true) {
+        let completing: Vec<i32> = current.iter().filter(|todo| !todo.completed).map(|todo| todo.id).collect();
+        ensure_unblocked(&mut tx, &completing).await?;
+    }
+
+    let updated = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET completed = COALESCE($3, completed), archived = COALESCE($4, archived), version = version + 1
+         WHERE id = ANY($1) AND user_id = $2
+         RETURNING *, {}",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(&ids)
+        .bind(auth.user_id)
+        .bind(bulk.update.completed)
+        .bind(bulk.update.archived)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut completed_now = Vec::new();
+    for todo in &updated {
+        let todo_id = todo.id.unwrap_or_default();
+        record_todo_activity(&mut *tx, auth.user_id, ActivityAction::Updated, todo_id, json!({})).await?;
+
+        let was_completed = current.iter().any(|row| row.id == todo_id && row.completed);
+        if todo.completed == Some(true) && !was_completed {
+            record_todo_activity(&mut *tx, auth.user_id, ActivityAction::Completed, todo_id, json!({})).await?;
+            completed_now.push(todo_id);
+        }
+    }
+
+    tx.commit().await?;
+
+    for todo in &updated {
+        let todo_id = todo.id.unwrap_or_default();
+        invalidate_todo(&req, todo_id).await;
+        webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.updated", todo);
+        if completed_now.contains(&todo_id) {
+            webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.completed", todo);
+        }
+        events::publish(&req, auth.user_id, TodoEventKind::Updated, todo_id, Some(todo));
+    }
+
+    Ok(HttpResponse::Ok().json(BulkUpdateResponse { updated: updated.len() as u64 }))
+}
+
+// Flip every live todo of the caller's that isn't in the wanted state yet, with the same
+// activity, webhooks, events and cache invalidations as updating them one by one. Blockers
+// always have the same owner, so completing all of them never leaves one blocked.
+async fn set_all_completed(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    completed: bool,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    let updated = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET completed = $2, version = version + 1
+         WHERE user_id = $1 AND completed <> $2 AND deleted_at IS NULL
+         RETURNING *, {}",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(auth.user_id)
+        .bind(completed)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    for todo in &updated {
+        let todo_id = todo.id.unwrap_or_default();
+        record_todo_activity(&mut *tx, auth.user_id, ActivityAction::Updated, todo_id, json!({})).await?;
+        if completed {
+            record_todo_activity(&mut *tx, auth.user_id, ActivityAction::Completed, todo_id, json!({})).await?;
+        }
+    }
+
+    tx.commit().await?;
+
+    for todo in &updated {
+        let todo_id = todo.id.unwrap_or_default();
+        invalidate_todo(&req, todo_id).await;
+        webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.updated", todo);
+        if completed {
+            webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.completed", todo);
+        }
+        events::publish(&req, auth.user_id, TodoEventKind::Updated, todo_id, Some(todo));
+    }
+
+    Ok(HttpResponse::Ok().json(BulkUpdateResponse { updated: updated.len() as u64 }))
+}
+
+// Handler for completing every open todo of the caller's, archived ones included
+#[utoipa::path(
+    post,
+    path = "/todos/complete-all",
+    tag = "todos",
+    responses(
+        (status = 200, description = "How many todos were completed", body = BulkUpdateResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn complete_all_todos(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    set_all_completed(auth, req, pool, true).await
+}
+
+// Handler for reopening every completed todo of the caller's
+#[utoipa::path(
+    post,
+    path = "/todos/incomplete-all",
+    tag = "todos",
+    responses(
+        (status = 200, description = "How many todos were reopened", body = BulkUpdateResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn incomplete_all_todos(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    set_all_completed(auth, req, pool, false).await
+}
+
+// Handler for clearing the caller's completed todos, into the trash or with `permanent=true`
+// for good. Needs `confirm=true`. The audit log gets one entry for all of them.
+#[utoipa::path(
+    delete,
+    path = "/todos/completed",
+    tag = "todos",
+    params(ClearCompletedQuery),
+    responses(
+        (status = 200, description = "How many todos were deleted", body = ClearCompletedResponse),
+        (status = 400, description = "confirm=true is missing", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn clear_completed_todos(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<ClearCompletedQuery>,
+) -> Result<HttpResponse, AppError> {
+    if query.confirm != Some(true) {
+        return Err(AppError::BadRequest("Pass confirm=true to delete every completed todo".to_string()));
+    }
+    let permanent = query.permanent.unwrap_or(false);
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    skip_row_audit(&mut *tx).await?;
+
+    let deleted_ids = if permanent {
+        sqlx::query_scalar!(
+            "DELETE FROM todos WHERE user_id = $1 AND completed = true AND deleted_at IS NULL RETURNING id",
+            auth.user_id
+        )
+            .fetch_all(&mut *tx)
+            .await?
+    } else {
+        sqlx::query_scalar!(
+            "UPDATE todos SET deleted_at = NOW()
+             WHERE user_id = $1 AND completed = true AND deleted_at IS NULL
+             RETURNING id",
+            auth.user_id
+        )
+            .fetch_all(&mut *tx)
+            .await?
+    };
+
+    let count = deleted_ids.len() as u64;
+    if count > 0 {
+        record_bulk_change(
+            &mut *tx,
+            "todos",
+            "bulk_delete",
+            auth.user_id,
+            json!({ "count": count, "permanent": permanent }),
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    TODOS_DELETED_TOTAL.inc_by(count);
+    for todo_id in deleted_ids {
+        invalidate_todo(&req, todo_id).await;
+        events::publish::<Todo>(&req, auth.user_id, TodoEventKind::Deleted, todo_id, None);
+    }
+
+    Ok(HttpResponse::Ok().json(ClearCompletedResponse { deleted: count }))
+}
+
+// Handler for deleting a todo, it goes to the trash and can be restored
+#[utoipa::path(
+    delete,
+    path = "/todos/{todo_id}",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 204, description = "Moved to the trash"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,  // Don't destructure here
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();  // Extract the value here
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    trash_todo(&mut tx, todo_id, auth.user_id).await?;
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+
+    TODOS_DELETED_TOTAL.inc();
+    events::publish::<Todo>(&req, auth.user_id, TodoEventKind::Deleted, todo_id, None);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Move one of the user's todos to the trash
+pub(crate) async fn trash_todo(conn: &mut PgConnection, todo_id: i32, user_id: i32) -> Result<(), AppError> {
+    check_todo_owner(&mut *conn, todo_id, user_id).await?;
+    set_audit_actor(&mut *conn, user_id).await?;
+
+    let result = sqlx::query!(
+        "UPDATE todos SET deleted_at = NOW() WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+        todo_id,
+        user_id
+    )
+        .execute(&mut *conn)
+        .await?;
+
+    // The row may have gone between the ownership check and the delete
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Todo not found".to_string()));
+    }
+
+    record_todo_activity(&mut *conn, user_id, ActivityAction::Deleted, todo_id, json!({})).await?;
+
+    Ok(())
+}
+
+// Handler for taking a todo back out of the trash
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/restore",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The restored todo", body = Todo),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn restore_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET deleted_at = NULL
+         WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL
+         RETURNING *, {}",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(todo_id)
+        .bind(auth.user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found in trash".to_string()))?;
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+    // Back from the trash, so to a stream it's a new todo again
+    events::publish(&req, auth.user_id, TodoEventKind::Created, todo_id, Some(&todo));
+
+    Ok(HttpResponse::Ok().json(todo))
+}
+
+// Archive or unarchive one of the caller's live todos and return it
+async fn set_todo_archived(
+    pool: &PgPool,
+    req: &HttpRequest,
+    todo_id: i32,
+    user_id: i32,
+    archived: bool,
+) -> Result<Todo, AppError> {
+    check_todo_owner(pool, todo_id, user_id).await?;
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, user_id, req).await?;
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET archived = $1
+         WHERE id = $2 AND user_id = $3 AND deleted_at IS NULL
+         RETURNING *, {}",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(archived)
+        .bind(todo_id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+    tx.commit().await?;
+
+    Ok(todo)
+}
+
+// Handler for hiding a todo from the default listing without deleting it
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/archive",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The archived todo", body = Todo),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn archive_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let todo = set_todo_archived(pool.get_ref(), &req, todo_id, auth.user_id, true).await?;
+    invalidate_todo(&req, todo_id).await;
+    events::publish(&req, auth.user_id, TodoEventKind::Updated, todo_id, Some(&todo));
+    Ok(HttpResponse::Ok().json(todo))
+}
+
+// Handler for bringing an archived todo back into the default listing
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/unarchive",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 200, description = "The unarchived todo", body = Todo),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn unarchive_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let todo = set_todo_archived(pool.get_ref(), &req, todo_id, auth.user_id, false).await?;
+    invalidate_todo(&req, todo_id).await;
+    events::publish(&req, auth.user_id, TodoEventKind::Updated, todo_id, Some(&todo));
+    Ok(HttpResponse::Ok().json(todo))
+}
+
+// Position of one of the user's live todos, named in a move request
+async fn neighbour_position(
+    tx: &mut Transaction<'_, Postgres>,
+    todo_id: i32,
+    user_id: i32,
+) -> Result<f64, AppError> {
+    sqlx::query_scalar!(
+        "SELECT position FROM todos WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL",
+        todo_id,
+        user_id
+    )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::BadRequest(format!("Unknown todo id {} in move request", todo_id)))
+}
+
+// Positions the moved todo has to land between. With one neighbour the other side is the
+// next todo in the list, or at either end a bound two steps away so the todo lands one step out.
+async fn move_bounds(
+    tx: &mut Transaction<'_, Postgres>,
+    todo_id: i32,
+    user_id: i32,
+    target: &MoveTodoReq,
+) -> Result<(f64, f64), AppError> {
+    match (target.after_id, target.before_id) {
+        (Some(after_id), Some(before_id)) => Ok((
+            neighbour_position(tx, after_id, user_id).await?,
+            neighbour_position(tx, before_id, user_id).await?,
+        )),
+        (Some(after_id), None) => {
+            let low = neighbour_position(tx, after_id, user_id).await?;
+            let high = sqlx::query_scalar!(
+                "SELECT MIN(position) FROM todos
+                 WHERE user_id = $1 AND deleted_at IS NULL AND position > $2 AND id <> $3",
+                user_id,
+                low,
+                todo_id
+            )
+                .fetch_one(&mut **tx)
+                .await?;
+            Ok((low, high.unwrap_or(low + 2.0)))
+        }
+        (None, Some(before_id)) => {
+            let high = neighbour_position(tx, before_id, user_id).await?;
+            let low = sqlx::query_scalar!(
+                "SELECT MAX(position) FROM todos
+                 WHERE user_id = $1 AND deleted_at IS NULL AND position < $2 AND id <> $3",
+                user_id,
+                high,
+                todo_id
+            )
+                .fetch_one(&mut **tx)
+                .await?;
+            Ok((low.unwrap_or(high - 2.0), high))
+        }
+        (None, None) => Err(AppError::BadRequest("after_id or before_id is required".to_string())),
+    }
+}
+
+// Handler for moving a todo between two others in the caller's manual order
+#[utoipa::path(
+    patch,
+    path = "/todos/{todo_id}/move",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = MoveTodoReq,
+    responses(
+        (status = 200, description = "The moved todo", body = Todo),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn move_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    target: web::Json<MoveTodoReq>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    if target.after_id == Some(todo_id) || target.before_id == Some(todo_id) {
+        return Err(AppError::BadRequest("A todo can't be moved next to itself".to_string()));
+    }
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    // Lock the caller's todos so concurrent moves don't pick the same gap
+    let user_todo_ids = sqlx::query_scalar!("SELECT id FROM todos WHERE user_id = $1 FOR UPDATE", auth.user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let (mut low, mut high) = move_bounds(&mut tx, todo_id, auth.user_id, &target).await?;
+
+    // Out of room between the neighbours: space all the user's todos evenly and look again
+    let respaced = high - low < f64::EPSILON * 2.0;
+    if respaced {
+        sqlx::query!(
+            "UPDATE todos SET position = ranked.rank
+             FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY position, id)::float8 AS rank
+                   FROM todos WHERE user_id = $1) AS ranked
+             WHERE todos.id = ranked.id",
+            auth.user_id
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        (low, high) = move_bounds(&mut tx, todo_id, auth.user_id, &target).await?;
+    }
+
+    if low >= high {
+        return Err(AppError::BadRequest("after_id must come before before_id".to_string()));
+    }
+
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET position = $1 WHERE id = $2 RETURNING *, {}",
+        TAG_NAMES_COLUMN
+    ))
+        .bind((low + high) / 2.0)
+        .bind(todo_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    if respaced {
+        for todo_id in user_todo_ids {
+            invalidate_todo(&req, todo_id).await;
+        }
+    } else {
+        invalidate_todo(&req, todo_id).await;
+    }
+    // Respacing keeps the order of the others, only this one moved
+    events::publish(&req, auth.user_id, TodoEventKind::Updated, todo_id, Some(&todo));
+
+    Ok(HttpResponse::Ok().json(todo))
+}
+
+// Handler for making a todo repeat, or stop repeating with a null rule. Once the todo is completed
+// and its next occurrence is due, the hourly job creates an open copy that carries the rule on.
+#[utoipa::path(
+    patch,
+    path = "/todos/{todo_id}/recurrence",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = RecurrenceReq,
+    responses(
+        (status = 200, description = "The todo with its new schedule", body = Todo),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails),
+        (status = 422, description = "Invalid rule", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn set_todo_recurrence(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    recurrence: web::Json<RecurrenceReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*recurrence)?;
+
+    let todo_id = todo_id.into_inner();
+    check_todo_owner(pool.get_ref(), todo_id, auth.user_id).await?;
+
+    // The first copy is due one interval from now
+    let next_occurrence_at = match &recurrence.rule {
+        Some(rule) => RecurrenceRule::parse(rule)
+            .map_err(AppError::BadRequest)?
+            .next_after(Utc::now()),
+        None => None,
+    };
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    let todo = sqlx::query_as::<_, Todo>(&format!(
+        "UPDATE todos SET recurrence_rule = $1, next_occurrence_at = $2
+         WHERE id = $3 AND deleted_at IS NULL
+         RETURNING *, {}",
+        TAG_NAMES_COLUMN
+    ))
+        .bind(recurrence.rule.as_deref().map(str::trim))
+        .bind(next_occurrence_at)
+        .bind(todo_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+    events::publish(&req, auth.user_id, TodoEventKind::Updated, todo_id, Some(&todo));
+
+    Ok(HttpResponse::Ok().json(todo))
+}
+
+// Handler for copying one of the caller's todos, with its tags and (unless with_subtasks is false)
+// its subtasks. The copy starts open and unarchived at the end of the list, titled "Copy of ...".
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/duplicate",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body(content = Option<DuplicateTodoReq>, description = "Optional, subtasks are copied by default"),
+    responses(
+        (status = 201, description = "The copy", body = TodoResponse, headers(("Location" = String, description = "Path of the new todo"))),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn duplicate_todo(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    options: Option<web::Json<DuplicateTodoReq>>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let with_subtasks = options.and_then(|options| options.with_subtasks).unwrap_or(true);
+
+    let mut tx = pool.begin().await?;
+    check_todo_owner(&mut *tx, todo_id, auth.user_id).await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    // FOR SHARE keeps the source from changing halfway through the copy
+    let source = sqlx::query!(
+        r#"SELECT title, description, due_date, priority AS "priority: Priority", category_id
+           FROM todos WHERE id = $1 FOR SHARE"#,
+        todo_id
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+    let tag_ids = sqlx::query_scalar!("SELECT tag_id FROM todo_tags WHERE todo_id = $1", todo_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let copy = NewTodo {
+        title: Some(format!("Copy of {}", source.title)),
+        description: source.description,
+        completed: Some(false),
+        priority: Some(source.priority),
+        due_date: source.due_date,
+        tag_ids: Some(tag_ids),
+        category_id: source.category_id,
+    };
+    let copy_id = insert_todo(&mut tx, auth.user_id, &copy).await?;
+
+    if with_subtasks {
+        sqlx::query!(
+            "INSERT INTO subtasks (todo_id, title, completed, position)
+             SELECT $1, title, false, position FROM subtasks WHERE todo_id = $2 ORDER BY position, id",
+            copy_id,
+            todo_id
+        )
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let response = fetch_todo_response(&mut tx, copy_id)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Duplicated todo could not be read back".to_string()))?;
+
+    tx.commit().await?;
+
+    TODOS_CREATED_TOTAL.inc();
+    webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.created", &response);
+    events::publish(&req, auth.user_id, TodoEventKind::Created, copy_id, Some(&response));
+
+    Ok(created_response(&req, &format!("/todos/{}", response.id), &response))
+}
+
+// Handler for deleting a todo for good, trashed or not (admins only)
+#[utoipa::path(
+    delete,
+    path = "/todos/{todo_id}/permanent",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    responses(
+        (status = 204, description = "Deleted for good"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn purge_todo(
+    admin: AdminGuard,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, admin.0.user_id, &req).await?;
+    let owner_id = sqlx::query_scalar!(r#"DELETE FROM todos WHERE id = $1 RETURNING user_id AS "user_id!""#, todo_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+    events::publish::<Todo>(&req, owner_id, TodoEventKind::Deleted, todo_id, None);
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler for giving a todo to another user, e.g. after merging accounts (admins only).
+// Tags are per user, so the todo gets the target user's tags of the same names, created when
+// missing. The category and the dependencies belong to the previous owner's todos and are dropped.
+#[utoipa::path(
+    post,
+    path = "/todos/{todo_id}/move-to-user",
+    tag = "todos",
+    params(("todo_id" = i32, Path, description = "Todo id")),
+    request_body = MoveToUserReq,
+    responses(
+        (status = 200, description = "The todo with its new owner", body = TodoResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "Todo or target user not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn move_todo_to_user(
+    admin: AdminGuard,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    todo_id: web::Path<i32>,
+    body: web::Json<MoveToUserReq>,
+) -> Result<HttpResponse, AppError> {
+    let todo_id = todo_id.into_inner();
+    let target_user_id = body.target_user_id;
+
+    let mut tx = pool.begin().await?;
+
+    let previous_owner_id = sqlx::query_scalar!(
+        "SELECT user_id FROM todos WHERE id = $1 AND deleted_at IS NULL FOR UPDATE",
+        todo_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+    sqlx::query_scalar!(
+        r#"SELECT id FROM "Users" WHERE id = $1 AND deleted_at IS NULL FOR SHARE"#,
+        target_user_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Target user not found".to_string()))?;
+
+    if previous_owner_id != target_user_id {
+        set_audit_context(&mut tx, admin.0.user_id, &req).await?;
+
+        // Swap the previous owner's tags for the target user's tags of the same names
+        sqlx::query!(
+            "INSERT INTO tags (user_id, name, color)
+             SELECT $2, tags.name, tags.color FROM todo_tags JOIN tags ON tags.id = todo_tags.tag_id
+             WHERE todo_tags.todo_id = $1 AND tags.user_id <> $2
+             ON CONFLICT (user_id, name) DO NOTHING",
+            todo_id,
+            target_user_id
+        )
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "INSERT INTO todo_tags (todo_id, tag_id)
+             SELECT $1, target.id FROM todo_tags
+             JOIN tags ON tags.id = todo_tags.tag_id
+             JOIN tags target ON target.user_id = $2 AND target.name = tags.name
+             WHERE todo_tags.todo_id = $1 AND tags.user_id <> $2
+             ON CONFLICT DO NOTHING",
+            todo_id,
+            target_user_id
+        )
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "DELETE FROM todo_tags USING tags
+             WHERE todo_tags.tag_id = tags.id AND todo_tags.todo_id = $1 AND tags.user_id <> $2",
+            todo_id,
+            target_user_id
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            "DELETE FROM todo_dependencies WHERE blocker_id = $1 OR blocked_id = $1",
+            todo_id
+        )
+            .execute(&mut *tx)
+            .await?;
+        // The new owner doesn't need a share of their own todo
+        sqlx::query!(
+            "DELETE FROM todo_shares WHERE todo_id = $1 AND shared_with_user_id = $2",
+            todo_id,
+            target_user_id
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            "UPDATE todos SET user_id = $1, category_id = NULL, version = version + 1,
+                 position = (SELECT COALESCE(MAX(position), 0) + 1 FROM todos WHERE user_id = $1)
+             WHERE id = $2",
+            target_user_id,
+            todo_id
+        )
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    let response = fetch_todo_response(&mut tx, todo_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Todo not found".to_string()))?;
+
+    tx.commit().await?;
+    invalidate_todo(&req, todo_id).await;
+
+    if previous_owner_id != target_user_id {
+        webhooks::dispatch(pool.get_ref(), target_user_id, "todo.updated", &response);
+        events::publish::<Todo>(&req, previous_owner_id, TodoEventKind::Deleted, todo_id, None);
+        events::publish(&req, target_user_id, TodoEventKind::Created, todo_id, Some(&response));
+    }
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+
+// Insert a todo for the user with its tags, returns the new id
+pub(crate) async fn insert_todo(
+    conn: &mut PgConnection,
+    user_id: i32,
+    new_todo: &NewTodo,
+) -> Result<i32, AppError> {
+    if let Some(category_id) = new_todo.category_id {
+        ensure_own_category(conn, category_id, user_id).await?;
+    }
+    set_audit_actor(&mut *conn, user_id).await?;
+
+    let todo_id = sqlx::query_scalar!(
+        "INSERT INTO todos (title, completed, description, due_date, priority, user_id, position, category_id)
+         VALUES ($1, $2, $3, $4, $5, $6, (SELECT COALESCE(MAX(position), 0) + 1 FROM todos WHERE user_id = $6), $7)
+         RETURNING id",
+        new_todo.title.clone().unwrap_or_else(|| "Untitled".to_string()),
+        new_todo.completed.unwrap_or(false),
+        new_todo.description.clone().unwrap_or_else(|| "".to_string()),
+        new_todo.due_date,
+        new_todo.priority.unwrap_or(Priority::Medium) as Priority,
+        user_id,
+        new_todo.category_id,
+    )
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if let Some(tag_ids) = &new_todo.tag_ids {
+        set_todo_tags(conn, todo_id, user_id, tag_ids).await?;
+    }
+
+    record_todo_activity(&mut *conn, user_id, ActivityAction::Created, todo_id, json!({})).await?;
+
+    Ok(todo_id)
+}
+
+// Create the todo, and save the response under the idempotency key in the same transaction
+async fn create_todo_response(
+    pool: &PgPool,
+    req: &HttpRequest,
+    user_id: i32,
+    new_todo: &NewTodo,
+    idempotency_key: Option<&str>,
+) -> Result<TodoResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, user_id, req).await?;
+
+    let todo_id = insert_todo(&mut tx, user_id, new_todo).await?;
+    let response = fetch_todo_response(&mut tx, todo_id)
+        .await?
+        .ok_or_else(|| AppError::InternalError("Created todo could not be read back".to_string()))?;
+
+    if let Some(key) = idempotency_key {
+        let body = serde_json::to_value(&response).map_err(|e| AppError::InternalError(e.to_string()))?;
+        idempotency::store_response(&mut tx, key, StatusCode::CREATED, &body).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(response)
+}
+
+// Handler for creating a new todo. A retry carrying the same Idempotency-Key within
+// 24 hours gets the first response back instead of creating a duplicate.
+#[utoipa::path(
+    post,
+    path = "/todos",
+    tag = "todos",
+    params(("Idempotency-Key" = Option<String>, Header, description = "UUID identifying this request across retries")),
+    request_body = NewTodo,
+    responses(
+        (status = 201, description = "The created todo, or the cached response to the same Idempotency-Key", body = TodoResponse, headers(("Location" = String, description = "Path of the new todo"))),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 409, description = "A request with the same Idempotency-Key is still in progress", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_todo(
+    req: HttpRequest,
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    new_todo: web::Json<NewTodo>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_todo)?;
+
+    let key = idempotency_key(&req)?;
+    if let Some(key) = &key {
+        if let Claim::Replay(response) = idempotency::claim(pool.get_ref(), key, auth.user_id).await? {
+            return Ok(response);
+        }
+    }
+
+    let result = create_todo_response(pool.get_ref(), &req, auth.user_id, &new_todo, key.as_deref()).await;
+    if let (Err(_), Some(key)) = (&result, &key) {
+        idempotency::release(pool.get_ref(), key).await?;
+    }
+    let response = result?;
+
+    TODOS_CREATED_TOTAL.inc();
+    webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.created", &response);
+    events::publish(&req, auth.user_id, TodoEventKind::Created, response.id, Some(&response));
+
+    Ok(created_response(&req, &format!("/todos/{}", response.id), &response))
+}
+
+// Handler for bulk-creating todos from a CSV or JSON file sent as the multipart field `file`.
+// All rows go in one transaction: any bad row rolls the import back (422 with the report)
+// unless ?partial=true, then bad rows are skipped and the rest is kept.
+#[utoipa::path(
+    post,
+    path = "/todos/import",
+    tag = "todos",
+    params(ImportQuery),
+    request_body(content = String, content_type = "multipart/form-data", description = "A CSV or JSON file in the `file` field"),
+    responses(
+        (status = 200, description = "Import report", body = ImportReport),
+        (status = 400, description = "Invalid request", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 422, description = "Some rows failed, nothing was imported", body = ImportReport)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn import_todos(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> Result<HttpResponse, AppError> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let file = multipart_file(content_type, &body, "file")?;
+    let rows = parse_rows(detect_format(&file)?, &file.data)?;
+
+    let mut report = ImportReport {
+        imported: 0,
+        skipped: 0,
+        errors: Vec::new(),
+    };
+
+    let mut created_ids = Vec::new();
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    for (index, row) in rows.into_iter().enumerate() {
+        let result = match row {
+            Ok(new_todo) => {
+                // A savepoint per row so a failed one can be dropped without losing the others
+                let mut savepoint = tx.begin().await?;
+                let inserted = insert_todo(&mut savepoint, auth.user_id, &new_todo).await;
+                if inserted.is_ok() {
+                    savepoint.commit().await?;
+                }
+                inserted
+            }
+            Err(reason) => Err(AppError::BadRequest(reason)),
+        };
+
+        match result {
+            Ok(todo_id) => {
+                report.imported += 1;
+                created_ids.push(todo_id);
+            }
+            Err(AppError::BadRequest(reason)) => {
+                report.skipped += 1;
+                report.errors.push(ImportRowError { row: index + 1, reason });
+            }
+            // Only the row's savepoint is rolled back, so the rest of the file carries on
+            Err(e @ AppError::DuplicateTitle) => {
+                report.skipped += 1;
+                report.errors.push(ImportRowError { row: index + 1, reason: e.to_string() });
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    if !report.errors.is_empty() && query.partial != Some(true) {
+        // Dropping `tx` rolls back every row that did go in
+        report.skipped += report.imported;
+        report.imported = 0;
+        return Ok(HttpResponse::UnprocessableEntity().json(report));
+    }
+
+    let mut created = Vec::with_capacity(created_ids.len());
+    for todo_id in created_ids {
+        if let Some(todo) = fetch_todo_response(&mut tx, todo_id).await? {
+            created.push(todo);
+        }
+    }
+
+    tx.commit().await?;
+
+    TODOS_CREATED_TOTAL.inc_by(created.len() as u64);
+    for todo in &created {
+        webhooks::dispatch(pool.get_ref(), auth.user_id, "todo.created", todo);
+        events::publish(&req, auth.user_id, TodoEventKind::Created, todo.id, Some(todo));
+    }
+
+    Ok(HttpResponse::Ok().json(report))
+}
@@ -0,0 +1,185 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::auth::password::verify_password;
+use crate::auth::refresh::hash_refresh_token;
+use crate::auth::totp::{build_totp, check_code, generate_backup_codes, generate_secret, verify_second_factor};
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{TotpCodeReq, TotpDisableReq, TotpSetupResponse};
+
+// Handler for starting two-factor setup: stores a new secret, not yet enabled, and returns
+// what the authenticator app needs. Calling it again before verifying replaces the secret.
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/2fa/setup",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "The otpauth:// URI, its QR code and the backup codes", body = TotpSetupResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 409, description = "Two-factor authentication is already enabled", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn setup_two_factor(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let name = sqlx::query_scalar!(r#"SELECT name FROM "Users" WHERE id = $1"#, user_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let secret = generate_secret();
+    let totp = build_totp(&secret, &name)?;
+    let qr_code_png = totp.get_qr_base64().map_err(AppError::InternalError)?;
+    let backup_codes = generate_backup_codes();
+    let hashed_codes: Vec<String> = backup_codes.iter().map(|code| hash_refresh_token(code)).collect();
+
+    // An enabled secret is never overwritten, it has to be disabled first
+    let result = sqlx::query!(
+        "INSERT INTO totp_secrets (user_id, secret, backup_codes) VALUES ($1, $2, $3)
+         ON CONFLICT (user_id) DO UPDATE SET secret = EXCLUDED.secret, backup_codes = EXCLUDED.backup_codes
+         WHERE totp_secrets.enabled_at IS NULL",
+        user_id,
+        secret,
+        &hashed_codes
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(TotpSetupResponse {
+        otpauth_uri: totp.get_url(),
+        qr_code_png,
+        backup_codes,
+    }))
+}
+
+// Handler for finishing two-factor setup: a code from the app proves it has the secret,
+// from then on logging in needs a code as well
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/2fa/verify",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = TotpCodeReq,
+    responses(
+        (status = 204, description = "Two-factor authentication enabled"),
+        (status = 400, description = "Setup wasn't started or the code is wrong", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 409, description = "Two-factor authentication is already enabled", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn verify_two_factor(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    body: web::Json<TotpCodeReq>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let pending = sqlx::query!(
+        "SELECT secret, enabled_at FROM totp_secrets WHERE user_id = $1",
+        user_id
+    )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Two-factor setup has not been started".to_string()))?;
+
+    if pending.enabled_at.is_some() {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+
+    // Backup codes don't count here, only the app can show it was set up right
+    if !check_code(&build_totp(&pending.secret, "")?, &body.code) {
+        return Err(AppError::BadRequest("Invalid two-factor code".to_string()));
+    }
+
+    // The secret must still be the one the code was checked against
+    let result = sqlx::query!(
+        "UPDATE totp_secrets SET enabled_at = NOW() WHERE user_id = $1 AND secret = $2 AND enabled_at IS NULL",
+        user_id,
+        pending.secret
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::Conflict("Two-factor setup changed, start again".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler for turning two-factor authentication off, needs the password and a code (or backup code)
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/2fa/disable",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = TotpDisableReq,
+    responses(
+        (status = 204, description = "Two-factor authentication disabled"),
+        (status = 400, description = "Not enabled, or the password or code is wrong", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn disable_two_factor(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    body: web::Json<TotpDisableReq>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let stored_hash = sqlx::query_scalar!(r#"SELECT password FROM "Users" WHERE id = $1"#, user_id)
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if !verify_password(&body.password, &stored_hash) {
+        return Err(AppError::BadRequest("Current password is incorrect".to_string()));
+    }
+
+    let secret = sqlx::query_scalar!(
+        "SELECT secret FROM totp_secrets WHERE user_id = $1 AND enabled_at IS NOT NULL",
+        user_id
+    )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Two-factor authentication is not enabled".to_string()))?;
+
+    if !verify_second_factor(pool.get_ref(), user_id, &secret, &body.code).await? {
+        return Err(AppError::BadRequest("Invalid two-factor code".to_string()));
+    }
+
+    sqlx::query!("DELETE FROM totp_secrets WHERE user_id = $1", user_id)
+        .execute(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,818 @@
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+use sqlx::PgPool;
+use url::Url;
+use validator::{ValidateEmail, ValidationError, ValidationErrors};
+
+use super::{created_response, page_bounds, paginated_response, TOTAL_COUNT_HEADER};
+use crate::audit::{set_audit_actor_ip, set_audit_context};
+use crate::auth::jwt::issue_token;
+use crate::auth::password::{hash_password, verify_password};
+use crate::auth::refresh::{generate_refresh_token, hash_refresh_token, REFRESH_TOKEN_TTL_DAYS};
+use crate::auth::totp::verify_second_factor;
+use crate::config::{AppConfig, DEFAULT_AVATAR_ALLOWED_DOMAINS};
+use crate::error::{problem_types, AppError, ProblemDetails};
+use crate::jsonapi::JsonApiResponder;
+use crate::auth::{AdminGuard, AuthUser, TokenDenylist};
+use crate::models::{
+    ChangePasswordReq, EraseDataReq, ERASED_USER_NAME_PREFIX, EraseDataResponse, LoginReq, LoginResponse, NewUser, PageQuery, PaginatedResponse, RefreshReq, Role, UpdateRoleReq,
+    UpdateAvatarReq, UpdateUserReq, UserContact, UserProfileResponse, UserResponse, UserSearchQuery,
+};
+use crate::todo_cache::invalidate_all_todos;
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+const SEARCH_MIN_CHARS: usize = 2;
+const SEARCH_DEFAULT_LIMIT: u32 = 10;
+const SEARCH_MAX_LIMIT: u32 = 50;
+
+const AVATAR_URL_MAX_CHARS: usize = 2048;
+
+#[utoipa::path(
+    post,
+    path = "/register",
+    tag = "users",
+    request_body = NewUser,
+    responses(
+        (status = 201, description = "The registered user", body = UserResponse, headers(("Location" = String, description = "Path of the new user"))),
+        (status = 409, description = "The name is taken", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    )
+)]
+pub async fn create_user(
+    req: HttpRequest,
+    pool:web::Data<PgPool>,
+    new_user: web::Json<NewUser>
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_user)?;
+    if let Some(email) = &new_user.email {
+        if !email.validate_email() {
+            let mut errors = ValidationErrors::new();
+            errors.add("email", ValidationError::new("email").with_message("must be a valid email address".into()));
+            return Err(errors.into());
+        }
+    }
+
+    // Never store the plaintext password, only its hash
+    let password_hash = hash_password(&new_user.password)?;
+
+    let mut tx = pool.begin().await?;
+    // There's no actor yet, the new row itself says who registered
+    if let Some(addr) = req.peer_addr() {
+        set_audit_actor_ip(&mut *tx, &addr.ip().to_string()).await?;
+    }
+
+    // New registrations always start as plain users, admins promote them later.
+    // Dropping `tx` on an early return rolls the insert back.
+    let user_response = sqlx::query_as!(
+        UserResponse,
+        r#"INSERT INTO "Users" (name, password, role, email) VALUES ($1, $2, 'user', $3)
+           RETURNING id, name, role AS "role: Role", avatar_url"#,
+        new_user.name,
+        password_hash,
+        new_user.email,
+    )
+        .fetch_one(&mut *tx)
+        .await;
+
+    let user_response = match user_response {
+        Ok(user) => user,
+        // 23505 is unique_violation: the name is already taken
+        Err(e) if e.as_database_error().and_then(|db| db.code()).as_deref() == Some("23505") => {
+            return Err(AppError::Conflict("A user with that name already exists".to_string()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    tx.commit().await?;
+
+    Ok(created_response(&req, &format!("/users/{}", user_response.id), &user_response))
+}
+
+// Handler for logging in, returns a signed JWT on success
+#[utoipa::path(
+    post,
+    path = "/login",
+    tag = "auth",
+    request_body = LoginReq,
+    responses(
+        (status = 200, description = "Access and refresh tokens", body = LoginResponse),
+        (status = 401, description = "Invalid name or password, or a missing or wrong two-factor code", body = ProblemDetails)
+    )
+)]
+pub async fn login(
+    pool: web::Data<PgPool>,
+    credentials: web::Json<LoginReq>,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query!(
+        r#"SELECT id, password, role AS "role: Role" FROM "Users" WHERE name = $1 AND deleted_at IS NULL"#,
+        credentials.name
+    )
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+    // Same response for unknown names and wrong passwords so names can't be probed
+    match user {
+        Some(user) if verify_password(&credentials.password, &user.password) => {
+            // Only asked for once the password is right, so it gives nothing away about the account
+            let totp_secret = sqlx::query_scalar!(
+                "SELECT secret FROM totp_secrets WHERE user_id = $1 AND enabled_at IS NOT NULL",
+                user.id
+            )
+                .fetch_optional(pool.get_ref())
+                .await?;
+
+            if let Some(secret) = totp_secret {
+                let detail = match &credentials.totp_code {
+                    None => Some("Two-factor code required"),
+                    Some(code) if !verify_second_factor(pool.get_ref(), user.id, &secret, code).await? => {
+                        Some("Invalid two-factor code")
+                    }
+                    Some(_) => None,
+                };
+                if let Some(detail) = detail {
+                    return Ok(ProblemDetails::new(StatusCode::UNAUTHORIZED, problem_types::UNAUTHORIZED, detail).response());
+                }
+            }
+
+            let refresh_token = store_refresh_token(pool.get_ref(), user.id).await?;
+            Ok(HttpResponse::Ok().json(LoginResponse {
+                token: issue_token(user.id, user.role),
+                refresh_token,
+            }))
+        }
+        _ => Ok(ProblemDetails::new(StatusCode::UNAUTHORIZED, problem_types::UNAUTHORIZED, "Invalid name or password")
+            .response()),
+    }
+}
+
+// Generate a refresh token for the user and save its hash, returns the plain token
+async fn store_refresh_token<'e, E>(executor: E, user_id: i32) -> Result<String, AppError>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    let token = generate_refresh_token();
+
+    sqlx::query!(
+        "INSERT INTO refresh_tokens (user_id, token_hash, expires_at)
+         VALUES ($1, $2, NOW() + make_interval(days => $3))",
+        user_id,
+        hash_refresh_token(&token),
+        REFRESH_TOKEN_TTL_DAYS as i32
+    )
+        .execute(executor)
+        .await?;
+
+    Ok(token)
+}
+
+// Handler for trading a refresh token for a new access token.
+// The refresh token is single use: it is revoked and a new one is returned with the access token.
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    tag = "auth",
+    request_body = RefreshReq,
+    responses(
+        (status = 200, description = "New access and refresh tokens", body = LoginResponse),
+        (status = 401, description = "Invalid or expired refresh token", body = ProblemDetails)
+    )
+)]
+pub async fn refresh(
+    pool: web::Data<PgPool>,
+    body: web::Json<RefreshReq>,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    // Revoking in the same statement as the lookup means a token can't be used twice concurrently
+    let user = sqlx::query!(
+        r#"UPDATE refresh_tokens AS t SET revoked_at = NOW()
+           FROM "Users" AS u
+           WHERE t.user_id = u.id AND t.token_hash = $1 AND u.deleted_at IS NULL
+             AND t.revoked_at IS NULL AND t.expires_at > NOW()
+           RETURNING u.id, u.role AS "role: Role""#,
+        hash_refresh_token(&body.refresh_token)
+    )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    let Some(user) = user else {
+        return Ok(ProblemDetails::new(
+            StatusCode::UNAUTHORIZED,
+            problem_types::UNAUTHORIZED,
+            "Invalid or expired refresh token",
+        )
+        .response());
+    };
+
+    let refresh_token = store_refresh_token(&mut *tx, user.id).await?;
+
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(LoginResponse {
+        token: issue_token(user.id, user.role),
+        refresh_token,
+    }))
+}
+
+// Handler for logging out, revokes the access token used for this request
+// and every refresh token of the user
+#[utoipa::path(
+    post,
+    path = "/logout",
+    tag = "auth",
+    responses(
+        (status = 204, description = "Tokens revoked"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn logout(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    denylist: web::Data<TokenDenylist>,
+) -> Result<HttpResponse, AppError> {
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        auth.user_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    // An API key stays valid, it's deleted through DELETE /api-keys/{key_id} instead
+    if auth.api_key_id.is_none() {
+        denylist.revoke(&auth.jti, auth.exp);
+    }
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler for changing one's own password, the current one has to be given.
+// Every refresh token of the user is revoked so other sessions have to log in again.
+#[utoipa::path(
+    post,
+    path = "/users/{user_id}/change-password",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = ChangePasswordReq,
+    responses(
+        (status = 204, description = "Password changed"),
+        (status = 400, description = "Current password is incorrect", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn change_password(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    body: web::Json<ChangePasswordReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*body)?;
+
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+
+    let stored_hash = sqlx::query_scalar!(
+        r#"SELECT password FROM "Users" WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
+        user_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if !verify_password(&body.current_password, &stored_hash) {
+        return Err(AppError::BadRequest("Current password is incorrect".to_string()));
+    }
+
+    sqlx::query!(
+        r#"UPDATE "Users" SET password = $1 WHERE id = $2"#,
+        hash_password(&body.new_password)?,
+        user_id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+        user_id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Handler for erasing everything a user has stored (right to erasure), after checking their
+// password once more. Their todos and everything hanging off them, comments, keys, settings
+// and logs are deleted; the "Users" row stays, anonymised, so the id isn't handed out again.
+#[utoipa::path(
+    delete,
+    path = "/users/{user_id}/data",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = EraseDataReq,
+    responses(
+        (status = 200, description = "Everything erased", body = EraseDataResponse),
+        (status = 400, description = "Password is incorrect", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn erase_user_data(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    denylist: web::Data<TokenDenylist>,
+    user_id: web::Path<i32>,
+    body: web::Json<EraseDataReq>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let stored_hash = sqlx::query_scalar!(
+        r#"SELECT password FROM "Users" WHERE id = $1 AND deleted_at IS NULL FOR UPDATE"#,
+        user_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    if !verify_password(&body.password, &stored_hash) {
+        return Err(AppError::BadRequest("Password is incorrect".to_string()));
+    }
+
+    set_audit_context(&mut tx, user_id, &req).await?;
+
+    // Subtasks, comments, shares, notes and time entries of the todos go with them
+    sqlx::query!("DELETE FROM todos WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    for statement in [
+        "DELETE FROM comments WHERE user_id = $1",
+        "DELETE FROM time_entries WHERE user_id = $1",
+        "DELETE FROM todo_shares WHERE shared_with_user_id = $1",
+        "DELETE FROM api_keys WHERE user_id = $1",
+        "DELETE FROM refresh_tokens WHERE user_id = $1",
+        "DELETE FROM password_reset_tokens WHERE user_id = $1",
+        "DELETE FROM totp_secrets WHERE user_id = $1",
+        "DELETE FROM activity_log WHERE user_id = $1",
+        "DELETE FROM user_preferences WHERE user_id = $1",
+        "DELETE FROM webhooks WHERE user_id = $1",
+        "DELETE FROM tags WHERE user_id = $1",
+        "DELETE FROM categories WHERE user_id = $1",
+        "DELETE FROM todo_templates WHERE user_id = $1",
+        "DELETE FROM notifications_dismissed WHERE user_id = $1",
+        "DELETE FROM stats_snapshots WHERE user_id = $1",
+        "DELETE FROM idempotency_cache WHERE user_id = $1",
+    ] {
+        sqlx::query(statement).bind(user_id).execute(&mut *tx).await?;
+    }
+
+    // 'REDACTED' isn't a password hash, so nobody can log in as the account anymore
+    sqlx::query!(
+        r#"UPDATE "Users" SET name = $2 || id, password = 'REDACTED', email = NULL,
+                              email_verified = false, avatar_url = NULL, deleted_at = NOW()
+           WHERE id = $1"#,
+        user_id,
+        ERASED_USER_NAME_PREFIX
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    // The audit trail keeps who did what and when, but not their name, email or todo contents,
+    // including the entries the statements above just wrote
+    sqlx::query!(
+        r#"UPDATE audit_log SET old_data = NULL, new_data = NULL
+           WHERE (table_name = 'Users' AND row_id = $1)
+              OR (table_name = 'todos'
+                  AND ((old_data ->> 'user_id')::INTEGER = $1 OR (new_data ->> 'user_id')::INTEGER = $1))"#,
+        user_id
+    )
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    // Their todos are gone and so are shares of other people's todos with them
+    invalidate_all_todos(&req);
+
+    if auth.api_key_id.is_none() {
+        denylist.revoke(&auth.jti, auth.exp);
+    }
+    tracing::info!(user_id, "user data erased");
+
+    Ok(HttpResponse::Ok().json(EraseDataResponse { message: "All data erased".to_string() }))
+}
+
+// Handler for a user's public profile, any authenticated caller may look it up (JSON:API on request).
+// The user themselves and admins also get the email address.
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "The user's profile", body = UserProfileResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn get_user(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    auth: AuthUser,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query!(
+        r#"SELECT id, name, role AS "role: Role", avatar_url, email, email_verified
+           FROM "Users" WHERE id = $1 AND deleted_at IS NULL"#,
+        user_id.into_inner()
+    )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+
+    let contact = (auth.user_id == user.id || auth.role == Role::Admin).then_some(UserContact {
+        email: user.email,
+        email_verified: user.email_verified,
+    });
+    let profile = UserProfileResponse {
+        user: UserResponse { id: user.id, name: user.name, role: user.role, avatar_url: user.avatar_url },
+        contact,
+    };
+
+    Ok(JsonApiResponder(profile).respond_to(&req))
+}
+
+// Handler for deleting a user (admins only)
+#[utoipa::path(
+    delete,
+    path = "/users/{user_id}",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "User deleted", body = String),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_user(
+    admin: AdminGuard,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, admin.0.user_id, &req).await?;
+    let result = sqlx::query!("DELETE FROM \"Users\" WHERE id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // No user with the given ID
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("User not found".to_string()));
+    }
+    tx.commit().await?;
+    invalidate_all_todos(&req);
+
+    Ok(HttpResponse::Ok().body("User successfully deleted"))
+}
+
+// Handler for renaming a user (the user themselves or an admin). Passwords are changed at
+// /users/{user_id}/change-password, where the current one has to be given.
+#[utoipa::path(
+    patch,
+    path = "/user/{user_id}",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = UpdateUserReq,
+    responses(
+        (status = 200, description = "The updated user", body = UserResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 409, description = "The name is taken", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_user(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    user_data: web::Json<UpdateUserReq>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*user_data)?;
+    let user_id = user_id.into_inner();
+    if auth.role != Role::Admin && auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, auth.user_id, &req).await?;
+    let updated_user = sqlx::query_as!(
+        UserResponse,
+        r#"UPDATE "Users" SET name = COALESCE($1, name) WHERE id = $2 AND deleted_at IS NULL
+           RETURNING id, name, role AS "role: Role", avatar_url"#,
+        user_data.name.as_deref(),
+        user_id
+    )
+        .fetch_optional(&mut *tx)
+        .await;
+
+    match updated_user {
+        Ok(Some(user)) => {
+            tx.commit().await?;
+            Ok(HttpResponse::Ok().json(user))
+        }
+        Ok(None) => Err(AppError::NotFound("User not found".to_string())),
+        // 23505 is unique_violation: the name is already taken
+        Err(e) if e.as_database_error().and_then(|db| db.code()).as_deref() == Some("23505") => {
+            Err(AppError::Conflict("A user with that name already exists".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Handler for listing every user, one page at a time (admins only)
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "users",
+    params(PageQuery),
+    responses(
+        (status = 200, description = "A page of users", body = PaginatedResponse<UserResponse>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_users(
+    _admin: AdminGuard,
+    pool: web::Data<PgPool>,
+    query: web::Query<PageQuery>,
+) -> Result<HttpResponse, AppError> {
+    let (page, per_page, offset) = page_bounds(query.page, query.per_page);
+
+    // Same snapshot for the page and the count, like the todo lists
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let users = sqlx::query_as!(
+        UserResponse,
+        r#"SELECT id, name, role AS "role: Role", avatar_url FROM "Users"
+           WHERE deleted_at IS NULL ORDER BY id LIMIT $1 OFFSET $2"#,
+        per_page as i64,
+        offset
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM "Users" WHERE deleted_at IS NULL"#)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(paginated_response(&PaginatedResponse {
+        items: users,
+        total,
+        page,
+        per_page,
+        next_cursor: None,
+    }))
+}
+
+// Handler for finding users by part of their name, for autocomplete (admins only)
+#[utoipa::path(
+    get,
+    path = "/users/search",
+    tag = "users",
+    params(UserSearchQuery),
+    responses(
+        (status = 200, description = "Users whose name contains q, by name", body = Vec<UserResponse>),
+        (status = 400, description = "q is shorter than 2 characters", body = ProblemDetails),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn search_users(
+    _admin: AdminGuard,
+    pool: web::Data<PgPool>,
+    query: web::Query<UserSearchQuery>,
+) -> Result<HttpResponse, AppError> {
+    // A shorter q would match nearly everyone
+    let q = query.q.trim();
+    if q.chars().count() < SEARCH_MIN_CHARS {
+        return Err(AppError::BadRequest(format!("q must be at least {} characters", SEARCH_MIN_CHARS)));
+    }
+    let limit = query.limit.unwrap_or(SEARCH_DEFAULT_LIMIT).clamp(1, SEARCH_MAX_LIMIT);
+
+    // % and _ in q are matched literally
+    let pattern = format!("%{}%", q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+
+    // Same snapshot for the matches and the count, like the todo lists
+    let mut tx = pool.begin().await?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *tx)
+        .await?;
+
+    let users = sqlx::query_as!(
+        UserResponse,
+        r#"SELECT id, name, role AS "role: Role", avatar_url FROM "Users"
+           WHERE name ILIKE $1 AND deleted_at IS NULL
+           ORDER BY name, id LIMIT $2"#,
+        pattern,
+        limit as i64
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM "Users" WHERE name ILIKE $1 AND deleted_at IS NULL"#,
+        pattern
+    )
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header((TOTAL_COUNT_HEADER, total.to_string()))
+        .json(users))
+}
+
+// Handler for changing a user's role (admins only)
+#[utoipa::path(
+    patch,
+    path = "/users/{user_id}/role",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = UpdateRoleReq,
+    responses(
+        (status = 200, description = "The user with the new role", body = UserResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_user_role(
+    admin: AdminGuard,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    body: web::Json<UpdateRoleReq>,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, admin.0.user_id, &req).await?;
+    let user = sqlx::query_as!(
+        UserResponse,
+        r#"UPDATE "Users" SET role = $1 WHERE id = $2 RETURNING id, name, role AS "role: Role", avatar_url"#,
+        body.role as Role,
+        user_id.into_inner()
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
+
+// Why the avatar URL can't be used, None when it's fine. The host must be one of the allowed
+// domains or a subdomain of one.
+fn avatar_url_problem(avatar_url: &str, allowed_domains: &[String]) -> Option<&'static str> {
+    if avatar_url.chars().count() > AVATAR_URL_MAX_CHARS {
+        return Some("must be at most 2048 characters");
+    }
+    let Ok(url) = Url::parse(avatar_url) else {
+        return Some("must be a valid URL");
+    };
+    if url.scheme() != "https" {
+        return Some("must be an https URL");
+    }
+    let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+    let allowed = allowed_domains.iter().any(|domain| {
+        let domain = domain.to_ascii_lowercase();
+        host == domain || host.ends_with(&format!(".{}", domain))
+    });
+    if !allowed {
+        return Some("must be hosted on one of the allowed domains");
+    }
+    None
+}
+
+// Handler for setting a user's profile picture (the user themselves or an admin)
+#[utoipa::path(
+    patch,
+    path = "/users/{user_id}/avatar",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    request_body = UpdateAvatarReq,
+    responses(
+        (status = 200, description = "The user with the new avatar", body = UserResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn update_avatar(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+    body: web::Json<UpdateAvatarReq>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.role != Role::Admin && auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    let allowed_domains = req.app_data::<web::Data<AppConfig>>().map_or_else(
+        || vec![DEFAULT_AVATAR_ALLOWED_DOMAINS.to_string()],
+        |config| config.avatar_allowed_domains.clone(),
+    );
+    if let Some(message) = avatar_url_problem(&body.avatar_url, &allowed_domains) {
+        let mut errors = ValidationErrors::new();
+        errors.add("avatar_url", ValidationError::new("avatar_url").with_message(message.into()));
+        return Err(errors.into());
+    }
+
+    set_avatar_url(pool.get_ref(), &req, auth.user_id, user_id, Some(&body.avatar_url)).await
+}
+
+// Handler for removing a user's profile picture (the user themselves or an admin)
+#[utoipa::path(
+    delete,
+    path = "/users/{user_id}/avatar",
+    tag = "users",
+    params(("user_id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "The user without an avatar", body = UserResponse),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "Not allowed for the caller", body = ProblemDetails),
+        (status = 404, description = "User not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_avatar(
+    auth: AuthUser,
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    if auth.role != Role::Admin && auth.user_id != user_id {
+        return Err(AppError::Forbidden);
+    }
+
+    set_avatar_url(pool.get_ref(), &req, auth.user_id, user_id, None).await
+}
+
+async fn set_avatar_url(
+    pool: &PgPool,
+    req: &HttpRequest,
+    actor_id: i32,
+    user_id: i32,
+    avatar_url: Option<&str>,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = pool.begin().await?;
+    set_audit_context(&mut tx, actor_id, req).await?;
+    let user = sqlx::query_as!(
+        UserResponse,
+        r#"UPDATE "Users" SET avatar_url = $1 WHERE id = $2 AND deleted_at IS NULL
+           RETURNING id, name, role AS "role: Role", avatar_url"#,
+        avatar_url,
+        user_id
+    )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    tx.commit().await?;
+
+    Ok(HttpResponse::Ok().json(user))
+}
@@ -0,0 +1,110 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use super::email_verification::ensure_email_verified;
+use crate::auth::AuthUser;
+use crate::error::{AppError, ProblemDetails};
+use crate::models::{NewWebhook, Webhook};
+use crate::validation::{validate_input, ValidationErrorResponse};
+
+// Handler for listing the caller's webhooks
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "The caller's webhooks", body = Vec<Webhook>),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn list_webhooks(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, AppError> {
+    let webhooks = sqlx::query_as!(
+        Webhook,
+        "SELECT id, url, events, created_at FROM webhooks WHERE user_id = $1 ORDER BY id",
+        auth.user_id
+    )
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+// Handler for registering a webhook for some of the todo events
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    tag = "webhooks",
+    request_body = NewWebhook,
+    responses(
+        (status = 201, description = "The created webhook", body = Webhook),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 403, description = "The caller's email address isn't verified", body = ProblemDetails),
+        (status = 422, description = "Validation failed", body = ValidationErrorResponse)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn create_webhook(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    new_webhook: web::Json<NewWebhook>,
+) -> Result<HttpResponse, AppError> {
+    validate_input(&*new_webhook)?;
+    // Deliveries that keep failing will need a way to reach the owner
+    ensure_email_verified(pool.get_ref(), auth.user_id).await?;
+
+    let mut events = new_webhook.events.clone();
+    events.sort();
+    events.dedup();
+
+    let webhook = sqlx::query_as!(
+        Webhook,
+        "INSERT INTO webhooks (user_id, url, events, secret) VALUES ($1, $2, $3, $4)
+         RETURNING id, url, events, created_at",
+        auth.user_id,
+        new_webhook.url,
+        &events,
+        new_webhook.secret
+    )
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    Ok(HttpResponse::Created().json(webhook))
+}
+
+// Handler for removing one of the caller's webhooks, its delivery history goes with it
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(("webhook_id" = i32, Path, description = "Webhook id")),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 401, description = "Missing or invalid bearer token", body = ProblemDetails),
+        (status = 404, description = "Webhook not found", body = ProblemDetails)
+    ),
+    security(("BearerAuth" = []))
+)]
+pub async fn delete_webhook(
+    auth: AuthUser,
+    pool: web::Data<PgPool>,
+    webhook_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let result = sqlx::query!(
+        "DELETE FROM webhooks WHERE id = $1 AND user_id = $2",
+        webhook_id.into_inner(),
+        auth.user_id
+    )
+        .execute(pool.get_ref())
+        .await?;
+
+    // Someone else's webhook is reported the same as a missing one
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Webhook not found".to_string()));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,115 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse};
+use serde_json::Value;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+// What a request holding an Idempotency-Key should do
+pub enum Claim {
+    New,                  // First time the key is seen, handle the request and store its response
+    Replay(HttpResponse), // The key's request already completed, answer with its response again
+}
+
+// The Idempotency-Key header, which has to be a UUID when present (400 otherwise)
+pub fn idempotency_key(req: &HttpRequest) -> Result<Option<String>, AppError> {
+    let Some(value) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|value| Uuid::parse_str(value.trim()).ok())
+        .map(|key| Some(key.to_string()))
+        .ok_or_else(|| AppError::BadRequest("Idempotency-Key must be a UUID".to_string()))
+}
+
+// Reserve the key for this request. Entries older than 24 hours are treated as gone.
+// 409 while another request with the key is still being handled.
+pub async fn claim(pool: &PgPool, key: &str, user_id: i32) -> Result<Claim, AppError> {
+    loop {
+        let claimed = sqlx::query!(
+            "INSERT INTO idempotency_cache (key, user_id) VALUES ($1, $2)
+             ON CONFLICT (key) DO UPDATE
+             SET user_id = EXCLUDED.user_id, response_status = NULL, response_body = NULL, created_at = NOW()
+             WHERE idempotency_cache.created_at < NOW() - INTERVAL '24 hours'",
+            key,
+            user_id
+        )
+            .execute(pool)
+            .await?;
+
+        if claimed.rows_affected() == 1 {
+            return Ok(Claim::New);
+        }
+
+        let cached = sqlx::query!(
+            "SELECT user_id, response_status, response_body FROM idempotency_cache WHERE key = $1",
+            key
+        )
+            .fetch_optional(pool)
+            .await?;
+
+        // Released by a request that failed in the meantime, try to take it again
+        let Some(cached) = cached else { continue };
+
+        if cached.user_id != user_id {
+            return Err(AppError::Conflict("Idempotency-Key was already used".to_string()));
+        }
+
+        return match (cached.response_status, cached.response_body) {
+            (Some(status), Some(body)) => {
+                let status = StatusCode::from_u16(status as u16)
+                    .map_err(|e| AppError::InternalError(e.to_string()))?;
+                Ok(Claim::Replay(HttpResponse::build(status).json(body)))
+            }
+            _ => Err(AppError::Conflict(
+                "A request with this Idempotency-Key is still in progress".to_string(),
+            )),
+        };
+    }
+}
+
+// Save the response for the key, in the transaction that made the change it describes
+pub async fn store_response(
+    tx: &mut Transaction<'_, Postgres>,
+    key: &str,
+    status: StatusCode,
+    body: &Value,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        "UPDATE idempotency_cache SET response_status = $2, response_body = $3 WHERE key = $1",
+        key,
+        status.as_u16() as i16,
+        body
+    )
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+// Give the key up after the request failed so a retry is handled from scratch
+pub async fn release(pool: &PgPool, key: &str) -> Result<(), AppError> {
+    sqlx::query!(
+        "DELETE FROM idempotency_cache WHERE key = $1 AND response_status IS NULL",
+        key
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+// Drop entries that can no longer be replayed, returns how many went
+pub async fn purge_expired(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM idempotency_cache WHERE created_at < NOW() - INTERVAL '24 hours'")
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,198 @@
+use chrono::NaiveDate;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::NewTodo;
+
+// A file sent as one field of a multipart/form-data body
+pub struct UploadedFile {
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// Value of a `key=value` parameter in a header such as Content-Type or Content-Disposition
+fn header_param<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    header.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.trim().split_once('=')?;
+        name.eq_ignore_ascii_case(key).then(|| value.trim_matches('"'))
+    })
+}
+
+// Pull the named field out of a multipart/form-data body
+pub fn multipart_file(content_type: &str, body: &[u8], field: &str) -> Result<UploadedFile, AppError> {
+    let malformed = || AppError::BadRequest("Malformed multipart body".to_string());
+
+    if !content_type.starts_with("multipart/form-data") {
+        return Err(AppError::BadRequest("Expected a multipart/form-data body".to_string()));
+    }
+    let boundary = header_param(content_type, "boundary").ok_or_else(malformed)?;
+    let delimiter = format!("\r\n--{}", boundary);
+
+    // The first delimiter has no line break in front of it
+    let start = find(body, &delimiter.as_bytes()[2..]).ok_or_else(malformed)?;
+    let mut rest = &body[start + delimiter.len() - 2..];
+
+    // Each part is `\r\n<headers>\r\n\r\n<data>` up to the next delimiter, the last one ends in `--`
+    while !rest.starts_with(b"--") {
+        rest = rest.strip_prefix(b"\r\n").ok_or_else(malformed)?;
+        let end = find(rest, delimiter.as_bytes()).ok_or_else(malformed)?;
+        let part = &rest[..end];
+        rest = &rest[end + delimiter.len()..];
+
+        let header_end = find(part, b"\r\n\r\n").ok_or_else(malformed)?;
+        let headers = std::str::from_utf8(&part[..header_end]).map_err(|_| malformed())?;
+
+        let mut disposition = None;
+        let mut part_type = None;
+        for line in headers.split("\r\n") {
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-disposition") {
+                    disposition = Some(value.trim());
+                } else if name.eq_ignore_ascii_case("content-type") {
+                    part_type = Some(value.trim());
+                }
+            }
+        }
+
+        let Some(disposition) = disposition else { continue };
+        if header_param(disposition, "name") == Some(field) {
+            return Ok(UploadedFile {
+                filename: header_param(disposition, "filename").map(str::to_string),
+                content_type: part_type.map(str::to_string),
+                data: part[header_end + 4..].to_vec(),
+            });
+        }
+    }
+
+    Err(AppError::BadRequest(format!("Missing '{}' field", field)))
+}
+
+// CSV or JSON, from the part's Content-Type or else the file extension
+pub fn detect_format(file: &UploadedFile) -> Result<ImportFormat, AppError> {
+    let content_type = file.content_type.as_deref().unwrap_or_default();
+    if content_type.starts_with("text/csv") {
+        return Ok(ImportFormat::Csv);
+    }
+    if content_type.starts_with("application/json") {
+        return Ok(ImportFormat::Json);
+    }
+
+    let filename = file.filename.as_deref().unwrap_or_default().to_ascii_lowercase();
+    if filename.ends_with(".csv") {
+        Ok(ImportFormat::Csv)
+    } else if filename.ends_with(".json") {
+        Ok(ImportFormat::Json)
+    } else {
+        Err(AppError::BadRequest("Upload a .csv or .json file".to_string()))
+    }
+}
+
+// Split CSV text into records, honouring quoted fields with commas, quotes and line breaks
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    // Blank lines carry no todo
+    records.retain(|record| record.iter().any(|field| !field.is_empty()));
+    records
+}
+
+// One CSV record as a NewTodo, columns are looked up by their header name
+fn csv_todo(header: &[String], record: &[String]) -> Result<NewTodo, String> {
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| column == name)
+            .and_then(|index| record.get(index))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    };
+
+    Ok(NewTodo {
+        title: column("title").map(str::to_string),
+        description: column("description").map(str::to_string),
+        completed: column("completed")
+            .map(|value| value.parse::<bool>().map_err(|_| format!("Invalid completed '{}'", value)))
+            .transpose()?,
+        priority: column("priority").map(str::parse).transpose()?,
+        due_date: column("due_date")
+            .map(|value| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid due_date '{}', expected YYYY-MM-DD", value))
+            })
+            .transpose()?,
+        tag_ids: None,
+        category_id: None,
+    })
+}
+
+// Parse the file into one NewTodo per row, or the reason the row can't be imported.
+// Errors for the file as a whole (bad encoding, not a JSON array) fail the request.
+pub fn parse_rows(format: ImportFormat, data: &[u8]) -> Result<Vec<Result<NewTodo, String>>, AppError> {
+    let rows = match format {
+        ImportFormat::Json => {
+            let values: Vec<serde_json::Value> = serde_json::from_slice(data)
+                .map_err(|e| AppError::BadRequest(format!("Expected a JSON array of todos: {}", e)))?;
+            values
+                .into_iter()
+                .map(|value| serde_json::from_value::<NewTodo>(value).map_err(|e| e.to_string()))
+                .collect::<Vec<_>>()
+        }
+        ImportFormat::Csv => {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| AppError::BadRequest("CSV file must be UTF-8".to_string()))?;
+            let mut records = csv_records(text.trim_start_matches('\u{feff}')).into_iter();
+            let header: Vec<String> = records
+                .next()
+                .ok_or_else(|| AppError::BadRequest("CSV file is empty".to_string()))?
+                .into_iter()
+                .map(|column| column.trim().to_ascii_lowercase())
+                .collect();
+            records.map(|record| csv_todo(&header, &record)).collect()
+        }
+    };
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let todo = row?;
+            todo.validate().map_err(|errors| errors.to_string())?;
+            Ok(todo)
+        })
+        .collect())
+}
@@ -0,0 +1,92 @@
+use actix_web::body::BoxBody;
+use actix_web::http::header::{ACCEPT, VARY};
+use actix_web::{HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::models::{TodoResponse, UserProfileResponse, UserResponse};
+
+// Clients that ask for this in Accept get the JSON:API envelope (e.g. Ember Data)
+pub const JSON_API_MEDIA_TYPE: &str = "application/vnd.api+json";
+
+// A value that can be sent as a JSON:API resource object
+pub trait JsonApiResource {
+    // The resource type, plural by convention
+    fn type_name() -> &'static str;
+    fn id(&self) -> String;
+}
+
+impl JsonApiResource for TodoResponse {
+    fn type_name() -> &'static str {
+        "todos"
+    }
+
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl JsonApiResource for UserResponse {
+    fn type_name() -> &'static str {
+        "users"
+    }
+
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl JsonApiResource for UserProfileResponse {
+    fn type_name() -> &'static str {
+        "users"
+    }
+
+    fn id(&self) -> String {
+        self.user.id.to_string()
+    }
+}
+
+// Whether one of the Accept entries is the JSON:API media type
+pub fn wants_json_api(req: &HttpRequest) -> bool {
+    req.headers()
+        .get_all(ACCEPT)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|entry| entry.split(';').next().unwrap_or_default().trim() == JSON_API_MEDIA_TYPE)
+}
+
+// Sends T as plain JSON, or as `{ "data": { "type", "id", "attributes" } }` when the client
+// asked for JSON:API. Plain JSON stays the default so existing clients see no change.
+pub struct JsonApiResponder<T>(pub T);
+
+impl<T: Serialize + JsonApiResource> Responder for JsonApiResponder<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        // Caches must keep the two representations apart
+        let mut builder = HttpResponse::Ok();
+        builder.insert_header((VARY, "Accept"));
+
+        if !wants_json_api(req) {
+            return builder.json(self.0);
+        }
+
+        let mut attributes = match serde_json::to_value(&self.0) {
+            Ok(value) => value,
+            Err(e) => return AppError::InternalError(e.to_string()).error_response(),
+        };
+        // The id sits next to the type, not among the attributes
+        if let Value::Object(fields) = &mut attributes {
+            fields.remove("id");
+        }
+
+        builder.content_type(JSON_API_MEDIA_TYPE).json(json!({
+            "data": {
+                "type": T::type_name(),
+                "id": self.0.id(),
+                "attributes": attributes,
+            }
+        }))
+    }
+}
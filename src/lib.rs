@@ -0,0 +1,146 @@
+use actix_web::web;
+use sqlx::migrate::Migrator;
+
+pub mod activity;
+pub mod audit;
+pub mod auth;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod handlers;
+pub mod idempotency;
+pub mod import;
+pub mod jsonapi;
+pub mod metrics;
+pub mod middleware;
+pub mod models;
+pub mod openapi;
+pub mod recurrence;
+pub mod snapshots;
+pub mod telemetry;
+pub mod templates;
+pub mod todo_cache;
+pub mod todo_titles;
+pub mod validation;
+pub mod webhooks;
+
+use handlers::{api_keys, batch, categories, comments, data_export, dependencies, email_verification, health, home_page, notes, notifications, password_reset, preferences, shares, stats, subtasks, tags, time_entries, todos, two_factor, users};
+use middleware::api_version::ApiVersion;
+
+// Schema migrations embedded at compile time, applied on startup and by the tests
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+// Register every route of the API; shared by the server binary and the integration tests
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    // Probes, metrics and the docs aren't part of the versioned API
+    cfg.route("/", web::get().to(home_page))
+        .route("/health", web::get().to(health::health_check))
+        .route("/ready", web::get().to(health::readiness_check))
+        .route("/metrics", web::get().to(handlers::metrics::metrics))
+        .route("/openapi.json", web::get().to(openapi::openapi_json))
+        .route("/swagger-ui/", web::get().to(openapi::swagger_ui))
+        .service(web::scope(ApiVersion::V1.prefix()).configure(api_routes))
+        // The un-prefixed routes stay as aliases of /v1 for one more release
+        .configure(api_routes);
+}
+
+// The routes of one API version, mounted under its prefix
+fn api_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/todos", web::get().to(todos::get_todos))
+        .route("/todos", web::post().to(todos::create_todo))
+        .route("/tags", web::get().to(tags::list_tags))
+        .route("/tags", web::post().to(tags::create_tag))
+        .route("/categories", web::get().to(categories::list_categories))
+        .route("/categories", web::post().to(categories::create_category))
+        .route("/categories/{category_id}", web::get().to(categories::get_category))
+        .route("/categories/{category_id}", web::patch().to(categories::update_category))
+        .route("/categories/{category_id}", web::delete().to(categories::delete_category))
+        .route("/categories/{category_id}/todos", web::get().to(categories::list_category_todos))
+        .route("/templates", web::get().to(handlers::templates::list_templates))
+        .route("/templates", web::post().to(handlers::templates::create_template))
+        .route("/templates/{template_id}", web::get().to(handlers::templates::get_template))
+        .route("/templates/{template_id}", web::patch().to(handlers::templates::update_template))
+        .route("/templates/{template_id}", web::delete().to(handlers::templates::delete_template))
+        .route("/templates/{template_id}/instantiate", web::post().to(handlers::templates::instantiate_template))
+        .route("/notifications", web::get().to(notifications::list_notifications))
+        .route("/notifications/{todo_id}/dismiss", web::post().to(notifications::dismiss_notification))
+        .route("/webhooks", web::get().to(handlers::webhooks::list_webhooks))
+        .route("/webhooks", web::post().to(handlers::webhooks::create_webhook))
+        .route("/webhooks/{webhook_id}", web::delete().to(handlers::webhooks::delete_webhook))
+        .route("/register", web::post().to(users::create_user))
+        .route("/login", web::post().to(users::login))
+        .route("/refresh", web::post().to(users::refresh))
+        .route("/logout", web::post().to(users::logout))
+        .route("/password-reset/request", web::post().to(password_reset::request_password_reset))
+        .route("/password-reset/confirm", web::post().to(password_reset::confirm_password_reset))
+        .route("/api-keys", web::get().to(api_keys::list_api_keys))
+        .route("/api-keys", web::post().to(api_keys::create_api_key))
+        .route("/api-keys/{key_id}", web::delete().to(api_keys::delete_api_key))
+        .route("/batch", web::post().to(batch::batch))
+        // Before /todos/{todo_id} so "trash" isn't taken for an id
+        .route("/todos/trash", web::get().to(todos::get_trash))
+        .route("/todos/stats", web::get().to(stats::get_stats))
+        .route("/todos/events", web::get().to(handlers::events::todo_events))
+        .route("/todos/export", web::get().to(todos::export_todos))
+        .route("/todos/export.ics", web::get().to(todos::export_todos_ics))
+        .route("/todos/import", web::post().to(todos::import_todos))
+        .route("/todos/bulk", web::patch().to(todos::bulk_update_todos))
+        .route("/todos/complete-all", web::post().to(todos::complete_all_todos))
+        .route("/todos/incomplete-all", web::post().to(todos::incomplete_all_todos))
+        .route("/todos/completed", web::delete().to(todos::clear_completed_todos))
+        .route("/todos/{todo_id}", web::get().to(todos::get_todo_by_id))
+        .route("/todos/{todo_id}", web::patch().to(todos::update_todo))
+        .route("/user/{user_id}", web::patch().to(users::update_user))
+        .route("/todos/{todo_id}", web::delete().to(todos::delete_todo))
+        .route("/todos/{todo_id}/restore", web::post().to(todos::restore_todo))
+        .route("/todos/{todo_id}/complete", web::post().to(todos::complete_todo))
+        .route("/todos/{todo_id}/incomplete", web::post().to(todos::incomplete_todo))
+        .route("/todos/{todo_id}/snooze", web::post().to(todos::snooze_todo))
+        .route("/todos/{todo_id}/duplicate", web::post().to(todos::duplicate_todo))
+        .route("/todos/{todo_id}/move", web::patch().to(todos::move_todo))
+        .route("/todos/{todo_id}/recurrence", web::patch().to(todos::set_todo_recurrence))
+        .route("/todos/{todo_id}/archive", web::post().to(todos::archive_todo))
+        .route("/todos/{todo_id}/unarchive", web::post().to(todos::unarchive_todo))
+        .route("/todos/{todo_id}/permanent", web::delete().to(todos::purge_todo))
+        .route("/todos/{todo_id}/move-to-user", web::post().to(todos::move_todo_to_user))
+        .route("/todos/{todo_id}/notes", web::get().to(notes::get_notes))
+        .route("/todos/{todo_id}/notes", web::put().to(notes::put_notes))
+        .route("/todos/{todo_id}/comments", web::get().to(comments::list_comments))
+        .route("/todos/{todo_id}/comments", web::post().to(comments::create_comment))
+        .route("/todos/{todo_id}/comments/{comment_id}", web::patch().to(comments::update_comment))
+        .route("/todos/{todo_id}/comments/{comment_id}", web::delete().to(comments::delete_comment))
+        .route("/todos/{todo_id}/shares", web::post().to(shares::share_todo))
+        .route("/todos/{todo_id}/shares/{user_id}", web::delete().to(shares::unshare_todo))
+        .route("/todos/{todo_id}/time", web::get().to(time_entries::list_time_entries))
+        .route("/todos/{todo_id}/time/start", web::post().to(time_entries::start_timer))
+        .route("/todos/{todo_id}/time/stop", web::post().to(time_entries::stop_timer))
+        .route("/todos/{todo_id}/dependencies", web::get().to(dependencies::list_dependencies))
+        .route("/todos/{todo_id}/dependencies", web::post().to(dependencies::add_dependency))
+        .route("/todos/{todo_id}/dependencies/{blocker_id}", web::delete().to(dependencies::remove_dependency))
+        .route("/todos/{todo_id}/subtasks", web::get().to(subtasks::list_subtasks))
+        .route("/todos/{todo_id}/subtasks", web::post().to(subtasks::create_subtask))
+        .route("/todos/{todo_id}/subtasks/{subtask_id}", web::patch().to(subtasks::update_subtask))
+        .route("/todos/{todo_id}/subtasks/{subtask_id}", web::delete().to(subtasks::delete_subtask))
+        .route("/users", web::get().to(users::list_users))
+        .route("/users/search", web::get().to(users::search_users))
+        .route("/users/{user_id}/todos", web::get().to(todos::get_user_todos))
+        .route("/users/{user_id}/activity", web::get().to(handlers::activity::get_activity))
+        .route("/users/{user_id}/stats/history", web::get().to(stats::get_stats_history))
+        .route("/users/{user_id}/stats/reset", web::post().to(stats::reset_stats))
+        .route("/admin/audit-log", web::get().to(handlers::audit::get_audit_log))
+        .route("/users/{user_id}/preferences", web::get().to(preferences::get_preferences))
+        .route("/users/{user_id}/preferences", web::patch().to(preferences::update_preferences))
+        .route("/users/{user_id}/change-password", web::post().to(users::change_password))
+        .route("/users/{user_id}/2fa/setup", web::post().to(two_factor::setup_two_factor))
+        .route("/users/{user_id}/2fa/verify", web::post().to(two_factor::verify_two_factor))
+        .route("/users/{user_id}/2fa/disable", web::post().to(two_factor::disable_two_factor))
+        .route("/users/{user_id}/data", web::delete().to(users::erase_user_data))
+        .route("/users/{user_id}/export", web::get().to(data_export::export_user_data))
+        .route("/users/{user_id}/email/verify/send", web::post().to(email_verification::send_email_verification))
+        .route("/users/{user_id}/email/verify/confirm", web::post().to(email_verification::confirm_email_verification))
+        .route("/users/{user_id}/role", web::patch().to(users::update_user_role))
+        .route("/users/{user_id}/avatar", web::patch().to(users::update_avatar))
+        .route("/users/{user_id}/avatar", web::delete().to(users::delete_avatar))
+        .route("/users/{user_id}", web::get().to(users::get_user))
+        .route("/users/{user_id}", web::delete().to(users::delete_user));
+}
@@ -1,313 +1,300 @@
-use actix_web::{web, App, HttpServer, HttpResponse, Responder};
+use actix_web::middleware::Compress;
+use actix_web::{web, App, HttpServer};
+use actix_web_opentelemetry::RequestTracing;
+use chrono::Utc;
 use dotenvy::dotenv;
-use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
-use std::env;
-use actix_web::dev::Path;
-use actix_web::http::header::q;
-use serde_json::json;
-use sqlx::postgres::PgRow;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::time::Duration;
+
+use todo_backend::auth::admin::seed_admin;
+use todo_backend::auth::jwt;
+use todo_backend::auth::TokenDenylist;
+use todo_backend::config::AppConfig;
+use todo_backend::events;
+use todo_backend::idempotency;
+use todo_backend::middleware::api_version::ApiVersionMiddleware;
+use todo_backend::middleware::body_limit::{extractor_limits, BodyLimit};
+use todo_backend::middleware::compress::CompressionThreshold;
+use todo_backend::middleware::cors::build_cors;
+use todo_backend::middleware::error_reporting::SentryContext;
+use todo_backend::middleware::logging::RequestLogger;
+use todo_backend::middleware::metrics::RequestMetrics;
+use todo_backend::middleware::rate_limit::{RateLimitStore, RateLimiter};
+use todo_backend::middleware::request_id::RequestIdMiddleware;
+use todo_backend::middleware::security_headers::SecurityHeadersMiddleware;
+use todo_backend::middleware::timeout::RequestTimeout;
+use todo_backend::middleware::tracing::TraceContext;
+use todo_backend::recurrence;
+use todo_backend::snapshots;
+use todo_backend::telemetry;
+use todo_backend::todo_cache::TodoCache;
+use todo_backend::todo_titles::enforce_unique_titles;
+use todo_backend::{configure_routes, MIGRATOR};
+
+// Handlers that haven't produced a response by then get a 408
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Smaller responses are sent as they are, compressing them saves next to nothing
+const COMPRESSION_MIN_SIZE: u64 = 1024;
+
+// Requests get this long to finish after SIGTERM, the process exits when it runs out
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const SHUTDOWN_WARNING: Duration = Duration::from_secs(20);
+
+// Resolves on SIGTERM (e.g. from the orchestrator) or Ctrl-C
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to install the SIGTERM handler");
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = tokio::signal::ctrl_c() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL not found in env file");
-    let server_addr = env::var("SERVER_ADDR").expect("SERVER_ADDR not found in env file");
-
-    let pool = PgPool::connect(&database_url)
-        .await
-        .expect("Failed to create database pool");
-
-    HttpServer::new(move || {
-        App::new()
-            .app_data(web::Data::new(pool.clone()))
-            .route("/", web::get().to(home_page))
-            .route("/todos", web::get().to(get_todos))
-            .route("/todos", web::post().to(create_todo))
-            .route("/register", web::post().to(create_user))
-            .route("/todos/{todo_id}", web::patch().to(update_todo))
-            .route("/user/{user_id}", web::patch().to(update_user))
-            .route("/todos/{todo_id}", web::delete().to(delete_todo))
-            .route("/users/{user_id}", web::delete().to(delete_user))
-    })
-        .bind(&server_addr)?
-        .run()
-        .await
-}
-
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-struct Todo {
-    id: Option<i32>,
-    title: Option<String>,
-    completed: Option<bool>,
-    description: Option<String>,
-}
+    // Nothing is logged yet, so the problems go straight to stderr
+    let config = AppConfig::load().unwrap_or_else(|e| {
+        eprint!("{}", e);
+        std::process::exit(1);
+    });
+
+    let tracer_provider = telemetry::init(&config);
+
+    // Flushes what is still queued when dropped at the end of main
+    let _sentry = config.sentry_dsn.as_deref().map(|dsn| {
+        let mut options = sentry::ClientOptions::default();
+        options.release = sentry::release_name!();
+        sentry::init((dsn, options))
+    });
+
+    // Logged here rather than while loading, tracing isn't set up until now
+    match &config.config_file {
+        Some(path) => tracing::info!(path = %path.display(), "config file loaded"),
+        None => tracing::warn!("no config file found, using environment variables only"),
+    }
+    for warning in &config.warnings {
+        tracing::warn!("{}", warning);
+    }
+    jwt::init_secret(config.jwt_secret.clone());
+
+    tracing::info!(
+        max_connections = config.max_connections,
+        min_connections = config.min_connections,
+        acquire_timeout = ?config.acquire_timeout,
+        idle_timeout = ?config.idle_timeout,
+        max_lifetime = ?config.max_lifetime,
+        test_before_acquire = config.test_before_acquire,
+        "database pool configured"
+    );
+
+    let mut connect_options: PgConnectOptions = config.database_url.parse().expect("Invalid DATABASE_URL");
+    if let Some(password) = &config.db_password {
+        connect_options = connect_options.password(password);
+    }
 
+    let pool_options = PgPoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(config.acquire_timeout)
+        .idle_timeout(config.idle_timeout)
+        .max_lifetime(config.max_lifetime)
+        .test_before_acquire(config.test_before_acquire);
+
+    // The database may still be starting, e.g. when both come up with Docker Compose
+    let mut attempt = 1;
+    let pool = loop {
+        match pool_options.clone().connect_with(connect_options.clone()).await {
+            Ok(pool) => break pool,
+            Err(e) if attempt < config.db_connect_retries => {
+                tracing::warn!(error = %e, "Waiting for database... attempt {}/{}", attempt, config.db_connect_retries);
+                tokio::time::sleep(config.db_connect_retry_delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                tracing::error!(
+                    error = %e,
+                    "could not connect to the database after {} attempts, giving up",
+                    config.db_connect_retries
+                );
+                std::process::exit(1);
+            }
+        }
+    };
 
-#[derive(Deserialize, Serialize)]
-struct UpdateTaskReq {
-    title: Option<String>,
-    completed: Option<bool>,
-    description: Option<String>,
-}
+    if !config.skip_migrations {
+        MIGRATOR
+            .run(&pool)
+            .await
+            .expect("Migration failed");
+    }
 
-#[derive(Deserialize,Serialize)]
-struct UpdateUserReq {
-    name: Option<String>, // Optional field for updating
-    password: Option<String>, // Optional field for updating
-}
+    // The index behind PREVENT_DUPLICATE_TITLES follows the setting on every start
+    if let Err(e) = enforce_unique_titles(&pool, config.prevent_duplicate_titles).await {
+        tracing::error!(
+            error = %e,
+            "PREVENT_DUPLICATE_TITLES: could not update the title index, users may already have duplicate titles"
+        );
+        std::process::exit(1);
+    }
 
-#[derive(Serialize)]
-struct TodoResponse {
-    id: i32,
-    title: String,
-    completed: bool,
-    description: String,
-}
+    // Without an admin nobody could list or delete users, so the first one comes from the environment
+    if let (Some(name), Some(password)) = (&config.admin_name, &config.admin_password) {
+        match seed_admin(&pool, name, password).await {
+            Ok(Some(user_id)) => tracing::info!(user_id, "created the initial admin"),
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = %e, "failed to create the initial admin"),
+        }
+    }
 
-#[derive(Deserialize)]
-struct NewUser {
-    name: String,
-    password: String,
-}
-#[derive(Serialize)]
-struct UserResponse {
-    id: i32,
-    name: String,
-}
+    let server_addr = config.server_addr.clone();
 
-#[derive(Serialize)]
-struct User {
-    id: i32,
-    name: String,
-    password: String,
-}
-// Handler for fetching todos
-async fn get_todos(pool: web::Data<PgPool>) -> impl Responder {
-    let todos = sqlx::query_as::<_, Todo>("SELECT * FROM todos")
-        .fetch_all(pool.get_ref())
-        .await
-        .expect("Failed to fetch todos");
-
-    HttpResponse::Ok().json(todos)
-}
+    let rate_limits = web::Data::new(RateLimitStore::new(config.rate_limit, config.auth_rate_limit));
 
-// Handler for updating a todo
-async fn update_todo(
-    pool: web::Data<PgPool>,
-    todo_data: web::Json<UpdateTaskReq>,
-    todo_id: web::Path<i32>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let todo_id = todo_id.into_inner();
-
-    // SQL query to update title, completed, and description, excluding the id
-    let result = sqlx::query(
-        "UPDATE todos SET title = $1, completed = $2, description = $3 WHERE id = $4"
-    )
-        .bind(todo_data.title.clone().unwrap_or_else(|| "Untitled".to_string())) // Title or default
-        .bind(todo_data.completed.unwrap_or(false))                             // Completed status or default
-        .bind(todo_data.description.clone().unwrap_or_else(|| "".to_string()))   // Description or default
-        .bind(todo_id)                                                           // Bind the todo_id to ensure we don't change it
-        .execute(pool.get_ref())
-        .await;
-
-    match result {
-        Ok(_) => {
-            // Fetch the updated todo to return it in the response
-            let updated_todo = sqlx::query_as::<_, Todo>("SELECT * FROM todos WHERE id = $1")
-                .bind(todo_id)
-                .fetch_one(pool.get_ref())
-                .await
-                .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
-
-            Ok(HttpResponse::Ok().json(updated_todo)) // Return updated todo
+    // Drop buckets of clients that went quiet so the map doesn't grow forever
+    let purge_store = rate_limits.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            purge_store.purge_idle();
         }
-        Err(e) => Err(actix_web::error::ErrorInternalServerError(e.to_string())), // Handle error
-    }
-}
-
-async fn create_user(
-    pool:web::Data<PgPool>,
-    new_user: web::Json<NewUser>
-) -> Result<HttpResponse, actix_web::Error> {
-    let query = sqlx::query!(
-    r#"INSERT INTO "Users" (name, password) VALUES ($1, $2) RETURNING id"#,
-    new_user.name,
-    new_user.password,
-)
-        .fetch_one(pool.get_ref())
-        .await;
-    match query {
-        Ok(row) => {
-            let user_id = row.id; // Assuming the returned row has an `id` field
-            let row = sqlx::query!("SELECT id, name FROM \"Users\" WHERE id = $1", user_id) // Only select the fields you need
-                .fetch_one(pool.get_ref())
-                .await
-                .map_err(|e| {
-                    eprintln!("Error fetching user: {}", e);
-                    actix_web::error::ErrorInternalServerError("Database query failed")
-                })?;
-
-            // Map the row to the UserResponse struct
-            let user_response = UserResponse {
-                id: row.id,
-                name: row.name,
-            };
-
-            Ok(HttpResponse::Created().json(user_response))
+    });
+
+    // Notice a database that went away even while no requests come in
+    let ping_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = sqlx::query("SELECT 1").execute(&ping_pool).await {
+                tracing::error!(error = %e, "database pool is unhealthy");
+            }
         }
-        Err(e) => {
-            // Handle the error (you can log it, etc.)
-            eprintln!("Failed to create user: {:?}", e);
-            Err(actix_web::error::ErrorInternalServerError("Failed to create user"))
+    });
+
+    // Idempotency keys can only be replayed for 24 hours, clear out the older ones
+    let purge_pool = pool.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = idempotency::purge_expired(&purge_pool).await {
+                tracing::warn!(error = %e, "failed to purge expired idempotency keys");
+            }
         }
-    }
-}
-
-async fn delete_user(
-    pool: web::Data<PgPool>,
-    user_id: web::Path<i32>
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = user_id.into_inner();
-    let existing_user = sqlx::query!("SELECT * FROM \"Users\" WHERE id = $1", user_id)
-        .fetch_optional(pool.get_ref())
-        .await;
-
-    match existing_user {
-        Ok(Some(_)) => {
-            // If user exists, proceed to delete
-            let query = sqlx::query!("DELETE FROM \"Users\" WHERE id = $1", user_id)
-                .execute(pool.get_ref())
-                .await;
-
-            match query {
-                Ok(_) => {
-                    Ok(HttpResponse::Ok().body("User successfully deleted"))
-                },
-                Err(e) => {
-                    eprintln!("Failed to delete user: {:?}", e);
-                    Err(actix_web::error::ErrorInternalServerError("Failed to delete user"))
-                }
+    });
+
+    let todo_cache = web::Data::new(TodoCache::new());
+
+    // Create the next copy of recurring todos that were completed and are due again
+    let recurrence_pool = pool.clone();
+    let recurrence_cache = todo_cache.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            if let Err(e) = recurrence::create_due_occurrences(&recurrence_pool, Some(&recurrence_cache)).await {
+                tracing::warn!(error = %e, "failed to create recurring todos");
             }
-        },
-        Ok(None) => {
-            // If no user is found with the given ID
-            Ok(HttpResponse::NotFound().body("User not found"))
-        },
-        Err(e) => {
-            eprintln!("Error checking user existence: {:?}", e);
-            Err(actix_web::error::ErrorInternalServerError("Error checking user existence"))
         }
-    }
-}
-async fn update_user(
-    pool: web::Data<PgPool>,
-    user_id: web::Path<i32>,
-    user_data: web::Json<UpdateUserReq>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let user_id = user_id.into_inner();
-
-    // First, check if the user exists
-    let existing_user = sqlx::query_as!(
-        User,
-        "SELECT id, name, password FROM \"Users\" WHERE id = $1",
-        user_id
-    )
-        .fetch_optional(pool.get_ref())
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching user: {:?}", e);
-            actix_web::error::ErrorInternalServerError("Database query failed")
-        })?;
-
-    // If the user does not exist, return a 404 response
-    if existing_user.is_none() {
-        return Ok(HttpResponse::NotFound().body("User not found"));
-    }
-
-    // Proceed to update the user
-    let query = sqlx::query!(
-        "UPDATE \"Users\" SET name = COALESCE($1, name), password = COALESCE($2, password) WHERE id = $3",
-        user_data.name.as_deref(),  // Use as_deref to convert Option<String> to Option<&str>
-        user_data.password.as_deref(),
-        user_id
-    )
-        .execute(pool.get_ref())
-        .await
-        .map_err(|e| {
-            eprintln!("Error updating user: {:?}", e);
-            actix_web::error::ErrorInternalServerError("Database query failed")
-        })?;
-
-    // Check if any rows were affected
-    if query.rows_affected() == 0 {
-        return Ok(HttpResponse::NotFound().body("User not found")); // Return 404 if no rows were affected
-    }
-
-    // Fetch the updated user to return
-    let updated_user = sqlx::query_as!(User, "SELECT id, name, password FROM \"Users\" WHERE id = $1", user_id)
-        .fetch_one(pool.get_ref())
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching updated user: {:?}", e);
-            actix_web::error::ErrorInternalServerError("Database query failed")
-        })?;
-
-    // Return the updated user as JSON
-    Ok(HttpResponse::Ok().json(updated_user)) // Returning updated user
-}
+    });
+
+    // Snapshot everyone's numbers for the day that just ended, every midnight UTC
+    let snapshot_pool = pool.clone();
+    tokio::spawn(async move {
+        let start = tokio::time::Instant::now() + snapshots::until_next_midnight();
+        let mut interval = tokio::time::interval_at(start, Duration::from_secs(24 * 60 * 60));
+        loop {
+            interval.tick().await;
+            let yesterday = Utc::now().date_naive() - chrono::Days::new(1);
+            if let Err(e) = snapshots::take_snapshots(&snapshot_pool, yesterday).await {
+                tracing::warn!(error = %e, "failed to take the daily stats snapshots");
+            }
+        }
+    });
 
+    let denylist = web::Data::new(TokenDenylist::default());
 
+    // Shared by every worker so GET /todos/events hears changes made through any of them
+    let todo_events = web::Data::new(events::channel());
 
+    let purge_denylist = denylist.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            purge_denylist.purge_expired();
+        }
+    });
 
-// Handler for deleting a todo
-async fn delete_todo(
-    pool: web::Data<PgPool>,
-    todo_id: web::Path<i32>,  // Don't destructure here
-) -> impl Responder {
-    let todo_id = todo_id.into_inner();  // Extract the value here
-    let result = sqlx::query!("DELETE FROM todos WHERE id = $1", todo_id)
-        .execute(pool.get_ref())
-        .await;
+    let app_pool = pool.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(RequestTimeout::new(REQUEST_TIMEOUT))
+            .wrap(BodyLimit::new(config.max_request_body_bytes))
+            .wrap(RateLimiter::new(rate_limits.clone()))
+            .wrap(build_cors(&config.cors_allowed_origins))
+            .wrap(ApiVersionMiddleware)
+            .wrap(RequestLogger)
+            .wrap(RequestMetrics)
+            .wrap(TraceContext)
+            .wrap(CompressionThreshold::new(COMPRESSION_MIN_SIZE))
+            .wrap(Compress::default())
+            .wrap(SecurityHeadersMiddleware::new(config.hsts_max_age))
+            .wrap(SentryContext)
+            // AppError reports its own 500s with the underlying error, don't send them twice
+            .wrap(sentry_actix::Sentry::builder().capture_server_errors(false).finish())
+            .wrap(RequestTracing::new())
+            .wrap(RequestIdMiddleware)
+            .app_data(web::Data::new(app_pool.clone()))
+            .app_data(web::Data::new(config.clone()))
+            .app_data(denylist.clone())
+            .app_data(todo_events.clone())
+            .app_data(todo_cache.clone())
+            .configure(extractor_limits(config.max_request_body_bytes))
+            .configure(configure_routes)
+    })
+        .keep_alive(Duration::from_secs(75))
+        .shutdown_timeout(SHUTDOWN_TIMEOUT.as_secs())
+        .disable_signals() // Handled below, so the pool is closed after the drain too
+        .bind(&server_addr)?
+        .run();
 
-    match result {
-        Ok(_) => HttpResponse::NoContent().finish(),
-        Err(e) => HttpResponse::InternalServerError().json(format!("Failed to delete todo: {}", e)),
-    }
-}
+    let server_handle = server.handle();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        tracing::info!("shutting down, waiting for in-flight requests");
 
+        // Don't hang forever on a request or connection that never finishes
+        tokio::spawn(async {
+            tokio::time::sleep(SHUTDOWN_WARNING).await;
+            tracing::warn!("shutdown is taking longer than {} seconds", SHUTDOWN_WARNING.as_secs());
+            tokio::time::sleep(SHUTDOWN_TIMEOUT - SHUTDOWN_WARNING).await;
+            tracing::error!("shutdown did not finish in {} seconds, exiting", SHUTDOWN_TIMEOUT.as_secs());
+            std::process::exit(1);
+        });
 
-// Handler for creating a new todo
-async fn create_todo(
-    pool: web::Data<PgPool>,
-    new_todo: web::Json<Todo>,
-) -> Result<HttpResponse, actix_web::Error> {
-    let row = sqlx::query!(
-        r#"INSERT INTO todos (title, completed, description) VALUES ($1, $2, $3) RETURNING id, title, completed, description"#,
-        new_todo.title.clone().unwrap_or_else(|| "Untitled".to_string()),
-        new_todo.completed.unwrap_or(false),
-        new_todo.description.clone().unwrap_or_else(|| "".to_string()),
-    )
-        .fetch_one(pool.get_ref())
-        .await
-        .map_err(|e| actix_web::error::ErrorInternalServerError(e.to_string()))?;
-
-    let response = TodoResponse {
-        id: row.id,
-        title: row.title,
-        completed: row.completed,
-        description: row.description.unwrap(),
-    };
+        server_handle.stop(true).await;
+    });
 
-    Ok(HttpResponse::Created().json(response))
-}
+    let result = server.await;
 
+    // Requests are done, so every transaction was committed or rolled back; close the connections cleanly
+    pool.close().await;
 
+    // Send the spans still waiting in the batch before exiting
+    if let Some(provider) = tracer_provider {
+        let _ = tokio::task::spawn_blocking(move || telemetry::shutdown(provider)).await;
+    }
 
-// Home page handler
-async fn home_page() -> impl Responder {
-    "Welcome to the Todo API"
+    result
 }
-
-
@@ -0,0 +1,64 @@
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use sqlx::PgPool;
+use std::sync::LazyLock;
+
+// Every metric the server exposes at GET /metrics
+pub static REGISTRY: LazyLock<Registry> = LazyLock::new(|| {
+    let registry = Registry::new();
+    registry.register(Box::new(HTTP_REQUESTS_TOTAL.clone())).unwrap();
+    registry.register(Box::new(HTTP_REQUEST_DURATION_SECONDS.clone())).unwrap();
+    registry.register(Box::new(TODOS_CREATED_TOTAL.clone())).unwrap();
+    registry.register(Box::new(TODOS_DELETED_TOTAL.clone())).unwrap();
+    registry.register(Box::new(DB_POOL_CONNECTIONS.clone())).unwrap();
+    registry.register(Box::new(DB_POOL_MAX_CONNECTIONS.clone())).unwrap();
+    registry
+});
+
+// `path` is the route pattern, e.g. /todos/{todo_id}, so ids don't each get a series
+pub static HTTP_REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    IntCounterVec::new(
+        Opts::new("http_requests_total", "HTTP requests handled"),
+        &["method", "path", "status"],
+    )
+    .unwrap()
+});
+
+pub static HTTP_REQUEST_DURATION_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    HistogramVec::new(
+        HistogramOpts::new("http_request_duration_seconds", "Time spent handling HTTP requests"),
+        &["method", "path"],
+    )
+    .unwrap()
+});
+
+pub static TODOS_CREATED_TOTAL: LazyLock<IntCounter> =
+    LazyLock::new(|| IntCounter::new("todos_created_total", "Todos created through POST /todos").unwrap());
+
+pub static TODOS_DELETED_TOTAL: LazyLock<IntCounter> =
+    LazyLock::new(|| IntCounter::new("todos_deleted_total", "Todos moved to the trash").unwrap());
+
+// `state` is active (lent to a query) or idle. sqlx doesn't report how many tasks wait for a
+// connection; active at the maximum with no idle ones left means new queries are queuing.
+pub static DB_POOL_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    IntGaugeVec::new(Opts::new("db_pool_connections", "Open database connections"), &["state"]).unwrap()
+});
+
+pub static DB_POOL_MAX_CONNECTIONS: LazyLock<IntGauge> =
+    LazyLock::new(|| IntGauge::new("db_pool_max_connections", "Most connections the pool may open").unwrap());
+
+// Refresh the pool gauges, called on every scrape
+pub fn record_pool(pool: &PgPool) {
+    let idle = pool.num_idle() as i64;
+    DB_POOL_CONNECTIONS.with_label_values(&["active"]).set(i64::from(pool.size()) - idle);
+    DB_POOL_CONNECTIONS.with_label_values(&["idle"]).set(idle);
+    DB_POOL_MAX_CONNECTIONS.set(i64::from(pool.options().get_max_connections()));
+}
+
+// All metrics in the Prometheus text exposition format
+pub fn render() -> Result<String, prometheus::Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer)?;
+    Ok(String::from_utf8(buffer).unwrap_or_default())
+}
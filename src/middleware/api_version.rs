@@ -0,0 +1,170 @@
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::InternalError;
+use actix_web::guard::{self, Guard};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{FromRequest, HttpMessage, HttpRequest, ResponseError};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+
+use crate::error::AppError;
+
+pub const API_VERSION_HEADER: &str = "X-API-Version";
+
+// The versions the API can serve. A V2 goes here, with its own prefix and header value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    V1,
+}
+
+impl ApiVersion {
+    // What requests without a prefix or header get, so existing clients keep working
+    pub const DEFAULT: ApiVersion = ApiVersion::V1;
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/v1",
+        }
+    }
+
+    pub fn header_value(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "1",
+        }
+    }
+
+    fn from_header(value: &str) -> Option<Self> {
+        // "v1" is common enough to accept as well
+        match value.trim().trim_start_matches(['v', 'V']) {
+            "1" => Some(ApiVersion::V1),
+            _ => None,
+        }
+    }
+
+    fn from_path(path: &str) -> Option<Self> {
+        [ApiVersion::V1].into_iter().find(|version| {
+            path.strip_prefix(version.prefix())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+}
+
+// The path without its version prefix, for code that matches on the un-prefixed routes
+pub fn unversioned_path(path: &str) -> &str {
+    match ApiVersion::from_path(path) {
+        Some(version) => &path[version.prefix().len()..],
+        None => path,
+    }
+}
+
+// The prefix in the path wins, then X-API-Version, then the default
+fn resolve(req: &HttpRequest) -> Result<ApiVersion, AppError> {
+    if let Some(version) = ApiVersion::from_path(req.path()) {
+        return Ok(version);
+    }
+
+    match req.headers().get(API_VERSION_HEADER) {
+        None => Ok(ApiVersion::DEFAULT),
+        Some(value) => value
+            .to_str()
+            .ok()
+            .and_then(ApiVersion::from_header)
+            .ok_or_else(|| AppError::BadRequest(format!("Unsupported {}, supported versions: 1", API_VERSION_HEADER))),
+    }
+}
+
+// Only matches requests resolved to the given version, for registering a route per version:
+// `.route("/todos", web::get().guard(version_guard(ApiVersion::V2)).to(...))`
+pub fn version_guard(version: ApiVersion) -> impl Guard {
+    guard::fn_guard(move |ctx| ctx.req_data().get::<ApiVersion>() == Some(&version))
+}
+
+// The version the request was resolved to by ApiVersionMiddleware
+pub struct VersionExtractor(pub ApiVersion);
+
+impl FromRequest for VersionExtractor {
+    type Error = AppError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        // Resolved here when the middleware isn't installed (e.g. in tests)
+        let version = match req.extensions().get::<ApiVersion>() {
+            Some(version) => Ok(*version),
+            None => resolve(req),
+        };
+
+        ready(version.map(VersionExtractor))
+    }
+}
+
+// Resolves the version once, keeps it in the request extensions and answers with X-API-Version.
+// Unsupported versions are refused with 400 before reaching a handler.
+pub struct ApiVersionMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for ApiVersionMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = ApiVersionService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ApiVersionService { service }))
+    }
+}
+
+pub struct ApiVersionService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for ApiVersionService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let header = |version: ApiVersion| {
+            (
+                HeaderName::from_static("x-api-version"),
+                HeaderValue::from_static(version.header_value()),
+            )
+        };
+
+        let version = match resolve(req.request()) {
+            Ok(version) => version,
+            Err(e) => {
+                let mut resp = e.error_response();
+                let (name, value) = header(ApiVersion::DEFAULT);
+                resp.headers_mut().insert(name, value);
+                return Box::pin(async move { Err(InternalError::from_response(e, resp).into()) });
+            }
+        };
+        req.extensions_mut().insert(version);
+
+        let (name, value) = header(version);
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            match fut.await {
+                Ok(mut resp) => {
+                    resp.headers_mut().insert(name, value);
+                    Ok(resp)
+                }
+                Err(e) => {
+                    let mut resp = e.error_response();
+                    resp.headers_mut().insert(name, value);
+                    Err(InternalError::from_response(e, resp).into())
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,105 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::{InternalError, JsonPayloadError, UrlencodedError};
+use actix_web::http::header::CONTENT_LENGTH;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+
+use crate::error::{problem_types, ProblemDetails};
+
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+// MAX_REQUEST_BODY_BYTES above this is lowered to it
+pub const MAX_BODY_BYTES_CAP: usize = 10 * 1024 * 1024;
+
+// 413 as a problem rather than actix-web's plain text
+fn payload_too_large(limit: usize) -> actix_web::Error {
+    let response = ProblemDetails::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        problem_types::PAYLOAD_TOO_LARGE,
+        &format!("Request body is larger than {} bytes", limit),
+    )
+    .response();
+    InternalError::from_response("request body too large", response).into()
+}
+
+// The body extractors stop reading at the limit, which also covers chunked bodies without a
+// Content-Length. Other JSON and form errors keep actix-web's handling.
+pub fn extractor_limits(max_bytes: usize) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        cfg.app_data(web::JsonConfig::default().limit(max_bytes).error_handler(move |err, _| match err {
+            JsonPayloadError::Overflow { .. } | JsonPayloadError::OverflowKnownLength { .. } => {
+                payload_too_large(max_bytes)
+            }
+            err => err.into(),
+        }))
+        .app_data(web::FormConfig::default().limit(max_bytes).error_handler(move |err, _| match err {
+            UrlencodedError::Overflow { .. } => payload_too_large(max_bytes),
+            err => err.into(),
+        }))
+        .app_data(web::PayloadConfig::new(max_bytes));
+    }
+}
+
+// Rejects requests that announce a larger body before any of it is read, whatever the route
+// extracts it as
+pub struct BodyLimit {
+    max_bytes: usize,
+}
+
+impl BodyLimit {
+    pub fn new(max_bytes: usize) -> Self {
+        BodyLimit { max_bytes }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for BodyLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = BodyLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(BodyLimitMiddleware {
+            service,
+            max_bytes: self.max_bytes,
+        }))
+    }
+}
+
+pub struct BodyLimitMiddleware<S> {
+    service: S,
+    max_bytes: usize,
+}
+
+impl<S, B> Service<ServiceRequest> for BodyLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<usize>().ok());
+
+        if length.is_some_and(|length| length > self.max_bytes) {
+            let max_bytes = self.max_bytes;
+            return Box::pin(async move { Err(payload_too_large(max_bytes)) });
+        }
+
+        Box::pin(self.service.call(req))
+    }
+}
@@ -0,0 +1,75 @@
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_TYPE};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+
+// Compress only handles Accept-Encoding, so this goes just inside it and marks the responses
+// it should leave alone as `Content-Encoding: identity`, which Compress never re-encodes.
+pub struct CompressionThreshold {
+    min_size: u64,
+}
+
+impl CompressionThreshold {
+    pub fn new(min_size: u64) -> Self {
+        CompressionThreshold { min_size }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CompressionThreshold
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = CompressionThresholdMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CompressionThresholdMiddleware {
+            service,
+            min_size: self.min_size,
+        }))
+    }
+}
+
+pub struct CompressionThresholdMiddleware<S> {
+    service: S,
+    min_size: u64,
+}
+
+impl<S, B> Service<ServiceRequest> for CompressionThresholdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let min_size = self.min_size;
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut resp = fut.await?;
+
+            // Small bodies barely shrink, and SSE events must reach the client as they are sent
+            let too_small = matches!(resp.response().body().size(), BodySize::Sized(len) if len < min_size);
+            let event_stream = resp
+                .headers()
+                .get(CONTENT_TYPE)
+                .is_some_and(|value| value.as_bytes().starts_with(b"text/event-stream"));
+
+            if (too_small || event_stream) && !resp.headers().contains_key(CONTENT_ENCODING) {
+                resp.headers_mut()
+                    .insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+            }
+            Ok(resp)
+        })
+    }
+}
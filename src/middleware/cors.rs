@@ -0,0 +1,22 @@
+use actix_cors::Cors;
+
+use crate::handlers::TOTAL_COUNT_HEADER;
+use crate::middleware::api_version::API_VERSION_HEADER;
+use crate::middleware::request_id::REQUEST_ID_HEADER;
+
+// CORS policy for browser clients. "*" in the list allows every origin (the development default).
+pub fn build_cors(allowed_origins: &[String]) -> Cors {
+    let cors = Cors::default()
+        .allow_any_method()
+        .allow_any_header()
+        .expose_headers([REQUEST_ID_HEADER, API_VERSION_HEADER, TOTAL_COUNT_HEADER, "Location"])
+        .max_age(3600);
+
+    if allowed_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
+
+    allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+}
@@ -0,0 +1,60 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+
+use crate::middleware::request_id::get_request_id;
+
+// Tags everything Sentry captures while handling the request with its request id. Goes just
+// inside sentry_actix::Sentry, which gives every request a scope of its own. Without a
+// SENTRY_DSN the scope is never sent anywhere.
+pub struct SentryContext;
+
+impl<S, B> Transform<S, ServiceRequest> for SentryContext
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = SentryContextMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(SentryContextMiddleware { service }))
+    }
+}
+
+pub struct SentryContextMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for SentryContextMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let request_id = get_request_id(req.request());
+        sentry::configure_scope(|scope| scope.set_tag("request_id", request_id));
+
+        Box::pin(self.service.call(req))
+    }
+}
+
+// Attach the authenticated caller to what Sentry captures for the rest of the request. The user
+// is only known once the AuthUser extractor ran, after the middleware.
+pub fn set_user(user_id: i32) {
+    sentry::configure_scope(|scope| {
+        scope.set_user(Some(sentry::User {
+            id: Some(user_id.to_string()),
+            ..Default::default()
+        }))
+    });
+}
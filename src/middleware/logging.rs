@@ -0,0 +1,82 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::time::Instant;
+use tracing::Instrument;
+
+use crate::middleware::request_id::get_request_id;
+
+// Logs one line per request: method, path, status and duration.
+// Bodies are never logged since they can carry passwords.
+pub struct RequestLogger;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestLogger
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = RequestLoggerMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestLoggerMiddleware { service }))
+    }
+}
+
+pub struct RequestLoggerMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestLoggerMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let request_id = get_request_id(req.request());
+
+        let span = tracing::info_span!(
+            "request",
+            method = %method,
+            path = %path,
+            request_id = %request_id,
+        );
+
+        let fut = self.service.call(req);
+
+        Box::pin(
+            async move {
+                let result = fut.await;
+                let duration_ms = started.elapsed().as_millis() as u64;
+
+                match &result {
+                    Ok(resp) => tracing::info!(
+                        status_code = resp.status().as_u16(),
+                        duration_ms,
+                        "request completed"
+                    ),
+                    Err(e) => tracing::warn!(
+                        status_code = e.as_response_error().status_code().as_u16(),
+                        duration_ms,
+                        "request failed"
+                    ),
+                }
+
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
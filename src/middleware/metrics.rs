@@ -0,0 +1,67 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::time::Instant;
+
+use crate::metrics::{HTTP_REQUESTS_TOTAL, HTTP_REQUEST_DURATION_SECONDS};
+
+// Counts every request and records how long it took, labelled by method, route and status
+pub struct RequestMetrics;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = MetricsMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddleware { service }))
+    }
+}
+
+pub struct MetricsMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().to_string();
+        // Unknown paths share one label so scanners can't blow up the series count
+        let path = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+
+            let status = match &result {
+                Ok(resp) => resp.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            HTTP_REQUESTS_TOTAL
+                .with_label_values(&[method.as_str(), path.as_str(), status.as_str()])
+                .inc();
+            HTTP_REQUEST_DURATION_SECONDS
+                .with_label_values(&[method.as_str(), path.as_str()])
+                .observe(started.elapsed().as_secs_f64());
+
+            result
+        })
+    }
+}
@@ -0,0 +1,12 @@
+pub mod api_version;
+pub mod body_limit;
+pub mod compress;
+pub mod cors;
+pub mod error_reporting;
+pub mod logging;
+pub mod metrics;
+pub mod rate_limit;
+pub mod request_id;
+pub mod security_headers;
+pub mod timeout;
+pub mod tracing;
@@ -0,0 +1,177 @@
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderValue, RETRY_AFTER};
+use actix_web::http::StatusCode;
+use actix_web::web;
+use dashmap::DashMap;
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+
+use crate::error::{problem_types, ProblemDetails};
+use crate::middleware::api_version::unversioned_path;
+
+// Login and registration get the strict limit since they are the brute-force targets, under /v1 too
+const AUTH_PATHS: &[&str] = &["/register", "/login", "/refresh", "/password-reset/request", "/password-reset/confirm"];
+// Probes and metric scrapes must never be throttled
+const EXEMPT_PATHS: &[&str] = &["/health", "/ready", "/metrics"];
+
+// `max_requests` per `window_seconds`, refilled continuously
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub window_seconds: u64,
+}
+
+impl RateLimit {
+    fn refill_per_second(&self) -> f64 {
+        self.max_requests as f64 / self.window_seconds.max(1) as f64
+    }
+}
+
+#[derive(Debug)]
+pub struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimit) -> Self {
+        TokenBucket {
+            tokens: limit.max_requests as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    // Take a token, or return how long until one is available
+    fn try_take(&mut self, limit: &RateLimit) -> Result<(), Duration> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.refill_per_second()).min(limit.max_requests as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let wait = (1.0 - self.tokens) / limit.refill_per_second();
+            Err(Duration::from_secs_f64(wait))
+        }
+    }
+}
+
+// Per-IP buckets shared by every worker
+pub struct RateLimitStore {
+    general: RateLimit,
+    auth: RateLimit,
+    general_buckets: DashMap<IpAddr, TokenBucket>,
+    auth_buckets: DashMap<IpAddr, TokenBucket>,
+}
+
+impl RateLimitStore {
+    pub fn new(general: RateLimit, auth: RateLimit) -> Self {
+        RateLimitStore {
+            general,
+            auth,
+            general_buckets: DashMap::new(),
+            auth_buckets: DashMap::new(),
+        }
+    }
+
+    fn check(&self, ip: IpAddr, path: &str) -> Result<(), Duration> {
+        let (limit, buckets) = if AUTH_PATHS.contains(&unversioned_path(path)) {
+            (&self.auth, &self.auth_buckets)
+        } else {
+            (&self.general, &self.general_buckets)
+        };
+
+        buckets
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::full(limit))
+            .try_take(limit)
+    }
+
+    // Forget clients that have been quiet for a whole window (their bucket would be full again anyway)
+    pub fn purge_idle(&self) {
+        let general_window = Duration::from_secs(self.general.window_seconds);
+        let auth_window = Duration::from_secs(self.auth.window_seconds);
+
+        self.general_buckets
+            .retain(|_, bucket| bucket.last_refill.elapsed() < general_window);
+        self.auth_buckets
+            .retain(|_, bucket| bucket.last_refill.elapsed() < auth_window);
+    }
+}
+
+// Rejects clients that exhausted their bucket with 429 and a Retry-After header
+pub struct RateLimiter {
+    store: web::Data<RateLimitStore>,
+}
+
+impl RateLimiter {
+    pub fn new(store: web::Data<RateLimitStore>) -> Self {
+        RateLimiter { store }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service,
+            store: self.store.clone(),
+        }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: S,
+    store: web::Data<RateLimitStore>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        if !EXEMPT_PATHS.contains(&req.path()) {
+            let ip = req
+                .peer_addr()
+                .map(|addr| addr.ip())
+                .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+            if let Err(wait) = self.store.check(ip, req.path()) {
+                let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+                let mut response = ProblemDetails::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    problem_types::RATE_LIMITED,
+                    "Too many requests, slow down",
+                )
+                .response();
+                response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after));
+
+                return Box::pin(async move { Ok(req.into_response(response).map_into_right_body()) });
+            }
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
@@ -0,0 +1,121 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{HttpMessage, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+// Longer ids from the caller are replaced, they end up in every log line
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+// What error bodies need to know about the request that failed
+struct CurrentRequest {
+    id: String,
+    path: String,
+}
+
+tokio::task_local! {
+    // Lets error bodies pick these up without every handler passing the request around
+    static CURRENT_REQUEST: CurrentRequest;
+}
+
+// Stored in the request extensions by RequestIdMiddleware
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+// The id of the request being handled, "-" when the middleware isn't installed
+pub fn get_request_id(req: &HttpRequest) -> String {
+    req.extensions()
+        .get::<RequestId>()
+        .map(|id| id.0.clone())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+// The id of the request this task is serving, for code that has no HttpRequest at hand
+pub fn current_request_id() -> Option<String> {
+    CURRENT_REQUEST.try_with(|request| request.id.clone()).ok()
+}
+
+// Likewise for the path of that request
+pub fn current_request_path() -> Option<String> {
+    CURRENT_REQUEST.try_with(|request| request.path.clone()).ok()
+}
+
+// Printable ASCII only, so a caller can't smuggle line breaks into the logs
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+// Takes the caller's X-Request-Id (or makes up a UUID), keeps it in the request extensions
+// and echoes it on the response. Goes outermost so every other middleware can see it.
+pub struct RequestIdMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = RequestIdService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestIdService { service }))
+    }
+}
+
+pub struct RequestIdService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestIdService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| is_valid(id))
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        req.extensions_mut().insert(RequestId(id.clone()));
+
+        let header = (
+            HeaderName::from_static("x-request-id"),
+            HeaderValue::from_str(&id).expect("request ids are printable ASCII"),
+        );
+        let current = CurrentRequest {
+            id,
+            path: req.path().to_string(),
+        };
+        let fut = self.service.call(req);
+
+        Box::pin(CURRENT_REQUEST.scope(current, async move {
+            match fut.await {
+                Ok(mut resp) => {
+                    resp.headers_mut().insert(header.0, header.1);
+                    Ok(resp)
+                }
+                // Render the error here, inside the scope, so its body carries the id too
+                Err(e) => {
+                    let mut resp = e.error_response();
+                    resp.headers_mut().insert(header.0, header.1);
+                    Err(InternalError::from_response(e, resp).into())
+                }
+            }
+        }))
+    }
+}
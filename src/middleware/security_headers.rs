@@ -0,0 +1,104 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::InternalError;
+use actix_web::http::header::{
+    HeaderMap, HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY, STRICT_TRANSPORT_SECURITY,
+    X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS, X_XSS_PROTECTION,
+};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+
+// Responses are JSON, nothing in them should ever be loaded or run by a browser
+const API_CSP: &str = "default-src 'none'; frame-ancestors 'none'";
+// The Swagger UI page pulls its assets from unpkg and the spec from us
+const SWAGGER_UI_CSP: &str = "default-src 'none'; script-src https://unpkg.com 'unsafe-inline'; \
+    style-src https://unpkg.com; img-src 'self' data:; connect-src 'self'; frame-ancestors 'none'";
+const SWAGGER_UI_PATH: &str = "/swagger-ui/";
+
+// Adds the usual hardening headers to every response. Strict-Transport-Security is only
+// sent when a max age is configured, i.e. when the API is known to be served over HTTPS.
+pub struct SecurityHeadersMiddleware {
+    hsts_max_age: Option<u64>,
+}
+
+impl SecurityHeadersMiddleware {
+    pub fn new(hsts_max_age: Option<u64>) -> Self {
+        SecurityHeadersMiddleware { hsts_max_age }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SecurityHeadersMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = SecurityHeadersService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        let mut headers = vec![
+            (X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")),
+            (X_FRAME_OPTIONS, HeaderValue::from_static("DENY")),
+            (X_XSS_PROTECTION, HeaderValue::from_static("1; mode=block")),
+            (REFERRER_POLICY, HeaderValue::from_static("no-referrer")),
+        ];
+        if let Some(max_age) = self.hsts_max_age {
+            let value = format!("max-age={}; includeSubDomains", max_age);
+            headers.push((STRICT_TRANSPORT_SECURITY, HeaderValue::from_str(&value).expect("digits are a valid header value")));
+        }
+
+        ready(Ok(SecurityHeadersService {
+            service,
+            headers: Rc::new(headers),
+        }))
+    }
+}
+
+pub struct SecurityHeadersService<S> {
+    service: S,
+    headers: Rc<Vec<(HeaderName, HeaderValue)>>,
+}
+
+impl<S, B> Service<ServiceRequest> for SecurityHeadersService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let csp = if req.path() == SWAGGER_UI_PATH { SWAGGER_UI_CSP } else { API_CSP };
+        let headers = self.headers.clone();
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let apply = |response_headers: &mut HeaderMap| {
+                for (name, value) in headers.iter() {
+                    response_headers.insert(name.clone(), value.clone());
+                }
+                response_headers.insert(CONTENT_SECURITY_POLICY, HeaderValue::from_static(csp));
+            };
+
+            match fut.await {
+                Ok(mut resp) => {
+                    apply(resp.headers_mut());
+                    Ok(resp)
+                }
+                // Errors from other middleware (e.g. the 408) need the headers as well
+                Err(e) => {
+                    let mut resp = e.error_response();
+                    apply(resp.headers_mut());
+                    Err(InternalError::from_response(e, resp).into())
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::InternalError;
+use actix_web::http::StatusCode;
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::time::Duration;
+
+use crate::error::{problem_types, ProblemDetails};
+use crate::handlers::metrics::is_internal;
+use crate::middleware::request_id::get_request_id;
+
+// Set by our own proxy for routes that legitimately need longer (or shorter) than the default
+pub const TIMEOUT_HEADER: &str = "X-Request-Timeout-Secs";
+// Overrides above this are ignored, so the header can't be used to hold a worker forever
+pub const MAX_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Answers 408 when the handler, including reading the request body, takes longer than the limit.
+// Streaming responses (SSE) aren't cut off, the limit only covers producing the response head.
+pub struct RequestTimeout {
+    default: Duration,
+}
+
+impl RequestTimeout {
+    pub fn new(default: Duration) -> Self {
+        RequestTimeout { default }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTimeout
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = RequestTimeoutMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestTimeoutMiddleware {
+            service,
+            default: self.default,
+        }))
+    }
+}
+
+pub struct RequestTimeoutMiddleware<S> {
+    service: S,
+    default: Duration,
+}
+
+impl<S> RequestTimeoutMiddleware<S> {
+    // The header only counts from internal addresses, a client could otherwise raise its own limit
+    fn limit_for(&self, req: &ServiceRequest) -> Duration {
+        match req.peer_addr() {
+            Some(addr) if is_internal(addr.ip()) => {}
+            _ => return self.default,
+        }
+
+        req.headers()
+            .get(TIMEOUT_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .filter(|limit| !limit.is_zero() && *limit <= MAX_TIMEOUT)
+            .unwrap_or(self.default)
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTimeoutMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let limit = self.limit_for(&req);
+        let path = req.path().to_string();
+        let request_id = get_request_id(req.request());
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            // Dropping the handler future rolls back any transaction it had open
+            tokio::time::timeout(limit, fut).await.unwrap_or_else(|_| {
+                tracing::warn!(
+                    request_id = %request_id,
+                    path = %path,
+                    timeout_secs = limit.as_secs(),
+                    "request timed out"
+                );
+
+                let response = ProblemDetails::new(
+                    StatusCode::REQUEST_TIMEOUT,
+                    problem_types::REQUEST_TIMEOUT,
+                    "The request took too long to complete",
+                )
+                .response();
+                Err(InternalError::from_response("request timed out", response).into())
+            })
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use futures_util::future::LocalBoxFuture;
+use opentelemetry::propagation::Injector;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::{global, Context};
+use std::future::{ready, Ready};
+
+// Goes inside actix-web-opentelemetry's RequestTracing: renames its server span to
+// `{method} {route}` and returns the trace context to the caller in a traceparent header.
+pub struct TraceContext;
+
+impl<S, B> Transform<S, ServiceRequest> for TraceContext
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Transform = TraceContextMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TraceContextMiddleware { service }))
+    }
+}
+
+pub struct TraceContextMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for TraceContextMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let name = format!(
+            "{} {}",
+            req.method(),
+            req.match_pattern().unwrap_or_else(|| "default".to_string())
+        );
+
+        let fut = self.service.call(req);
+
+        // RequestTracing attaches its span while polling, so it is only current in here
+        Box::pin(async move {
+            Context::current().span().update_name(name);
+
+            let mut resp = fut.await?;
+            global::get_text_map_propagator(|propagator| {
+                propagator.inject(&mut ResponseHeaderCarrier(resp.headers_mut()));
+            });
+            Ok(resp)
+        })
+    }
+}
+
+struct ResponseHeaderCarrier<'a>(&'a mut actix_web::http::header::HeaderMap);
+
+impl Injector for ResponseHeaderCarrier<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        // tracestate comes through empty when the caller sent none
+        if value.is_empty() {
+            return;
+        }
+        if let (Ok(name), Ok(value)) = (HeaderName::try_from(key), HeaderValue::try_from(value)) {
+            self.0.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,1047 @@
+use chrono::{DateTime, Days, Months, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::types::Json;
+use std::collections::HashMap;
+use std::str::FromStr;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
+
+use crate::recurrence::RecurrenceRule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "priority_enum", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Priority {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Critical => "critical",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "critical" => Ok(Priority::Critical),
+            _ => Err(format!(
+                "Unknown priority '{}', expected one of low, medium, high, critical",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "role_enum", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    User,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow, ToSchema)]
+pub struct Todo {
+    pub id: Option<i32>,
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+    pub description: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub due_date: Option<NaiveDate>,
+    pub priority: Option<Priority>,
+    pub category_id: Option<i32>,
+    #[serde(skip_deserializing)] // Always the authenticated caller, never taken from the body
+    pub user_id: Option<i32>,
+    #[serde(skip_deserializing)]
+    pub deleted_at: Option<DateTime<Utc>>, // Set while the todo is in the trash
+    #[serde(skip_deserializing)]
+    pub archived: Option<bool>, // Hidden from the default listing, changed through /archive and /unarchive
+    #[serde(skip_deserializing)]
+    pub position: Option<f64>, // Manual order, changed through /move
+    #[serde(skip_deserializing)]
+    pub version: Option<i32>, // Goes up with every update, for optimistic locking
+    #[serde(skip_deserializing)]
+    pub recurrence_rule: Option<String>, // Repeat schedule, changed through /recurrence
+    #[serde(skip_deserializing)]
+    pub next_occurrence_at: Option<DateTime<Utc>>, // When the next copy is created, once this one is completed
+    #[sqlx(default)]
+    #[serde(skip_deserializing)]
+    pub tags: Vec<String>, // Names of the attached tags, only filled in when the query selects them
+    #[sqlx(default)]
+    #[serde(skip_deserializing)]
+    pub shared: bool, // Someone else's todo shared with the caller
+    #[sqlx(default)]
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<String>, // Matching excerpt, only for searches
+}
+
+
+// Body accepted by POST /todos
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewTodo {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    #[schema(min_length = 1)]
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    #[schema(max_length = 2000)]
+    pub description: Option<String>,
+    pub due_date: Option<NaiveDate>,
+    pub priority: Option<Priority>,
+    pub tag_ids: Option<Vec<i32>>, // Tags of the caller to attach
+    pub category_id: Option<i32>, // One of the caller's categories
+}
+
+// Query string accepted by GET /todos
+#[derive(Deserialize, IntoParams)]
+pub struct TodoQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub after_id: Option<i32>,   // Cursor: todos with a larger id, in id order, instead of a page
+    pub completed: Option<bool>, // Only todos with this status when set
+    pub overdue: Option<bool>,   // Only incomplete todos past their due date when true
+    pub include_archived: Option<bool>, // Archived todos are left out unless this is true
+    pub priority: Option<String>, // Parsed into Priority
+    pub tag_id: Option<i32>,      // Only todos carrying this tag when set
+    pub q: Option<String>,        // Full-text search over title and description
+    pub sort_by: Option<String>,  // Parsed into SortField
+    pub sort_dir: Option<String>, // Parsed into SortDir
+}
+
+// Columns GET /todos may be ordered by. Only these ever reach the ORDER BY clause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortField {
+    Title,
+    Completed,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl SortField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            SortField::Title => "title",
+            SortField::Completed => "completed",
+            SortField::CreatedAt => "created_at",
+            SortField::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+impl FromStr for SortField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(SortField::Title),
+            "completed" => Ok(SortField::Completed),
+            "created_at" => Ok(SortField::CreatedAt),
+            "updated_at" => Ok(SortField::UpdatedAt),
+            _ => Err(format!("Unknown sort_by field '{}'", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortDir {
+    Asc,
+    Desc,
+}
+
+impl SortDir {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            SortDir::Asc => "ASC",
+            SortDir::Desc => "DESC",
+        }
+    }
+}
+
+impl FromStr for SortDir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "asc" => Ok(SortDir::Asc),
+            "desc" => Ok(SortDir::Desc),
+            _ => Err(format!("Unknown sort_dir '{}', expected 'asc' or 'desc'", s)),
+        }
+    }
+}
+
+// Query string accepted by POST /todos/import
+#[derive(Deserialize, IntoParams)]
+pub struct ImportQuery {
+    pub partial: Option<bool>, // Import the valid rows even when others fail
+}
+
+// Outcome of POST /todos/import, rows are numbered from 1 (a CSV header isn't counted)
+#[derive(Serialize, ToSchema)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub errors: Vec<ImportRowError>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportRowError {
+    pub row: usize,
+    pub reason: String,
+}
+
+// Query string accepted by GET /todos/export, next to the TodoQuery filters
+#[derive(Deserialize, IntoParams)]
+pub struct ExportQuery {
+    pub format: Option<String>, // Parsed into ExportFormat, wins over the Accept header
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("Unknown export format '{}', expected 'csv' or 'json'", s)),
+        }
+    }
+}
+
+// Body accepted by POST /batch. With `atomic` every operation runs in one transaction
+// and the first failure rolls all of them back, otherwise each stands on its own.
+#[derive(Deserialize, ToSchema)]
+pub struct BatchReq {
+    pub operations: Vec<BatchOperation>,
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+// One request inside a batch, e.g. PATCH /todos/3 with the same body the route takes
+#[derive(Deserialize, ToSchema)]
+pub struct BatchOperation {
+    pub method: String,
+    pub path: String,
+    pub body: Option<serde_json::Value>,
+}
+
+// Outcome of POST /batch, one result per operation in the order they were sent
+#[derive(Serialize, ToSchema)]
+pub struct BatchResponse {
+    pub results: Vec<BatchResult>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BatchResult {
+    pub status: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<serde_json::Value>,
+}
+
+// Envelope for list endpoints so callers know how many pages there are
+#[derive(Serialize, ToSchema)]
+pub struct PaginatedResponse<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub page: u32,
+    pub per_page: u32,
+    pub next_cursor: Option<i32>, // after_id for the next cursor page, None on the last one
+}
+
+// Body accepted by PATCH /todos/{id}, fields left out keep their values
+#[derive(Deserialize, Serialize, Validate, ToSchema)]
+pub struct UpdateTaskReq {
+    #[validate(length(min = 1, message = "must not be empty"))]
+    #[schema(min_length = 1)]
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    #[schema(max_length = 2000)]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<NaiveDate>)]
+    pub due_date: Option<Option<NaiveDate>>, // null removes the due date
+    pub priority: Option<Priority>,
+    pub tag_ids: Option<Vec<i32>>, // Replaces the attached tags when set, keeps them otherwise
+    // One of the owner's categories; kept when left out, null takes the todo out of its category
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<i32>)]
+    pub category_id: Option<Option<i32>>,
+    pub version: Option<i32>, // The version last read; when set the update fails with 409 if it changed since
+}
+
+// For PATCH fields where null (clear it) has to differ from leaving the field out (keep it)
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+// How far POST /todos/{id}/snooze pushes a todo's due date
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub enum SnoozeDuration {
+    #[serde(rename = "1d")]
+    OneDay,
+    #[serde(rename = "3d")]
+    ThreeDays,
+    #[serde(rename = "1w")]
+    OneWeek,
+    #[serde(rename = "2w")]
+    TwoWeeks,
+    #[serde(rename = "1m")]
+    OneMonth, // A calendar month, Jan 31 becomes Feb 28 (or 29)
+}
+
+impl SnoozeDuration {
+    // The date this long after `date`, None past the end of the calendar
+    pub fn after(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            SnoozeDuration::OneDay => date.checked_add_days(Days::new(1)),
+            SnoozeDuration::ThreeDays => date.checked_add_days(Days::new(3)),
+            SnoozeDuration::OneWeek => date.checked_add_days(Days::new(7)),
+            SnoozeDuration::TwoWeeks => date.checked_add_days(Days::new(14)),
+            SnoozeDuration::OneMonth => date.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+// Body accepted by POST /todos/{id}/snooze
+#[derive(Deserialize, ToSchema)]
+pub struct SnoozeReq {
+    pub duration: SnoozeDuration,
+}
+
+// Body accepted by PATCH /todos/{id}/recurrence, null stops the todo from repeating
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct RecurrenceReq {
+    #[validate(custom(function = "recurrence_rule"))]
+    #[schema(example = "FREQ=WEEKLY;INTERVAL=2;UNTIL=20251231")]
+    pub rule: Option<String>,
+}
+
+fn recurrence_rule(rule: &str) -> Result<(), ValidationError> {
+    let rule = RecurrenceRule::parse(rule)
+        .map_err(|e| ValidationError::new("rrule").with_message(e.into()))?;
+    if rule.next_after(Utc::now()).is_none() {
+        return Err(ValidationError::new("rrule").with_message("UNTIL leaves no occurrences to come".into()));
+    }
+    Ok(())
+}
+
+// Body accepted by PATCH /todos/bulk
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct BulkUpdateReq {
+    #[validate(length(min = 1, max = 100, message = "must have between 1 and 100 ids"))]
+    #[schema(min_items = 1, max_items = 100)]
+    pub ids: Vec<i32>,
+    pub update: BulkTodoUpdate,
+}
+
+// The fields PATCH /todos/bulk can change, the ones left out keep their values
+#[derive(Deserialize, ToSchema)]
+pub struct BulkTodoUpdate {
+    pub completed: Option<bool>,
+    pub archived: Option<bool>,
+}
+
+// Response of PATCH /todos/bulk
+#[derive(Serialize, ToSchema)]
+pub struct BulkUpdateResponse {
+    pub updated: u64,
+}
+
+// Query string accepted by DELETE /todos/completed
+#[derive(Deserialize, IntoParams)]
+pub struct ClearCompletedQuery {
+    pub confirm: Option<bool>, // Has to be true, guards against clearing by accident
+    pub permanent: Option<bool>, // Delete for good instead of moving to the trash
+}
+
+// Response of DELETE /todos/completed
+#[derive(Serialize, ToSchema)]
+pub struct ClearCompletedResponse {
+    pub deleted: u64,
+}
+
+// Body accepted by POST /todos/{id}/duplicate
+#[derive(Deserialize, ToSchema)]
+pub struct DuplicateTodoReq {
+    pub with_subtasks: Option<bool>, // Copy the subtasks too, true by default
+}
+
+// Body accepted by PATCH /todos/{id}/move, the todo lands between the two (at least one is required)
+#[derive(Deserialize, ToSchema)]
+pub struct MoveTodoReq {
+    pub after_id: Option<i32>,
+    pub before_id: Option<i32>,
+}
+
+// Body accepted by POST /todos/{id}/move-to-user
+#[derive(Deserialize, ToSchema)]
+pub struct MoveToUserReq {
+    pub target_user_id: i32,
+}
+
+// Body accepted by PATCH /user/{user_id}. Unknown fields are refused so a password sent here
+// isn't silently ignored.
+#[derive(Deserialize, Serialize, Validate, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateUserReq {
+    #[validate(length(min = 3, max = 64, message = "must be between 3 and 64 characters"))]
+    #[validate(custom(function = "unreserved_name"))]
+    #[schema(min_length = 3, max_length = 64)]
+    pub name: Option<String>, // Optional field for updating
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateRoleReq {
+    pub role: Role,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct PageQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct TodoResponse {
+    pub id: i32,
+    pub user_id: i32, // The owner
+    pub title: String,
+    pub completed: bool,
+    pub description: String,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub due_date: Option<NaiveDate>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    pub archived: bool,
+    pub position: f64,
+    pub version: i32,
+    pub recurrence_rule: Option<String>,
+    pub next_occurrence_at: Option<DateTime<Utc>>,
+    pub comment_count: i64,
+    pub shared_with: Vec<ShareEntry>,
+    pub subtask_count: i64,
+    pub completed_subtask_count: i64,
+    pub completion_percent: f64, // Share of completed subtasks, 0 without subtasks
+    pub total_tracked_seconds: i64, // Time logged by everyone, running timers up to now
+    pub category: Option<CategoryResponse>,
+    pub has_notes: bool, // Whether the notepad has anything in it, see GET /todos/{id}/notes
+}
+
+// What an activity_log entry records
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "activity_action_enum", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityAction {
+    Created,
+    Updated,
+    Deleted,
+    Completed,
+    Shared,
+    Commented,
+    Snoozed,
+}
+
+// One entry of a user's activity feed, user_id is who did it
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ActivityEntry {
+    pub id: i32,
+    pub user_id: i32,
+    pub action: ActivityAction,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub meta: serde_json::Value,
+    pub occurred_at: DateTime<Utc>,
+}
+
+// Query string accepted by GET /users/{id}/activity
+#[derive(Deserialize, IntoParams)]
+pub struct ActivityQuery {
+    pub limit: Option<u32>,
+    pub before_id: Option<i32>, // Cursor: entries older than this one
+}
+
+// A page of the activity feed, pass next_cursor as before_id for the one after it
+#[derive(Serialize, ToSchema)]
+pub struct ActivityPage {
+    pub items: Vec<ActivityEntry>,
+    pub next_cursor: Option<i32>,
+}
+
+// One change recorded by the audit_log trigger. old_data is None for inserts, new_data for deletes.
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub table_name: String, // "todos" or "Users"
+    pub row_id: Option<i32>,
+    pub action: String, // "insert", "update", "delete", or "bulk_delete" for a summary entry
+    pub actor_user_id: Option<i32>, // None when the change wasn't made on behalf of a user
+    pub actor_ip: Option<String>, // Only recorded for some changes, e.g. erasures
+    pub old_data: Option<serde_json::Value>,
+    pub new_data: Option<serde_json::Value>,
+    pub meta: Option<serde_json::Value>, // What a summary entry covers, e.g. {"count": 3}
+    pub occurred_at: DateTime<Utc>,
+}
+
+// Query string accepted by GET /admin/audit-log
+#[derive(Deserialize, IntoParams)]
+pub struct AuditLogQuery {
+    pub table: Option<String>, // Only changes to this table
+    pub row_id: Option<i32>, // Only changes to this row
+    pub limit: Option<u32>,
+}
+
+// A user a todo is shared with
+#[derive(Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct ShareEntry {
+    pub user_id: i32,
+    pub can_edit: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+// Body accepted by POST /todos/{id}/shares, sharing again with the same user updates can_edit
+#[derive(Deserialize, ToSchema)]
+pub struct ShareReq {
+    pub user_id: i32,
+    #[serde(default)]
+    pub can_edit: bool,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Comment {
+    pub id: i32,
+    pub todo_id: i32,
+    pub user_id: i32,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// Body accepted by POST and PATCH /todos/{id}/comments
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct CommentReq {
+    #[validate(length(min = 1, max = 5000, message = "must be between 1 and 5000 characters"))]
+    #[schema(min_length = 1, max_length = 5000)]
+    pub body: String,
+}
+
+// The notepad of a todo, GET /todos/{id}/notes answers with empty content until it's written
+#[derive(Serialize, ToSchema)]
+pub struct TodoNote {
+    pub content: String,
+    pub updated_at: Option<DateTime<Utc>>, // None while nothing has been saved
+}
+
+// Body accepted by PUT /todos/{id}/notes, replaces the whole note
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct TodoNoteReq {
+    #[validate(length(max = 50000, message = "must be at most 50000 characters"))]
+    #[schema(max_length = 50000)]
+    pub content: String,
+}
+
+// Body accepted by POST /todos/{id}/dependencies
+#[derive(Deserialize, ToSchema)]
+pub struct DependencyReq {
+    pub blocker_id: i32, // The todo that has to be completed first
+}
+
+// One side of a dependency, enough to show what is waiting on what
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct DependencyTodo {
+    pub id: i32,
+    pub title: String,
+    pub completed: bool,
+}
+
+// Response of GET /todos/{id}/dependencies
+#[derive(Serialize, ToSchema)]
+pub struct Dependencies {
+    pub blockers: Vec<DependencyTodo>, // Todos this one waits for
+    pub blocked: Vec<DependencyTodo>,  // Todos waiting for this one
+}
+
+// Response of GET /todos/stats, over the caller's todos that aren't in the trash
+#[derive(Clone, Serialize, ToSchema)]
+pub struct TodoStats {
+    pub total: i64,
+    pub completed: i64,
+    pub overdue: i64,   // Open and due before today
+    pub due_today: i64, // Open and due today
+    pub completion_rate: f64,     // completed / total, 0 without todos
+    pub avg_completion_days: f64, // From creation to the last change of completed todos, 0 without any
+    pub by_priority: PriorityCounts,
+    pub by_tag: Vec<TagCount>, // Most used first
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+pub struct PriorityCounts {
+    pub low: i64,
+    pub medium: i64,
+    pub high: i64,
+    pub critical: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: i64,
+}
+
+// One day of a user's numbers, as written by the daily snapshot job
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct StatsSnapshot {
+    pub snapshot_date: NaiveDate,
+    pub total_todos: i32, // Not in the trash at the end of the day
+    pub completed_todos: i32, // Of those, how many were completed
+    pub created_todos: i32, // Created during the day
+    pub deleted_todos: i32, // Moved to the trash during the day
+}
+
+// Query string accepted by GET /users/{id}/stats/history, both ends inclusive
+#[derive(Deserialize, IntoParams)]
+pub struct StatsHistoryQuery {
+    pub start: Option<NaiveDate>, // Defaults to 30 days before end
+    pub end: Option<NaiveDate>, // Defaults to today
+}
+
+// A todo of the caller coming due, listed by GET /notifications
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Notification {
+    pub todo_id: i32,
+    pub title: String,
+    pub due_date: NaiveDate,
+    pub minutes_until_due: i64, // Until the end of the due date, UTC
+}
+
+// A stretch of time a user spent on a todo, still running while ended_at is null
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct TimeEntry {
+    pub id: i32,
+    pub todo_id: i32,
+    pub user_id: i32,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub duration_seconds: i64, // Up to now for a running entry
+}
+
+// Response of GET /todos/{id}/time
+#[derive(Serialize, ToSchema)]
+pub struct TimeReport {
+    pub entries: Vec<TimeEntry>,
+    pub total_seconds: i64,
+}
+
+// Response of POST /todos/{id}/time/stop
+#[derive(Serialize, ToSchema)]
+pub struct StoppedTimer {
+    pub duration_seconds: i64,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Subtask {
+    pub id: i32,
+    pub todo_id: i32,
+    pub title: String,
+    pub completed: bool,
+    pub position: i32,
+}
+
+// Body accepted by POST /todos/{id}/subtasks
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewSubtask {
+    #[validate(length(min = 1, max = 500, message = "must be between 1 and 500 characters"))]
+    #[schema(min_length = 1, max_length = 500)]
+    pub title: String,
+    pub completed: Option<bool>,
+    pub position: Option<i32>, // Appended after the last subtask when not set
+}
+
+// Body accepted by PATCH /todos/{id}/subtasks/{subtask_id}, unset fields are kept
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct UpdateSubtaskReq {
+    #[validate(length(min = 1, max = 500, message = "must be between 1 and 500 characters"))]
+    #[schema(min_length = 1, max_length = 500)]
+    pub title: Option<String>,
+    pub completed: Option<bool>,
+    pub position: Option<i32>,
+}
+
+// A reusable todo pattern; the texts may contain `{{name}}` placeholders
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct TodoTemplate {
+    pub id: i32,
+    pub name: String,
+    pub title_template: String,
+    pub description_template: String,
+    pub priority: Priority,
+    #[schema(value_type = Vec<String>)]
+    pub subtask_templates: Json<Vec<String>>, // Titles of the subtasks to create
+    pub created_at: DateTime<Utc>,
+}
+
+// Body accepted by POST /templates
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewTodoTemplate {
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters"))]
+    #[schema(min_length = 1, max_length = 100)]
+    pub name: String,
+    #[validate(length(min = 1, max = 500, message = "must be between 1 and 500 characters"))]
+    #[schema(min_length = 1, max_length = 500)]
+    pub title_template: String,
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    #[schema(max_length = 2000)]
+    pub description_template: Option<String>,
+    pub priority: Option<Priority>,
+    #[validate(custom(function = "subtask_titles"))]
+    pub subtask_templates: Option<Vec<String>>,
+}
+
+// Body accepted by PATCH /templates/{template_id}, unset fields are kept
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct UpdateTodoTemplateReq {
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters"))]
+    #[schema(min_length = 1, max_length = 100)]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 500, message = "must be between 1 and 500 characters"))]
+    #[schema(min_length = 1, max_length = 500)]
+    pub title_template: Option<String>,
+    #[validate(length(max = 2000, message = "must be at most 2000 characters"))]
+    #[schema(max_length = 2000)]
+    pub description_template: Option<String>,
+    pub priority: Option<Priority>,
+    #[validate(custom(function = "subtask_titles"))]
+    pub subtask_templates: Option<Vec<String>>,
+}
+
+// Body accepted by POST /templates/{template_id}/instantiate, optional when there are no placeholders
+#[derive(Deserialize, ToSchema)]
+pub struct InstantiateTemplateReq {
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+// Same limits as POST /todos/{id}/subtasks
+fn subtask_titles(titles: &[String]) -> Result<(), ValidationError> {
+    if titles.iter().all(|title| (1..=500).contains(&title.chars().count())) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("length").with_message("every title must be between 1 and 500 characters".into()))
+    }
+}
+
+// A node of a user's category tree
+#[derive(Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct CategoryResponse {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>, // None for a top-level category
+    pub color: Option<String>,
+}
+
+// Body accepted by POST /categories
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewCategory {
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters"))]
+    #[schema(min_length = 1, max_length = 100)]
+    pub name: String,
+    pub parent_id: Option<i32>, // Another of the caller's categories
+    #[validate(length(max = 32, message = "must be at most 32 characters"))]
+    #[schema(max_length = 32)]
+    pub color: Option<String>,
+}
+
+// Body accepted by PATCH /categories/{category_id}; fields left out are kept, null clears
+// parent_id (making it top-level) or color
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct UpdateCategoryReq {
+    #[validate(length(min = 1, max = 100, message = "must be between 1 and 100 characters"))]
+    #[schema(min_length = 1, max_length = 100)]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<i32>)]
+    pub parent_id: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[validate(length(max = 32, message = "must be at most 32 characters"))]
+    #[schema(value_type = Option<String>, max_length = 32)]
+    pub color: Option<Option<String>>,
+}
+
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Tag {
+    pub id: i32,
+    pub name: String,
+    pub color: Option<String>,
+}
+
+// A user's webhook; the secret is write-only and never returned
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Body accepted by POST /webhooks
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewWebhook {
+    #[validate(custom(function = "http_url"))]
+    #[schema(format = "uri")]
+    pub url: String,
+    #[validate(length(min = 1, message = "must name at least one event"), custom(function = "known_webhook_events"))]
+    #[schema(min_items = 1)]
+    pub events: Vec<String>,
+    #[validate(length(min = 1, max = 256, message = "must be between 1 and 256 characters"))]
+    #[schema(min_length = 1, max_length = 256)]
+    pub secret: Option<String>,
+}
+
+// Events a webhook can subscribe to
+pub const WEBHOOK_EVENTS: &[&str] = &["todo.created", "todo.updated", "todo.completed"];
+
+fn known_webhook_events(events: &[String]) -> Result<(), ValidationError> {
+    if events.iter().all(|event| WEBHOOK_EVENTS.contains(&event.as_str())) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("unknown_event")
+            .with_message(format!("must be one of {}", WEBHOOK_EVENTS.join(", ")).into()))
+    }
+}
+
+fn http_url(url: &str) -> Result<(), ValidationError> {
+    match reqwest::Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok(()),
+        _ => Err(ValidationError::new("url").with_message("must be an http or https URL".into())),
+    }
+}
+
+// One of a user's API keys; the key itself is only shown once, when it's created
+#[derive(Serialize, sqlx::FromRow, ToSchema)]
+pub struct ApiKey {
+    pub id: i32,
+    pub key_prefix: String,
+    pub description: Option<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Body accepted by POST /api-keys
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewApiKey {
+    #[validate(length(max = 200, message = "must be at most 200 characters"))]
+    #[schema(max_length = 200)]
+    pub description: Option<String>,
+    #[validate(custom(function = "in_the_future"))]
+    pub expires_at: Option<DateTime<Utc>>, // Never expires when left out
+}
+
+// Returned by POST /api-keys
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+    pub key: String, // Send as `Authorization: ApiKey <key>`, it can't be shown again
+}
+
+fn in_the_future(time: &DateTime<Utc>) -> Result<(), ValidationError> {
+    if *time > Utc::now() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("past").with_message("must be in the future".into()))
+    }
+}
+
+// Body accepted by POST /tags
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewTag {
+    #[validate(length(min = 1, max = 50, message = "must be between 1 and 50 characters"))]
+    #[schema(min_length = 1, max_length = 50)]
+    pub name: String,
+    #[validate(length(max = 32, message = "must be at most 32 characters"))]
+    #[schema(max_length = 32)]
+    pub color: Option<String>,
+}
+
+// Erased accounts are renamed to this followed by their id, so nobody else may take such a name
+pub const ERASED_USER_NAME_PREFIX: &str = "deleted_user_";
+
+fn unreserved_name(name: &str) -> Result<(), ValidationError> {
+    if name.to_ascii_lowercase().starts_with(ERASED_USER_NAME_PREFIX) {
+        return Err(ValidationError::new("reserved").with_message("is reserved for erased accounts".into()));
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct NewUser {
+    #[validate(length(min = 3, max = 64, message = "must be between 3 and 64 characters"))]
+    #[validate(custom(function = "unreserved_name"))]
+    #[schema(min_length = 3, max_length = 64)]
+    pub name: String,
+    #[validate(length(min = 8, message = "must be at least 8 characters"))]
+    #[schema(min_length = 8)]
+    pub password: String,
+    #[schema(format = "email")]
+    pub email: Option<String>, // Unverified until confirmed at /users/{user_id}/email/verify/confirm
+}
+
+// Body accepted by POST /users/{user_id}/email/verify/confirm
+#[derive(Deserialize, ToSchema)]
+pub struct EmailVerifyConfirmReq {
+    pub token: String, // As issued by /users/{user_id}/email/verify/send
+}
+
+// Body accepted by DELETE /users/{user_id}/data
+#[derive(Deserialize, ToSchema)]
+pub struct EraseDataReq {
+    pub password: String, // The current password, erasing can't be undone
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EraseDataResponse {
+    #[schema(example = "All data erased")]
+    pub message: String,
+}
+
+// Body accepted by POST /users/{user_id}/change-password
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct ChangePasswordReq {
+    pub current_password: String,
+    #[validate(length(min = 8, message = "must be at least 8 characters"))]
+    #[schema(min_length = 8)]
+    pub new_password: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct LoginReq {
+    pub name: String,
+    pub password: String,
+    // Required once the user has two-factor authentication on, a backup code works too
+    #[serde(default)]
+    pub totp_code: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct LoginResponse {
+    pub token: String,
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshReq {
+    pub refresh_token: String,
+}
+
+// Body accepted by POST /password-reset/request
+#[derive(Deserialize, ToSchema)]
+pub struct PasswordResetReq {
+    pub name: String,
+}
+
+// Body accepted by POST /password-reset/confirm
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct PasswordResetConfirmReq {
+    pub token: String,
+    #[validate(length(min = 8, message = "must be at least 8 characters"))]
+    #[schema(min_length = 8)]
+    pub new_password: String,
+}
+
+// Returned by POST /users/{user_id}/2fa/setup
+#[derive(Serialize, ToSchema)]
+pub struct TotpSetupResponse {
+    pub otpauth_uri: String,
+    pub qr_code_png: String, // Base64, for an <img src="data:image/png;base64,...">
+    pub backup_codes: Vec<String>, // Only shown here, each one works once in place of a code
+}
+
+// Body accepted by POST /users/{user_id}/2fa/verify
+#[derive(Deserialize, ToSchema)]
+pub struct TotpCodeReq {
+    pub code: String,
+}
+
+// Body accepted by POST /users/{user_id}/2fa/disable
+#[derive(Deserialize, ToSchema)]
+pub struct TotpDisableReq {
+    pub password: String,
+    pub code: String,
+}
+
+// The settings GET/PATCH /users/{user_id}/preferences know about, all optional.
+// Documentation only: the handlers work on the JSON object directly.
+#[derive(Serialize, ToSchema)]
+pub struct UserPreferences {
+    #[schema(example = "created_at")]
+    pub default_sort_by: Option<String>, // One of the GET /todos sort_by fields
+    #[schema(minimum = 1, maximum = 100)]
+    pub default_per_page: Option<u8>,
+    pub show_completed: Option<bool>,
+    #[schema(example = "dark")]
+    pub theme: Option<String>, // "light" or "dark"
+}
+
+// Query string accepted by GET /users/search
+#[derive(Deserialize, IntoParams)]
+pub struct UserSearchQuery {
+    pub q: String, // Part of the name, at least 2 characters
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UserResponse {
+    pub id: i32,
+    pub name: String,
+    pub role: Role,
+    pub avatar_url: Option<String>,
+}
+
+// Returned by GET /users/{user_id}: the public profile, with the email only for the user
+// themselves and admins
+#[derive(Serialize, ToSchema)]
+pub struct UserProfileResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    #[serde(flatten)]
+    pub contact: Option<UserContact>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UserContact {
+    pub email: Option<String>,
+    pub email_verified: bool,
+}
+
+// Body accepted by PATCH /users/{user_id}/avatar
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateAvatarReq {
+    #[schema(example = "https://gravatar.com/avatar/205e460b479e2e5b48aec07710c08d50")]
+    pub avatar_url: String, // HTTPS, at most 2048 characters, on one of AVATAR_ALLOWED_DOMAINS
+}
+
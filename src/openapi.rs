@@ -0,0 +1,204 @@
+use actix_web::HttpResponse;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::error::{BlockedResponse, ProblemDetails, UnprocessableTodoResponse, VersionConflictResponse};
+use crate::handlers::{self, activity, api_keys, audit, batch, categories, comments, data_export, dependencies, email_verification, events, health, metrics, notes, notifications, password_reset, preferences, shares, stats, subtasks, tags, templates, time_entries, todos, two_factor, users, webhooks};
+use crate::models::{
+    ActivityAction, ActivityEntry, ActivityPage, BatchOperation, BatchReq, BatchResponse, BatchResult, ChangePasswordReq, Comment, CommentReq,
+    ImportReport, ImportRowError, LoginReq, LoginResponse, MoveTodoReq, MoveToUserReq, SnoozeReq, SnoozeDuration, RecurrenceReq, DuplicateTodoReq, BulkUpdateReq, BulkTodoUpdate, BulkUpdateResponse, ClearCompletedResponse, NewSubtask, NewTag, NewTodo,
+    NewUser, Priority, RefreshReq, Role, ShareEntry, ShareReq, Subtask, Tag, Todo, TodoResponse,
+    UpdateAvatarReq, UpdateRoleReq, UpdateSubtaskReq, UpdateTaskReq, UpdateUserReq, UserContact, UserProfileResponse, UserResponse, Webhook, NewWebhook,
+    TimeEntry, TimeReport, StoppedTimer, Notification, Dependencies, DependencyReq, DependencyTodo,
+    TodoStats, PriorityCounts, TagCount, PasswordResetReq, PasswordResetConfirmReq, StatsSnapshot,
+    TotpSetupResponse, TotpCodeReq, TotpDisableReq, ApiKey, NewApiKey, CreatedApiKey,
+    TodoTemplate, NewTodoTemplate, UpdateTodoTemplateReq, InstantiateTemplateReq,
+    UserPreferences,
+    CategoryResponse, NewCategory, UpdateCategoryReq,
+    TodoNote, TodoNoteReq,
+    EmailVerifyConfirmReq,
+    AuditEntry, EraseDataReq, EraseDataResponse,
+};
+use crate::validation::ValidationErrorResponse;
+
+// OpenAPI 3 description of every route, served at /openapi.json
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Todo API", description = "Todos with tags, subtasks, comments and sharing"),
+    paths(
+        handlers::home_page,
+        health::health_check,
+        health::readiness_check,
+        metrics::metrics,
+        todos::get_todos,
+        todos::create_todo,
+        todos::get_trash,
+        events::todo_events,
+        stats::get_stats,
+        todos::export_todos,
+        todos::export_todos_ics,
+        todos::import_todos,
+        todos::get_todo_by_id,
+        todos::update_todo,
+        todos::delete_todo,
+        todos::restore_todo,
+        todos::bulk_update_todos,
+        todos::complete_all_todos,
+        todos::incomplete_all_todos,
+        todos::clear_completed_todos,
+        todos::complete_todo,
+        todos::incomplete_todo,
+        todos::snooze_todo,
+        todos::duplicate_todo,
+        todos::move_todo,
+        todos::set_todo_recurrence,
+        todos::archive_todo,
+        todos::unarchive_todo,
+        todos::purge_todo,
+        todos::move_todo_to_user,
+        todos::get_user_todos,
+        activity::get_activity,
+        stats::get_stats_history,
+        stats::reset_stats,
+        batch::batch,
+        time_entries::start_timer,
+        time_entries::stop_timer,
+        time_entries::list_time_entries,
+        dependencies::list_dependencies,
+        dependencies::add_dependency,
+        dependencies::remove_dependency,
+        subtasks::list_subtasks,
+        subtasks::create_subtask,
+        subtasks::update_subtask,
+        subtasks::delete_subtask,
+        notes::get_notes,
+        notes::put_notes,
+        comments::list_comments,
+        comments::create_comment,
+        comments::update_comment,
+        comments::delete_comment,
+        shares::share_todo,
+        shares::unshare_todo,
+        tags::list_tags,
+        tags::create_tag,
+        categories::list_categories,
+        categories::create_category,
+        categories::get_category,
+        categories::update_category,
+        categories::delete_category,
+        categories::list_category_todos,
+        templates::list_templates,
+        templates::create_template,
+        templates::get_template,
+        templates::update_template,
+        templates::delete_template,
+        templates::instantiate_template,
+        notifications::list_notifications,
+        notifications::dismiss_notification,
+        webhooks::list_webhooks,
+        webhooks::create_webhook,
+        webhooks::delete_webhook,
+        users::create_user,
+        users::login,
+        users::refresh,
+        users::logout,
+        api_keys::list_api_keys,
+        api_keys::create_api_key,
+        api_keys::delete_api_key,
+        password_reset::request_password_reset,
+        password_reset::confirm_password_reset,
+        users::list_users,
+        users::search_users,
+        users::get_user,
+        users::update_user,
+        users::update_user_role,
+        users::update_avatar,
+        users::delete_avatar,
+        preferences::get_preferences,
+        preferences::update_preferences,
+        users::change_password,
+        two_factor::setup_two_factor,
+        two_factor::verify_two_factor,
+        two_factor::disable_two_factor,
+        email_verification::send_email_verification,
+        email_verification::confirm_email_verification,
+        users::delete_user,
+        users::erase_user_data,
+        data_export::export_user_data,
+        audit::get_audit_log,
+    ),
+    components(schemas(
+        EraseDataReq, EraseDataResponse,
+        AuditEntry,
+        EmailVerifyConfirmReq,
+        TodoNote, TodoNoteReq,
+        CategoryResponse, NewCategory, UpdateCategoryReq,
+        UserPreferences,
+        TodoTemplate, NewTodoTemplate, UpdateTodoTemplateReq, InstantiateTemplateReq,
+        ApiKey, NewApiKey, CreatedApiKey,
+        TotpSetupResponse, TotpCodeReq, TotpDisableReq,
+        StatsSnapshot,
+        PasswordResetReq, PasswordResetConfirmReq,
+        TodoStats, PriorityCounts, TagCount,
+        VersionConflictResponse,
+        Dependencies, DependencyReq, DependencyTodo, BlockedResponse, UnprocessableTodoResponse,
+        Notification,
+        TimeEntry, TimeReport, StoppedTimer,
+        Webhook, NewWebhook,
+        ActivityAction, ActivityEntry, ActivityPage,
+        BatchReq, BatchOperation, BatchResponse, BatchResult,
+        Todo, TodoResponse, NewTodo, UpdateTaskReq, MoveTodoReq, MoveToUserReq, SnoozeReq, SnoozeDuration, RecurrenceReq, DuplicateTodoReq, BulkUpdateReq, BulkTodoUpdate, BulkUpdateResponse, ClearCompletedResponse, Priority,
+        ImportReport, ImportRowError, Subtask, NewSubtask, UpdateSubtaskReq, Comment, CommentReq, ShareEntry,
+        ShareReq, Tag, NewTag, UserResponse, UserProfileResponse, UserContact, NewUser, UpdateUserReq, UpdateRoleReq, UpdateAvatarReq, ChangePasswordReq,
+        LoginReq, LoginResponse, RefreshReq, Role, ProblemDetails, ValidationErrorResponse,
+    )),
+    modifiers(&SecurityAddon)
+)]
+pub struct ApiDoc;
+
+// Registers the JWT bearer scheme the protected paths refer to as "BearerAuth"
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "BearerAuth",
+            SecurityScheme::Http(
+                Http::builder()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+// Serve the generated spec
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+// Swagger UI page, the assets come from a CDN and the spec from /openapi.json
+pub async fn swagger_ui() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(SWAGGER_UI_HTML)
+}
+
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Todo API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Days, Months, NaiveDate, NaiveDateTime, Utc};
+use sqlx::PgPool;
+
+use crate::error::AppError;
+use crate::handlers::todos::{fetch_todo_response, insert_todo};
+use crate::metrics::TODOS_CREATED_TOTAL;
+use crate::models::{NewTodo, Priority};
+use crate::todo_cache::TodoCache;
+use crate::webhooks::{self, WebhookEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// A simplified iCal RRULE such as `FREQ=WEEKLY;INTERVAL=2;UNTIL=20251231`.
+// Only FREQ (DAILY, WEEKLY or MONTHLY), INTERVAL (default 1) and UNTIL are understood.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub until: Option<DateTime<Utc>>,
+}
+
+// UNTIL is a date (`20251231`, through the end of that day) or a UTC time (`20251231T090000Z`)
+fn parse_until(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Some(time.and_utc());
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+    Some(date.and_hms_opt(23, 59, 59)?.and_utc())
+}
+
+impl RecurrenceRule {
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let mut frequency = None;
+        let mut interval = None;
+        let mut until = None;
+
+        for part in rule.trim().trim_start_matches("RRULE:").split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Expected NAME=VALUE, got '{}'", part))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" if frequency.is_none() => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return Err(format!("FREQ must be DAILY, WEEKLY or MONTHLY, got '{}'", value)),
+                    })
+                }
+                "INTERVAL" if interval.is_none() => {
+                    interval = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|interval| (1..=1000).contains(interval))
+                            .ok_or_else(|| format!("INTERVAL must be a number from 1 to 1000, got '{}'", value))?,
+                    )
+                }
+                "UNTIL" if until.is_none() => {
+                    until = Some(
+                        parse_until(value)
+                            .ok_or_else(|| format!("UNTIL must be YYYYMMDD or YYYYMMDDTHHMMSSZ, got '{}'", value))?,
+                    )
+                }
+                "FREQ" | "INTERVAL" | "UNTIL" => return Err(format!("{} is given twice", name)),
+                _ => return Err(format!("Unsupported part '{}'", name)),
+            }
+        }
+
+        Ok(RecurrenceRule {
+            frequency: frequency.ok_or("FREQ is required")?,
+            interval: interval.unwrap_or(1),
+            until,
+        })
+    }
+
+    // The occurrence one interval after `from`, None once it would fall after UNTIL
+    pub fn next_after(&self, from: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let next = match self.frequency {
+            Frequency::Daily => from.checked_add_days(Days::new(self.interval.into())),
+            Frequency::Weekly => from.checked_add_days(Days::new(u64::from(self.interval) * 7)),
+            Frequency::Monthly => from.checked_add_months(Months::new(self.interval)),
+        }?;
+        match self.until {
+            Some(until) if next > until => None,
+            _ => Some(next),
+        }
+    }
+}
+
+// Create the next copy of every completed recurring todo whose occurrence is due. The rule moves
+// to the new copy, so a series waits for its latest todo to be completed before repeating again
+// and the completed ones stay behind as history. Returns how many todos were created.
+pub async fn create_due_occurrences(pool: &PgPool, cache: Option<&TodoCache>) -> Result<usize, AppError> {
+    let mut tx = pool.begin().await?;
+
+    // SKIP LOCKED so two instances of the server don't copy the same todo
+    let due = sqlx::query!(
+        r#"SELECT id, user_id AS "user_id!", title, description, priority AS "priority: Priority", due_date,
+                  category_id, recurrence_rule AS "recurrence_rule!", next_occurrence_at AS "next_occurrence_at!"
+           FROM todos
+           WHERE recurrence_rule IS NOT NULL AND next_occurrence_at <= NOW()
+             AND completed = true AND deleted_at IS NULL
+           ORDER BY id
+           FOR UPDATE SKIP LOCKED"#
+    )
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let now = Utc::now();
+    let mut events = Vec::with_capacity(due.len());
+
+    for todo in &due {
+        // The rule stops repeating here if it can't be read anymore
+        let next = match RecurrenceRule::parse(&todo.recurrence_rule) {
+            Ok(rule) => {
+                let mut next = rule.next_after(todo.next_occurrence_at);
+                // Occurrences missed while the todo was open or the server was down aren't made up for
+                while let Some(time) = next.filter(|time| *time <= now) {
+                    next = rule.next_after(time);
+                }
+                next
+            }
+            Err(e) => {
+                tracing::warn!(todo_id = todo.id, error = %e, "dropping unreadable recurrence rule");
+                None
+            }
+        };
+
+        let tag_ids = sqlx::query_scalar!("SELECT tag_id FROM todo_tags WHERE todo_id = $1", todo.id)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let new_todo = NewTodo {
+            title: Some(todo.title.clone()),
+            description: todo.description.clone(),
+            completed: Some(false),
+            priority: Some(todo.priority),
+            due_date: todo.due_date.map(|_| todo.next_occurrence_at.date_naive()),
+            tag_ids: Some(tag_ids),
+            category_id: todo.category_id,
+        };
+        let copy_id = insert_todo(&mut tx, todo.user_id, &new_todo).await?;
+
+        sqlx::query!(
+            "UPDATE todos SET recurrence_rule = $2, next_occurrence_at = $3 WHERE id = $1",
+            copy_id,
+            next.map(|_| todo.recurrence_rule.clone()),
+            next
+        )
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query!(
+            "UPDATE todos SET recurrence_rule = NULL, next_occurrence_at = NULL WHERE id = $1",
+            todo.id
+        )
+            .execute(&mut *tx)
+            .await?;
+
+        if let Some(response) = fetch_todo_response(&mut tx, copy_id).await? {
+            let data = serde_json::to_value(&response).map_err(|e| AppError::InternalError(e.to_string()))?;
+            events.push(WebhookEvent { user_id: todo.user_id, event: "todo.created", data });
+        }
+    }
+
+    tx.commit().await?;
+
+    // The originals just lost their rule
+    if let Some(cache) = cache {
+        for todo in &due {
+            cache.invalidate(todo.id).await;
+        }
+    }
+
+    TODOS_CREATED_TOTAL.inc_by(due.len() as u64);
+    for event in events {
+        webhooks::dispatch(pool, event.user_id, event.event, &event.data);
+    }
+
+    Ok(due.len())
+}
@@ -0,0 +1,45 @@
+use chrono::{NaiveDate, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::error::AppError;
+
+// Record every user's numbers for the given (UTC) day. Running it again for the same day
+// overwrites that day's rows. Returns how many users got a snapshot.
+pub async fn take_snapshots(pool: &PgPool, date: NaiveDate) -> Result<u64, AppError> {
+    // created_at is a plain TIMESTAMP in UTC, deleted_at and stats_reset_at are TIMESTAMPTZ
+    let result = sqlx::query!(
+        r#"INSERT INTO stats_snapshots (user_id, snapshot_date, total_todos, completed_todos, created_todos, deleted_todos)
+           SELECT u.id, $1,
+                  COUNT(t.id) FILTER (WHERE t.deleted_at IS NULL),
+                  COUNT(t.id) FILTER (WHERE t.deleted_at IS NULL AND t.completed),
+                  COUNT(t.id) FILTER (WHERE t.created_at >= $1::date AND t.created_at < $1::date + 1
+                                        AND (u.stats_reset_at IS NULL OR t.created_at AT TIME ZONE 'UTC' >= u.stats_reset_at)),
+                  COUNT(t.id) FILTER (WHERE t.deleted_at >= $1::date AT TIME ZONE 'UTC'
+                                        AND t.deleted_at < ($1::date + 1) AT TIME ZONE 'UTC'
+                                        AND (u.stats_reset_at IS NULL OR t.deleted_at >= u.stats_reset_at))
+           FROM "Users" u
+           LEFT JOIN todos t ON t.user_id = u.id
+           GROUP BY u.id
+           ON CONFLICT (user_id, snapshot_date) DO UPDATE
+           SET total_todos = EXCLUDED.total_todos,
+               completed_todos = EXCLUDED.completed_todos,
+               created_todos = EXCLUDED.created_todos,
+               deleted_todos = EXCLUDED.deleted_todos"#,
+        date
+    )
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Time left until the next UTC midnight, when the daily snapshot is due
+pub fn until_next_midnight() -> Duration {
+    let now = Utc::now();
+    let midnight = (now.date_naive() + chrono::Days::new(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is a valid time")
+        .and_utc();
+    (midnight - now).to_std().unwrap_or_default()
+}
@@ -0,0 +1,103 @@
+use opentelemetry::global;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Span;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::AppConfig;
+
+const SERVICE_NAME: &str = "todo_backend";
+const DB_QUERY_SPAN: &str = "db.query";
+
+// Set up logging, and span export to the OTLP collector when OTEL_EXPORTER_OTLP_ENDPOINT is set.
+// The returned provider must be shut down on exit so buffered spans get flushed.
+pub fn init(config: &AppConfig) -> Option<SdkTracerProvider> {
+    // W3C traceparent/tracestate, read from requests and written to responses
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let provider = config.otel_exporter_otlp_endpoint.as_ref().map(|endpoint| {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()
+            .unwrap_or_else(|e| panic!("Failed to build the OTLP exporter: {}", e));
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+            .build();
+        global::set_tracer_provider(provider.clone());
+        provider
+    });
+
+    // Request spans come from the actix-web-opentelemetry middleware, so only the database
+    // spans are bridged from tracing. They pick the request span up as their parent.
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer(SERVICE_NAME))
+            .with_filter(filter_fn(|metadata| metadata.is_span() && metadata.name() == DB_QUERY_SPAN))
+    });
+
+    // RUST_LOG takes precedence over LOG_LEVEL when set
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level)))
+        .with(tracing_subscriber::fmt::layer())
+        .with(otel_layer)
+        .init();
+
+    provider
+}
+
+// Flush and stop the exporter, blocking until the last batch is sent
+pub fn shutdown(provider: SdkTracerProvider) {
+    if let Err(e) = provider.shutdown() {
+        eprintln!("Failed to flush traces: {}", e);
+    }
+}
+
+// Child span for one SQL statement. Record `db.rows_affected` on it once the query has run.
+pub fn db_query_span(statement: &str) -> Span {
+    tracing::info_span!(
+        "db.query",
+        db.statement = %redact_statement(statement),
+        db.rows_affected = tracing::field::Empty,
+    )
+}
+
+// The statement with string and number literals replaced by `?` and whitespace collapsed.
+// Bound values never show up in the SQL text, this catches any value inlined into it.
+fn redact_statement(statement: &str) -> String {
+    let mut redacted = String::with_capacity(statement.len());
+    let mut chars = statement.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // '' inside a literal is an escaped quote
+                while let Some(c) = chars.next() {
+                    if c == '\'' && chars.next_if_eq(&'\'').is_none() {
+                        break;
+                    }
+                }
+                redacted.push('?');
+            }
+            c if c.is_ascii_digit() && !redacted.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '$') => {
+                while chars.next_if(|c| c.is_ascii_digit() || *c == '.').is_some() {}
+                redacted.push('?');
+            }
+            c if c.is_whitespace() => {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                redacted.push(' ');
+            }
+            c => redacted.push(c),
+        }
+    }
+
+    redacted.trim().to_string()
+}
@@ -0,0 +1,52 @@
+use std::collections::{BTreeSet, HashMap};
+
+// Splits `text` around its `{{ name }}` placeholders: literal text, then Some(name) for each
+// placeholder. Names are letters, digits and underscores; anything else stays literal text.
+fn segments(text: &str) -> Vec<(&str, Option<&str>)> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    let mut literal_start = 0;
+
+    while let Some(open) = rest[literal_start..].find("{{") {
+        let open = literal_start + open;
+        let Some(close) = rest[open + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[open + 2..open + 2 + close].trim();
+
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            segments.push((&rest[..open], Some(name)));
+            rest = &rest[open + 2 + close + 2..];
+            literal_start = 0;
+        } else {
+            literal_start = open + 2;
+        }
+    }
+
+    segments.push((rest, None));
+    segments
+}
+
+// Every placeholder name used in the texts, sorted and without duplicates
+pub fn placeholders<'a>(texts: impl IntoIterator<Item = &'a str>) -> BTreeSet<&'a str> {
+    texts
+        .into_iter()
+        .flat_map(segments)
+        .filter_map(|(_, name)| name)
+        .collect()
+}
+
+// Replace every placeholder with its variable. Callers check with `placeholders` first that
+// all of them are supplied; one that isn't is left as it was.
+pub fn render(text: &str, variables: &HashMap<String, String>) -> String {
+    segments(text)
+        .into_iter()
+        .map(|(literal, name)| match name {
+            Some(name) => match variables.get(name) {
+                Some(value) => format!("{}{}", literal, value),
+                None => format!("{}{{{{{}}}}}", literal, name),
+            },
+            None => literal.to_string(),
+        })
+        .collect()
+}
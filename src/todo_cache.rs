@@ -0,0 +1,85 @@
+use actix_web::{web, HttpRequest};
+use moka::future::Cache;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::error::AppError;
+use crate::models::TodoResponse;
+
+// Single todos as GET /todos/{id} returns them, shared by everyone who can see the todo.
+// Access is still checked on every request, only the expensive response query is skipped.
+pub struct TodoCache {
+    entries: Cache<i32, TodoResponse>,
+    loads: AtomicU64,
+}
+
+impl TodoCache {
+    pub fn new() -> Self {
+        TodoCache {
+            entries: Cache::builder()
+                .max_capacity(10_000)
+                .time_to_live(Duration::from_secs(30))
+                .build(),
+            loads: AtomicU64::new(0),
+        }
+    }
+
+    // The cached copy of the todo, or the one `load` reads from the database, which is then kept
+    pub async fn get_or_load<F, Fut>(&self, todo_id: i32, load: F) -> Result<Option<TodoResponse>, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<TodoResponse>, AppError>>,
+    {
+        if let Some(todo) = self.entries.get(&todo_id).await {
+            return Ok(Some(todo));
+        }
+
+        self.loads.fetch_add(1, Ordering::Relaxed);
+        let todo = load().await?;
+        if let Some(todo) = &todo {
+            self.entries.insert(todo_id, todo.clone()).await;
+        }
+        Ok(todo)
+    }
+
+    pub async fn invalidate(&self, todo_id: i32) {
+        self.entries.invalidate(&todo_id).await;
+    }
+
+    // For changes that reach into many todos at once, e.g. renaming a category
+    pub fn invalidate_all(&self) {
+        self.entries.invalidate_all();
+    }
+
+    // How many times get_or_load had to query the database, for the tests
+    pub fn loads(&self) -> u64 {
+        self.loads.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for TodoCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The app's cache, None in apps built without one (most tests)
+pub fn todo_cache(req: &HttpRequest) -> Option<&web::Data<TodoCache>> {
+    req.app_data::<web::Data<TodoCache>>()
+}
+
+// Drop a todo's cached copy. Every handler that writes something GET /todos/{id} returns (the
+// todo's columns, its tags, shares, notes, comments, subtasks, time entries or category) calls
+// this after committing, otherwise GET and its ETag stay stale for up to the TTL.
+pub async fn invalidate_todo(req: &HttpRequest, todo_id: i32) {
+    if let Some(cache) = todo_cache(req) {
+        cache.invalidate(todo_id).await;
+    }
+}
+
+pub fn invalidate_all_todos(req: &HttpRequest) {
+    if let Some(cache) = todo_cache(req) {
+        cache.invalidate_all();
+    }
+}
@@ -0,0 +1,23 @@
+use sqlx::PgPool;
+
+// Partial unique index behind PREVENT_DUPLICATE_TITLES, trashed todos don't count
+pub const UNIQUE_TITLE_INDEX: &str = "unique_todo_title_per_user";
+
+// Create or drop the index to match the setting. Creating it fails while a user already has two
+// live todos whose titles only differ in case.
+pub async fn enforce_unique_titles(pool: &PgPool, enabled: bool) -> Result<(), sqlx::Error> {
+    let statement = if enabled {
+        "CREATE UNIQUE INDEX IF NOT EXISTS unique_todo_title_per_user
+         ON todos (user_id, LOWER(title)) WHERE deleted_at IS NULL"
+    } else {
+        "DROP INDEX IF EXISTS unique_todo_title_per_user"
+    };
+
+    let mut tx = pool.begin().await?;
+    // Replicas starting at once would otherwise race to create the same index
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext('unique_todo_titles'))")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(statement).execute(&mut *tx).await?;
+    tx.commit().await
+}
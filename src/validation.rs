@@ -0,0 +1,48 @@
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use validator::{Validate, ValidationErrors};
+
+use crate::error::{problem_response, problem_types, AppError, ProblemDetails};
+
+// 422 body listing every invalid field with its messages
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValidationErrorResponse {
+    #[serde(flatten)]
+    pub problem: ProblemDetails,
+    pub fields: HashMap<String, Vec<String>>,
+}
+
+// Run the struct's #[validate] rules, failures become AppError::ValidationError (422)
+pub fn validate_input<T: Validate>(data: &T) -> Result<(), AppError> {
+    data.validate().map_err(AppError::from)
+}
+
+// The 422 response for a set of failed #[validate] rules
+pub fn validation_error_response(errors: &ValidationErrors) -> HttpResponse {
+    let fields = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| match &error.message {
+                    Some(message) => message.to_string(),
+                    None => format!("failed the '{}' check", error.code),
+                })
+                .collect();
+            (field.to_string(), messages)
+        })
+        .collect();
+
+    let status = StatusCode::UNPROCESSABLE_ENTITY;
+    problem_response(
+        status,
+        &ValidationErrorResponse {
+            problem: ProblemDetails::new(status, problem_types::VALIDATION_FAILED, "Some fields are invalid"),
+            fields,
+        },
+    )
+}
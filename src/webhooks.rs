@@ -0,0 +1,240 @@
+use actix_web::http::header::CONTENT_TYPE;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+pub const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+
+// A delivery is tried this many times, waiting 1s, 2s, ... between attempts
+const MAX_ATTEMPTS: i32 = 3;
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// Receivers are user supplied, so by default only public addresses are contacted; otherwise a
+// webhook could reach the database, the cloud metadata endpoint or anything else on the network
+static ALLOW_PRIVATE_TARGETS: AtomicBool = AtomicBool::new(false);
+
+// Let deliveries go to loopback and private addresses, for tests and local development
+pub fn allow_private_targets(allow: bool) {
+    ALLOW_PRIVATE_TARGETS.store(allow, Ordering::Relaxed);
+}
+
+// Redirects aren't followed, they could point anywhere; a 3xx counts as a failed attempt
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("Failed to build the webhook HTTP client")
+});
+
+// False for loopback, private, link-local, shared, documentation, multicast and other reserved ranges
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && b & 0xc0 == 64) // 100.64.0.0/10, carrier-grade NAT
+                || (a == 198 && b & 0xfe == 18)) // 198.18.0.0/15, benchmarking
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || first & 0xfe00 == 0xfc00 // fc00::/7, unique local
+                    || first & 0xffc0 == 0xfe80) // fe80::/10, link-local
+            }
+        },
+    }
+}
+
+fn check_target(ip: IpAddr) -> Result<(), String> {
+    if ALLOW_PRIVATE_TARGETS.load(Ordering::Relaxed) || is_public_address(ip) {
+        Ok(())
+    } else {
+        Err(format!("refusing to deliver to non-public address {}", ip))
+    }
+}
+
+// Resolves host names for CLIENT and refuses non-public answers. Checking here, on the addresses
+// actually connected to, rather than before the request means a name can't resolve to a public
+// address for the check and a private one for the connection.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+            for addr in &addrs {
+                check_target(addr.ip())?;
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+// The resolver isn't consulted for URLs with an IP address as the host, so those are checked here
+fn check_url(url: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    match url.host() {
+        Some(url::Host::Ipv4(ip)) => check_target(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => check_target(IpAddr::V6(ip)),
+        _ => Ok(()),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// `sha256=<hex HMAC-SHA256 of the body>`, the format GitHub uses, so receivers can check the sender
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body);
+    format!("sha256={}", to_hex(&mac.finalize().into_bytes()))
+}
+
+// An event held back until the transaction that caused it commits
+pub struct WebhookEvent {
+    pub user_id: i32,
+    pub event: &'static str,
+    pub data: serde_json::Value,
+}
+
+// Send an event to every webhook of the user subscribed to it. Delivery happens in the background,
+// so call this once the change is committed; the request doesn't wait for the receivers.
+pub fn dispatch<T: Serialize>(pool: &PgPool, user_id: i32, event: &'static str, data: &T) {
+    let payload = json!({ "event": event, "occurred_at": Utc::now(), "data": data });
+    let pool = pool.clone();
+
+    tokio::spawn(async move {
+        if let Err(e) = fan_out(&pool, user_id, event, payload).await {
+            tracing::warn!(error = %e, event, "failed to queue webhook deliveries");
+        }
+    });
+}
+
+// Record a delivery per subscribed webhook and start sending them
+async fn fan_out(pool: &PgPool, user_id: i32, event: &'static str, payload: serde_json::Value) -> Result<(), sqlx::Error> {
+    let hooks = sqlx::query!(
+        "SELECT id, url, secret FROM webhooks WHERE user_id = $1 AND $2 = ANY(events)",
+        user_id,
+        event
+    )
+        .fetch_all(pool)
+        .await?;
+
+    let body = payload.to_string().into_bytes();
+
+    for hook in hooks {
+        let delivery_id = sqlx::query_scalar!(
+            "INSERT INTO webhook_deliveries (webhook_id, event, payload) VALUES ($1, $2, $3) RETURNING id",
+            hook.id,
+            event,
+            payload
+        )
+            .fetch_one(pool)
+            .await?;
+
+        let pool = pool.clone();
+        let body = body.clone();
+        tokio::spawn(async move {
+            if let Err(e) = deliver(&pool, delivery_id, &hook.url, hook.secret.as_deref(), event, body).await {
+                tracing::warn!(error = %e, delivery_id, "failed to record webhook delivery");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+// POST the body to the URL until it answers 2xx or the attempts run out, recording each try
+async fn deliver(
+    pool: &PgPool,
+    delivery_id: i32,
+    url: &str,
+    secret: Option<&str>,
+    event: &str,
+    body: Vec<u8>,
+) -> Result<(), sqlx::Error> {
+    let mut delay = FIRST_RETRY_DELAY;
+
+    // Retrying won't make the address public
+    if let Err(error) = check_url(url) {
+        sqlx::query!(
+            "UPDATE webhook_deliveries SET status = 'failed', attempts = 1, last_error = $2 WHERE id = $1",
+            delivery_id,
+            error
+        )
+            .execute(pool)
+            .await?;
+        return Ok(());
+    }
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut request = CLIENT
+            .post(url)
+            .header(CONTENT_TYPE.as_str(), "application/json")
+            .header(EVENT_HEADER, event)
+            .body(body.clone());
+        if let Some(secret) = secret {
+            request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+        }
+
+        let (response_status, error) = match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                sqlx::query!(
+                    "UPDATE webhook_deliveries
+                     SET status = 'delivered', attempts = $2, response_status = $3, last_error = NULL, delivered_at = NOW()
+                     WHERE id = $1",
+                    delivery_id,
+                    attempt,
+                    response.status().as_u16() as i16
+                )
+                    .execute(pool)
+                    .await?;
+                return Ok(());
+            }
+            Ok(response) => (Some(response.status().as_u16() as i16), format!("HTTP {}", response.status())),
+            Err(e) => (None, e.to_string()),
+        };
+
+        let status = if attempt == MAX_ATTEMPTS { "failed" } else { "pending" };
+        sqlx::query!(
+            "UPDATE webhook_deliveries SET status = $2, attempts = $3, response_status = $4, last_error = $5 WHERE id = $1",
+            delivery_id,
+            status,
+            attempt,
+            response_status,
+            error
+        )
+            .execute(pool)
+            .await?;
+
+        if attempt < MAX_ATTEMPTS {
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+fn actions(page: &Value) -> Vec<&str> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn activity_feed_lists_own_and_collaborator_actions_newest_first() {
+    let ctx = TestContext::setup().await;
+    let (owner_id, owner_token) = create_user(&ctx.pool).await;
+    let (friend_id, friend_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", owner_token.as_str()))
+        .set_json(json!({ "title": "Plan trip" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    let todo_id = todo["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/todos/{}/shares", todo_id))
+        .insert_header(("Authorization", owner_token.as_str()))
+        .set_json(json!({ "user_id": friend_id, "can_edit": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    // The friend completes the owner's todo
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", todo_id))
+        .insert_header(("Authorization", friend_token.as_str()))
+        .set_json(json!({ "title": "Plan trip", "completed": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/activity", owner_id))
+        .insert_header(("Authorization", owner_token.as_str()))
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(actions(&page), ["completed", "updated", "shared", "created"]);
+    assert_eq!(page["items"][0]["user_id"], friend_id);
+    assert_eq!(page["items"][0]["entity_type"], "todo");
+    assert_eq!(page["items"][0]["entity_id"], todo_id);
+    assert_eq!(page["items"][2]["meta"]["shared_with_user_id"], friend_id);
+    assert_eq!(page["next_cursor"], Value::Null);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/activity?limit=2", owner_id))
+        .insert_header(("Authorization", owner_token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("X-Total-Count").unwrap(), "4");
+    let first: Value = test::read_body_json(resp).await;
+    assert_eq!(actions(&first), ["completed", "updated"]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/activity?limit=2&before_id={}", owner_id, first["next_cursor"]))
+        .insert_header(("Authorization", owner_token.as_str()))
+        .to_request();
+    let second: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(actions(&second), ["shared", "created"]);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/activity", owner_id))
+        .insert_header(("Authorization", friend_token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+}
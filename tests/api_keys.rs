@@ -0,0 +1,83 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn api_keys_authenticate_until_deleted() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api-keys")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "description": "CI", "expires_at": "2000-01-01T00:00:00Z" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = test::TestRequest::post()
+        .uri("/api-keys")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "description": "CI" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: Value = test::read_body_json(resp).await;
+    let key = created["key"].as_str().unwrap().to_string();
+    assert_eq!(key.len(), 72);
+    assert_eq!(created["key_prefix"], &key[..8]);
+    assert_eq!(created["description"], "CI");
+
+    // Only the hash is stored
+    let stored: String = sqlx::query_scalar("SELECT key_hash FROM api_keys WHERE id = $1")
+        .bind(created["id"].as_i64().unwrap() as i32)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert!(!stored.contains(&key));
+
+    let with_key = |key: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/users/{}/todos", user_id))
+            .insert_header(("Authorization", format!("ApiKey {}", key)))
+            .to_request()
+    };
+    let resp = test::call_service(&app, with_key(&key)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let wrong = format!("{}{}", &key[..8], "0".repeat(64));
+    let resp = test::call_service(&app, with_key(&wrong)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // The listing shows the use, but never the key
+    let req = test::TestRequest::get()
+        .uri("/api-keys")
+        .insert_header(("Authorization", format!("ApiKey {}", key)))
+        .to_request();
+    let keys: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(keys.as_array().unwrap().len(), 1);
+    assert!(keys[0]["last_used_at"].is_string());
+    assert!(keys[0].get("key").is_none());
+
+    // Someone else can't delete it
+    let delete = |token: &str| {
+        test::TestRequest::delete()
+            .uri(&format!("/api-keys/{}", created["id"]))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+    let resp = test::call_service(&app, delete(&other_token)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let resp = test::call_service(&app, delete(&token)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = test::call_service(&app, with_key(&key)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use actix_web::{test, web, App, HttpResponse};
+use serde_json::Value;
+use todo_backend::middleware::api_version::{ApiVersion, ApiVersionMiddleware, VersionExtractor};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn routes_are_served_under_v1_and_unprefixed() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool).wrap(ApiVersionMiddleware)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/v1/todos")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(serde_json::json!({ "title": "Versioned" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    assert_eq!(resp.headers().get("X-API-Version").unwrap(), "1");
+    let todo: Value = test::read_body_json(resp).await;
+
+    // The old path is an alias for the same thing
+    for uri in [format!("/v1/todos/{}", todo["id"]), format!("/todos/{}", todo["id"])] {
+        let req = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("Authorization", token.clone()))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 200, "{}", uri);
+        assert_eq!(resp.headers().get("X-API-Version").unwrap(), "1");
+    }
+
+    // Probes aren't versioned, but still say which version answered
+    let req = test::TestRequest::get().uri("/health").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    assert_eq!(resp.headers().get("X-API-Version").unwrap(), "1");
+
+    let req = test::TestRequest::get().uri("/v1/health").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 404);
+    assert_eq!(resp.headers().get("X-API-Version").unwrap(), "1");
+
+    // The header works in place of the prefix, unknown versions are refused
+    let req = test::TestRequest::get()
+        .uri("/todos")
+        .insert_header(("Authorization", token.clone()))
+        .insert_header(("X-API-Version", "1"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+
+    let req = test::TestRequest::get()
+        .uri("/todos")
+        .insert_header(("Authorization", token))
+        .insert_header(("X-API-Version", "2"))
+        .to_request();
+    let resp = match test::try_call_service(&app, req).await {
+        Ok(resp) => resp.into_parts().1,
+        Err(e) => e.error_response(),
+    };
+    assert_eq!(resp.status(), 400);
+    assert_eq!(resp.headers().get("X-API-Version").unwrap(), "1");
+}
+
+#[actix_web::test]
+async fn handlers_can_extract_the_resolved_version() {
+    let app = test::init_service(
+        App::new().wrap(ApiVersionMiddleware).route(
+            "/v1/version",
+            web::get().to(|version: VersionExtractor| async move {
+                assert_eq!(version.0, ApiVersion::V1);
+                HttpResponse::Ok().body(version.0.header_value())
+            }),
+        ),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/v1/version").to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "1");
+}
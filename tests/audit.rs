@@ -0,0 +1,206 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+use todo_backend::auth::jwt::issue_token;
+use todo_backend::models::Role;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn todo_changes_are_audited_with_their_actor() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (admin_id, _) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    // Ids restart with every test, earlier entries would match them too
+    sqlx::query("TRUNCATE audit_log").execute(&ctx.pool).await.unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "title": "Water plants" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "title": "Water the plants" }))
+        .to_request();
+    test::call_service(&app, req).await;
+    let req = test::TestRequest::delete()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    test::call_service(&app, req).await;
+
+    let audit_log = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/admin/audit-log?table=todos&row_id={}", todo["id"]))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, audit_log(&token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(&app, audit_log(&admin_token)).await;
+    assert_eq!(resp.headers().get("X-Total-Count").unwrap(), "3");
+    let entries: Value = test::read_body_json(resp).await;
+    let entries = entries.as_array().unwrap();
+    let actions: Vec<&str> = entries.iter().map(|e| e["action"].as_str().unwrap()).collect();
+    // Newest first; the trash is an update of deleted_at
+    assert_eq!(actions, ["update", "update", "insert"]);
+    assert!(entries.iter().all(|e| e["actor_user_id"] == user_id));
+    assert!(entries[2]["old_data"].is_null());
+    assert_eq!(entries[2]["new_data"]["title"], "Water plants");
+    assert_eq!(entries[1]["old_data"]["title"], "Water plants");
+    assert_eq!(entries[1]["new_data"]["title"], "Water the plants");
+    assert!(entries[0]["new_data"]["deleted_at"].is_string());
+
+    // Users are audited too, without their password hash
+    let req = test::TestRequest::get()
+        .uri(&format!("/admin/audit-log?table=Users&row_id={}", user_id))
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    let entries: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(entries.as_array().unwrap().len(), 0);
+    sqlx::query(r#"UPDATE "Users" SET name = 'renamed' WHERE id = $1"#)
+        .bind(user_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    let entry: (Option<i32>, Value) =
+        sqlx::query_as("SELECT actor_user_id, new_data FROM audit_log WHERE table_name = 'Users' AND row_id = $1")
+            .bind(user_id)
+            .fetch_one(&ctx.pool)
+            .await
+            .unwrap();
+    assert_eq!(entry.0, None);
+    assert_eq!(entry.1["name"], "renamed");
+    assert!(entry.1.get("password").is_none());
+}
+
+#[actix_web::test]
+async fn single_purpose_todo_routes_record_actor_and_address() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (admin_id, _) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let mut ids = Vec::new();
+    for title in ["First", "Second"] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.clone()))
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let todo: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(todo["id"].as_i64().unwrap() as i32);
+    }
+    let (first, second) = (ids[0], ids[1]);
+    // In the trash already, to be restored first
+    sqlx::query("UPDATE todos SET deleted_at = NOW() WHERE id = $1")
+        .bind(second)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    sqlx::query("TRUNCATE audit_log").execute(&ctx.pool).await.unwrap();
+
+    let requests = [
+        ("POST", format!("/todos/{}/restore", second), Value::Null),
+        ("PATCH", "/todos/bulk".to_string(), json!({ "ids": [first, second], "update": { "completed": true } })),
+        ("POST", format!("/todos/{}/archive", first), Value::Null),
+        ("POST", format!("/todos/{}/unarchive", first), Value::Null),
+        ("PATCH", format!("/todos/{}/move", first), json!({ "after_id": second })),
+        ("PATCH", format!("/todos/{}/recurrence", first), json!({ "rule": "FREQ=DAILY" })),
+    ];
+    for (method, uri, body) in requests {
+        let req = test::TestRequest::default()
+            .method(method.parse().unwrap())
+            .uri(&uri)
+            .insert_header(("Authorization", token.clone()))
+            .peer_addr("198.51.100.4:5000".parse().unwrap())
+            .set_json(body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success(), "{} {} answered {}", method, uri, resp.status());
+    }
+    let req = test::TestRequest::delete()
+        .uri(&format!("/todos/{}/permanent", second))
+        .insert_header(("Authorization", admin_token))
+        .peer_addr("198.51.100.9:5000".parse().unwrap())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let entries: Vec<(String, Option<i32>, Option<String>)> =
+        sqlx::query_as("SELECT action, actor_user_id, actor_ip FROM audit_log WHERE table_name = 'todos' ORDER BY id")
+            .fetch_all(&ctx.pool)
+            .await
+            .unwrap();
+    assert!(!entries.is_empty());
+    let (purge, changes) = entries.split_last().unwrap();
+    assert!(changes
+        .iter()
+        .all(|(_, actor, ip)| *actor == Some(user_id) && ip.as_deref() == Some("198.51.100.4")));
+    assert_eq!(purge, &("delete".to_string(), Some(admin_id), Some("198.51.100.9".to_string())));
+}
+
+#[actix_web::test]
+async fn everyday_todo_and_user_changes_record_actor_and_address() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (admin_id, _) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+    let app = test::init_service(build_app(&ctx.pool)).await;
+    sqlx::query("TRUNCATE audit_log").execute(&ctx.pool).await.unwrap();
+
+    let send = |method: &str, uri: String, token: &str, body: Value| {
+        test::TestRequest::default()
+            .method(method.parse().unwrap())
+            .uri(&uri)
+            .insert_header(("Authorization", token.to_string()))
+            .peer_addr("198.51.100.4:5000".parse().unwrap())
+            .set_json(body)
+            .to_request()
+    };
+
+    let todo: Value =
+        test::call_and_read_body_json(&app, send("POST", "/todos".to_string(), &token, json!({ "title": "Plan" }))).await;
+    for (method, uri, body) in [
+        ("PATCH", format!("/todos/{}", todo["id"]), json!({ "title": "Plan it" })),
+        ("POST", format!("/todos/{}/complete", todo["id"]), Value::Null),
+        ("POST", format!("/todos/{}/snooze", todo["id"]), json!({ "duration": "1d" })),
+        ("DELETE", format!("/todos/{}", todo["id"]), Value::Null),
+        ("PATCH", format!("/user/{}", user_id), json!({ "name": "renamed" })),
+    ] {
+        let resp = test::call_service(&app, send(method, uri.clone(), &token, body)).await;
+        assert!(resp.status().is_success(), "{} {} answered {}", method, uri, resp.status());
+    }
+    let resp = test::call_service(
+        &app,
+        send("PATCH", format!("/users/{}/role", user_id), &admin_token, json!({ "role": "admin" })),
+    )
+    .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let entries: Vec<(String, String, Option<i32>, Option<String>)> = sqlx::query_as(
+        "SELECT table_name, action, actor_user_id, actor_ip FROM audit_log ORDER BY id",
+    )
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    let (promotion, changes) = entries.split_last().unwrap();
+    assert_eq!(changes.len(), 6);
+    assert!(changes
+        .iter()
+        .all(|(_, _, actor, ip)| *actor == Some(user_id) && ip.as_deref() == Some("198.51.100.4")));
+    assert_eq!(
+        promotion,
+        &("Users".to_string(), "update".to_string(), Some(admin_id), Some("198.51.100.4".to_string()))
+    );
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn atomic_batch_rolls_back_every_operation_when_one_fails() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let operations = json!([
+        { "method": "POST", "path": "/todos", "body": { "title": "Buy milk" } },
+        { "method": "DELETE", "path": "/todos/999" }
+    ]);
+
+    let req = test::TestRequest::post()
+        .uri("/batch")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "operations": operations, "atomic": true }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["results"][0]["status"], 424);
+    assert_eq!(body["results"][1]["status"], 404);
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    // Without atomic the create stands even though the delete fails
+    let req = test::TestRequest::post()
+        .uri("/batch")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "operations": operations }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["results"][0]["status"], 201);
+    assert_eq!(body["results"][0]["body"]["title"], "Buy milk");
+    assert_eq!(body["results"][1]["status"], 404);
+    assert_eq!(body["results"][1]["body"]["type"], "https://api.example.com/errors/not-found");
+}
+
+#[actix_web::test]
+async fn batch_refuses_paths_outside_the_todo_routes() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    for path in ["http://internal/todos", "/todos/../users/1", "/users/1", "/todos/1?x=1"] {
+        let req = test::TestRequest::post()
+            .uri("/batch")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "operations": [{ "method": "DELETE", "path": path }] }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", path);
+    }
+}
@@ -0,0 +1,50 @@
+use actix_web::body::to_bytes;
+use actix_web::dev::Service;
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App, HttpResponse};
+use serde_json::{json, Value};
+use todo_backend::middleware::body_limit::{extractor_limits, BodyLimit};
+
+async fn echo(body: web::Json<Value>) -> HttpResponse {
+    HttpResponse::Ok().json(body.into_inner())
+}
+
+// Rejections surface as errors the server turns into responses, so do the same here
+async fn post_echo(with_middleware: bool, body: Value) -> HttpResponse {
+    let app = test::init_service(
+        App::new()
+            .wrap(actix_web::middleware::Condition::new(with_middleware, BodyLimit::new(64)))
+            .configure(extractor_limits(64))
+            .route("/echo", web::post().to(echo)),
+    )
+    .await;
+
+    let req = test::TestRequest::post().uri("/echo").set_json(body).to_request();
+    match app.call(req).await {
+        Ok(resp) => resp.into_parts().1.map_into_boxed_body(),
+        Err(e) => e.error_response(),
+    }
+}
+
+async fn problem_type(resp: HttpResponse) -> Value {
+    let body: Value = serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    body["type"].clone()
+}
+
+#[actix_web::test]
+async fn bodies_over_the_limit_get_a_413_problem() {
+    let small = json!({ "title": "Water plants" });
+    let large = json!({ "title": "x".repeat(100) });
+
+    let resp = post_echo(true, small.clone()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = post_echo(false, small).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Refused from the Content-Length by the middleware, and by the JSON extractor without it
+    for with_middleware in [true, false] {
+        let resp = post_echo(with_middleware, large.clone()).await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(problem_type(resp).await, "https://api.example.com/errors/payload-too-large");
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn todos_are_listed_through_the_category_tree() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+
+    let post = |uri: &str, token: &str, body: Value| {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("Authorization", token.to_string()))
+            .set_json(body)
+            .to_request()
+    };
+
+    let work: Value = test::call_and_read_body_json(&app, post("/categories", &token, json!({ "name": "Work" }))).await;
+    let req = post("/categories", &token, json!({ "name": "Meetings", "parent_id": work["id"], "color": "#ff0000" }));
+    let meetings: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(meetings["parent_id"], work["id"]);
+
+    // Parents and todo categories have to be the caller's own
+    let resp = test::call_service(&app, post("/categories", &other_token, json!({ "name": "Mine", "parent_id": work["id"] }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, post("/todos", &other_token, json!({ "title": "Sneaky", "category_id": work["id"] }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = post("/todos", &token, json!({ "title": "Standup", "category_id": meetings["id"] }));
+    let standup: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(standup["category"], json!({ "id": meetings["id"], "name": "Meetings", "parent_id": work["id"], "color": "#ff0000" }));
+    let req = post("/todos", &token, json!({ "title": "Report", "category_id": work["id"] }));
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    let req = post("/todos", &token, json!({ "title": "Groceries" }));
+    let groceries: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(groceries["category"].is_null());
+
+    let list_todos = |category: &Value| {
+        test::TestRequest::get()
+            .uri(&format!("/categories/{}/todos", category["id"]))
+            .insert_header(("Authorization", token.clone()))
+            .to_request()
+    };
+    let titles = |todos: Value| -> Vec<String> {
+        todos.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect()
+    };
+
+    let resp = test::call_service(&app, list_todos(&work)).await;
+    assert_eq!(resp.headers().get("X-Total-Count").unwrap(), "2");
+    let todos: Value = test::read_body_json(resp).await;
+    assert_eq!(titles(todos), ["Standup", "Report"]);
+    let todos: Value = test::call_and_read_body_json(&app, list_todos(&meetings)).await;
+    assert_eq!(titles(todos), ["Standup"]);
+
+    // A category can't end up below itself
+    let req = test::TestRequest::patch()
+        .uri(&format!("/categories/{}", work["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "parent_id": meetings["id"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    // Leaving category_id out keeps it, null clears it
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", report["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "title": "Quarterly report" }))
+        .to_request();
+    let updated: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated["category_id"], work["id"]);
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", report["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "title": "Quarterly report", "category_id": null }))
+        .to_request();
+    let updated: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(updated["category_id"].is_null());
+
+    // Deleting a category moves its children up and uncategorises its todos
+    let req = test::TestRequest::delete()
+        .uri(&format!("/categories/{}", work["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/categories/{}", meetings["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let meetings: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(meetings["parent_id"].is_null());
+
+    let req = test::TestRequest::get()
+        .uri("/categories")
+        .insert_header(("Authorization", other_token))
+        .to_request();
+    let categories: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(categories, json!([]));
+}
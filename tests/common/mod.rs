@@ -0,0 +1,109 @@
+// Each test binary compiles its own copy of this module and uses a different subset of it
+#![allow(dead_code)]
+
+use actix_web::body::BoxBody;
+use actix_web::dev::{ServiceFactory, ServiceRequest, ServiceResponse};
+use actix_web::{web, App};
+use sqlx::PgPool;
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use todo_backend::auth::jwt::issue_token;
+use todo_backend::auth::TokenDenylist;
+use todo_backend::auth::password::hash_password;
+use todo_backend::models::Role;
+use todo_backend::todo_titles::enforce_unique_titles;
+use todo_backend::{configure_routes, MIGRATOR};
+use tokio::sync::{Mutex, MutexGuard};
+
+// Tests in one binary share the database, so they take turns
+static DB_LOCK: Mutex<()> = Mutex::const_new(());
+static USER_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// A connection to the test database (TEST_DATABASE_URL), emptied before each test
+pub struct TestContext {
+    pub pool: PgPool,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestContext {
+    pub async fn setup() -> Self {
+        let lock = DB_LOCK.lock().await;
+
+        if env::var("JWT_SECRET").is_err() {
+            env::set_var("JWT_SECRET", "integration-test-secret");
+        }
+
+        let database_url = env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL must be set");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("Failed to connect to the test database");
+
+        MIGRATOR
+            .run(&pool)
+            .await
+            .expect("Failed to run migrations");
+
+        let ctx = TestContext { pool, _lock: lock };
+        ctx.cleanup().await;
+        ctx
+    }
+
+    // Empty every table the migrations created, ids start over at 1, and drop the optional title
+    // index a test may have created
+    pub async fn cleanup(&self) {
+        sqlx::query(
+            "DO $$
+             DECLARE
+                 tables TEXT;
+             BEGIN
+                 SELECT string_agg(format('%I', tablename), ', ') INTO tables
+                 FROM pg_tables
+                 WHERE schemaname = 'public' AND tablename <> '_sqlx_migrations';
+                 EXECUTE 'TRUNCATE ' || tables || ' RESTART IDENTITY CASCADE';
+             END $$",
+        )
+        .execute(&self.pool)
+        .await
+        .expect("Failed to truncate tables");
+        enforce_unique_titles(&self.pool, false)
+            .await
+            .expect("Failed to drop the title index");
+    }
+}
+
+// The app with every route, backed by the pool. Tests chain whatever else they need (middleware,
+// a cache, an event channel) onto it.
+pub fn build_app(
+    pool: &PgPool,
+) -> App<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<BoxBody>, Error = actix_web::Error, InitError = ()>> {
+    App::new()
+        .app_data(web::Data::new(pool.clone()))
+        .app_data(web::Data::new(TokenDenylist::default()))
+        .configure(configure_routes)
+}
+
+// Insert a user straight into the database and return its id with a bearer token for it
+pub async fn create_user(pool: &PgPool) -> (i32, String) {
+    let name = format!("user{}", USER_COUNTER.fetch_add(1, Ordering::SeqCst));
+    let password_hash = hash_password("correct horse battery").expect("Failed to hash password");
+
+    let user_id: i32 =
+        sqlx::query_scalar(r#"INSERT INTO "Users" (name, password) VALUES ($1, $2) RETURNING id"#)
+            .bind(name)
+            .bind(password_hash)
+            .fetch_one(pool)
+            .await
+            .expect("Failed to insert user");
+
+    (user_id, format!("Bearer {}", issue_token(user_id, Role::User)))
+}
+
+// Give the user a verified email address, for the features that require one
+pub async fn verify_email(pool: &PgPool, user_id: i32) {
+    sqlx::query(r#"UPDATE "Users" SET email = $1, email_verified = true WHERE id = $2"#)
+        .bind(format!("user{}@example.com", user_id))
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .expect("Failed to verify email");
+}
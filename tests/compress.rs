@@ -0,0 +1,64 @@
+mod common;
+
+use actix_web::middleware::Compress;
+use actix_web::test;
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use std::io::Read;
+use todo_backend::middleware::compress::CompressionThreshold;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn large_responses_are_gzipped_when_the_client_accepts_it() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(
+        build_app(&ctx.pool)
+            .wrap(CompressionThreshold::new(1024))
+            .wrap(Compress::default()),
+    )
+    .await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    sqlx::query(
+        "INSERT INTO todos (title, description, user_id)
+         SELECT 'Todo ' || n, 'Something that needs doing, number ' || n, $1 FROM generate_series(1, 50) n",
+    )
+        .bind(user_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let req = test::TestRequest::get()
+        .uri("/todos?per_page=50")
+        .insert_header(("Authorization", token.clone()))
+        .insert_header(("Accept-Encoding", "gzip"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("Content-Encoding").unwrap(), "gzip");
+
+    let compressed = test::read_body(resp).await;
+    let mut json = String::new();
+    GzDecoder::new(&compressed[..]).read_to_string(&mut json).unwrap();
+    let page: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(page["items"].as_array().unwrap().len(), 50);
+
+    // Clients that don't ask for it get plain JSON
+    let req = test::TestRequest::get()
+        .uri("/todos?per_page=50")
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_ne!(resp.headers().get("Content-Encoding").map(|value| value.as_bytes()), Some(&b"gzip"[..]));
+    let page: Value = test::read_body_json(resp).await;
+    assert_eq!(page["items"].as_array().unwrap().len(), 50);
+
+    // And small bodies are never compressed
+    let req = test::TestRequest::get()
+        .uri("/todos?per_page=1")
+        .insert_header(("Authorization", token))
+        .insert_header(("Accept-Encoding", "gzip"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("Content-Encoding").unwrap(), "identity");
+}
@@ -0,0 +1,151 @@
+use std::env;
+use std::fs;
+use todo_backend::config::AppConfig;
+
+// One test for the whole file, the environment is shared by every test in the binary
+#[test]
+fn config_file_values_are_overridden_by_the_environment() {
+    let dir = env::temp_dir().join(format!("todo-config-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    for key in [
+        "DATABASE_URL",
+        "SERVER_ADDR",
+        "JWT_SECRET",
+        "DB_MAX_CONNECTIONS",
+        "RATE_LIMIT_MAX_REQUESTS",
+        "CORS_ALLOWED_ORIGINS",
+        "AVATAR_ALLOWED_DOMAINS",
+        "DB_PASSWORD",
+        "DB_TEST_BEFORE_ACQUIRE",
+        "MAX_REQUEST_BODY_BYTES",
+        "PREVENT_DUPLICATE_TITLES",
+        "DB_CONNECT_RETRIES",
+        "DB_CONNECT_RETRY_DELAY_MS",
+        "DATABASE_URL_FILE",
+        "JWT_SECRET_FILE",
+        "DB_PASSWORD_FILE",
+    ] {
+        env::remove_var(key);
+    }
+
+    let toml_path = dir.join("config.toml");
+    fs::write(
+        &toml_path,
+        r#"
+database_url = "postgres://file/todo"
+server_addr = "127.0.0.1:9000"
+jwt_secret = "from-file-secret-at-least-32-bytes"
+max_connections = 20
+cors_allowed_origins = ["https://a.example", "https://b.example"]
+
+[rate_limit]
+max_requests = 100
+"#,
+    )
+    .unwrap();
+    env::set_var("CONFIG_PATH", &toml_path);
+    env::set_var("DB_MAX_CONNECTIONS", "30");
+
+    let config = AppConfig::load().unwrap();
+    assert_eq!(config.config_file.as_deref(), Some(toml_path.as_path()));
+    assert_eq!(config.database_url, "postgres://file/todo");
+    assert_eq!(config.server_addr, "127.0.0.1:9000");
+    assert_eq!(config.max_connections, 30); // The environment wins
+    assert_eq!(config.rate_limit.max_requests, 100);
+    assert_eq!(config.rate_limit.window_seconds, 60); // Neither sets it, so the default
+    assert!(config.test_before_acquire);
+    assert_eq!(config.max_request_body_bytes, 1024 * 1024);
+    assert!(!config.prevent_duplicate_titles);
+    assert_eq!(config.db_connect_retries, 5);
+    assert_eq!(config.db_connect_retry_delay, std::time::Duration::from_secs(2));
+    assert_eq!(config.cors_allowed_origins, ["https://a.example", "https://b.example"]);
+    assert_eq!(config.avatar_allowed_domains, ["gravatar.com"]);
+
+    // YAML is picked by the extension
+    let yaml_path = dir.join("config.yaml");
+    fs::write(
+        &yaml_path,
+        "server_addr: 0.0.0.0:8080\njwt_secret: from-yaml-secret-at-least-32-bytes\n",
+    )
+    .unwrap();
+    env::set_var("CONFIG_PATH", &yaml_path);
+    env::set_var("DATABASE_URL", "postgres://env/todo");
+
+    let config = AppConfig::load().unwrap();
+    assert_eq!(config.database_url, "postgres://env/todo");
+    assert_eq!(config.jwt_secret, "from-yaml-secret-at-least-32-bytes");
+
+    // A missing file is fine as long as the environment has what's required
+    env::set_var("CONFIG_PATH", dir.join("missing.toml"));
+    env::remove_var("DATABASE_URL");
+    env::set_var("SERVER_ADDR", "0.0.0.0:8080");
+    env::set_var("JWT_SECRET", "from-env-secret-at-least-32-bytes");
+
+    let err = AppConfig::load().unwrap_err();
+    assert_eq!(err.problems, ["DATABASE_URL: not set"]);
+
+    env::set_var("DATABASE_URL", "postgres://env/todo");
+    let config = AppConfig::load().unwrap();
+    assert!(config.config_file.is_none());
+
+    // Body limits above 10 MB are lowered, with a warning
+    env::set_var("MAX_REQUEST_BODY_BYTES", "1000000000");
+    let config = AppConfig::load().unwrap();
+    assert_eq!(config.max_request_body_bytes, 10 * 1024 * 1024);
+    assert_eq!(config.warnings.len(), 1);
+    env::remove_var("MAX_REQUEST_BODY_BYTES");
+
+    // A file that doesn't parse is reported rather than ignored
+    fs::write(&toml_path, "max_connections = \"lots\"\n").unwrap();
+    env::set_var("CONFIG_PATH", &toml_path);
+    let err = AppConfig::load().unwrap_err();
+    assert!(err.problems[0].starts_with("CONFIG_PATH:"), "{:?}", err.problems);
+
+    // Secrets can come from files, the way Docker mounts them, trimmed
+    env::set_var("CONFIG_PATH", dir.join("missing.toml"));
+    env::remove_var("DATABASE_URL");
+    let url_path = dir.join("database_url");
+    let password_path = dir.join("db_password");
+    fs::write(&url_path, "postgres://secret/todo\n").unwrap();
+    fs::write(&password_path, "  hunter2\n").unwrap();
+    env::set_var("DATABASE_URL_FILE", &url_path);
+    env::set_var("DB_PASSWORD_FILE", &password_path);
+
+    let config = AppConfig::load().unwrap();
+    assert_eq!(config.database_url, "postgres://secret/todo");
+    assert_eq!(config.db_password.as_deref(), Some("hunter2"));
+    assert!(config.warnings.is_empty());
+
+    // The variable itself wins over its file, with a warning
+    env::set_var("DATABASE_URL", "postgres://env/todo");
+    let config = AppConfig::load().unwrap();
+    assert_eq!(config.database_url, "postgres://env/todo");
+    assert_eq!(config.warnings, ["DATABASE_URL and DATABASE_URL_FILE are both set, using DATABASE_URL"]);
+
+    // Everything wrong is reported at once
+    env::remove_var("DATABASE_URL");
+    env::remove_var("DATABASE_URL_FILE");
+    env::set_var("JWT_SECRET", "short");
+    env::set_var("DB_MAX_CONNECTIONS", "lots");
+    let err = AppConfig::load().unwrap_err();
+    assert_eq!(
+        err.problems,
+        [
+            "DATABASE_URL: not set",
+            "DB_MAX_CONNECTIONS: invalid value 'lots'",
+            "JWT_SECRET: must be at least 32 bytes",
+        ]
+    );
+    assert!(err.to_string().contains("  ✗ DATABASE_URL: not set\n"), "{}", err);
+    env::set_var("DATABASE_URL", "postgres://env/todo");
+    env::remove_var("DB_MAX_CONNECTIONS");
+
+    // A secret file that isn't there stops the startup
+    env::remove_var("JWT_SECRET");
+    env::set_var("JWT_SECRET_FILE", dir.join("missing_secret"));
+    let err = AppConfig::load().unwrap_err();
+    assert!(err.problems[0].starts_with("JWT_SECRET_FILE: can't read"), "{:?}", err.problems);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
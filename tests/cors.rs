@@ -0,0 +1,29 @@
+use actix_web::http::header;
+use actix_web::{test, App};
+use todo_backend::configure_routes;
+use todo_backend::middleware::cors::build_cors;
+
+#[actix_web::test]
+async fn preflight_allows_configured_origin() {
+    let app = test::init_service(
+        App::new()
+            .wrap(build_cors(&["http://localhost:3000".to_string()]))
+            .configure(configure_routes),
+    )
+    .await;
+
+    let req = test::TestRequest::default()
+        .method(actix_web::http::Method::OPTIONS)
+        .uri("/todos")
+        .insert_header((header::ORIGIN, "http://localhost:3000"))
+        .insert_header((header::ACCESS_CONTROL_REQUEST_METHOD, "POST"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    assert!(resp.status().is_success());
+    assert_eq!(
+        resp.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "http://localhost:3000"
+    );
+    assert_eq!(resp.headers().get(header::ACCESS_CONTROL_MAX_AGE).unwrap(), "3600");
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn export_is_a_zip_of_the_users_data_once_an_hour() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    for title in ["Keep", "Trash"] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.clone()))
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let todo: Value = test::call_and_read_body_json(&app, req).await;
+        if title == "Trash" {
+            let req = test::TestRequest::delete()
+                .uri(&format!("/todos/{}", todo["id"]))
+                .insert_header(("Authorization", token.clone()))
+                .to_request();
+            test::call_service(&app, req).await;
+        }
+    }
+
+    let export = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/users/{}/export", user_id))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, export(&other_token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(&app, export(&token)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "application/zip");
+    let body = test::read_body(resp).await;
+
+    let mut archive = ZipArchive::new(Cursor::new(body.to_vec())).unwrap();
+    let mut names: Vec<&str> = archive.file_names().collect();
+    names.sort_unstable();
+    assert_eq!(names, ["README.txt", "activity.json", "comments.json", "profile.json", "todos.json"]);
+
+    let mut read_json = |name: &str| -> Value {
+        let mut contents = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut contents).unwrap();
+        serde_json::from_str(&contents).unwrap()
+    };
+    let profile = read_json("profile.json");
+    assert_eq!(profile["id"], user_id);
+    assert!(profile.get("password").is_none());
+    let todos = read_json("todos.json");
+    assert_eq!(todos.as_array().unwrap().len(), 2);
+    assert!(todos[1]["deleted_at"].is_string());
+    assert_eq!(read_json("comments.json"), json!([]));
+
+    let resp = test::call_service(&app, export(&token)).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(resp.headers().contains_key("Retry-After"));
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn blocked_todo_cannot_be_completed_until_blockers_are() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let mut ids = Vec::new();
+    for (title, token) in [("Buy paint", &token), ("Paint fence", &token), ("Not mine", &other_token)] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let todo: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(todo["id"].as_i64().unwrap());
+    }
+    let (paint, fence, foreign) = (ids[0], ids[1], ids[2]);
+
+    let add = |todo_id: i64, blocker_id: i64| {
+        test::TestRequest::post()
+            .uri(&format!("/todos/{}/dependencies", todo_id))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "blocker_id": blocker_id }))
+            .to_request()
+    };
+
+    assert_eq!(test::call_service(&app, add(fence, fence)).await.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(test::call_service(&app, add(fence, foreign)).await.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(&app, add(fence, paint)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let dependencies: Value = test::read_body_json(resp).await;
+    assert_eq!(dependencies["blockers"][0]["id"], paint);
+    assert_eq!(dependencies["blocked"], json!([]));
+
+    assert_eq!(test::call_service(&app, add(fence, paint)).await.status(), StatusCode::CONFLICT);
+    // The reverse would be a cycle
+    assert_eq!(test::call_service(&app, add(paint, fence)).await.status(), StatusCode::CONFLICT);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}/dependencies", paint))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let dependencies: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(dependencies["blocked"][0]["title"], "Paint fence");
+
+    let complete = |todo_id: i64, title: &str| {
+        test::TestRequest::patch()
+            .uri(&format!("/todos/{}", todo_id))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title, "completed": true }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, complete(fence, "Paint fence")).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["type"], "https://api.example.com/errors/blocked");
+    assert_eq!(body["blockers"], json!([{ "id": paint, "title": "Buy paint", "completed": false }]));
+
+    let req = test::TestRequest::patch()
+        .uri("/todos/bulk")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "ids": [fence], "update": { "completed": true } }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    assert_eq!(test::call_service(&app, complete(paint, "Buy paint")).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, complete(fence, "Paint fence")).await.status(), StatusCode::OK);
+
+    let remove = || {
+        test::TestRequest::delete()
+            .uri(&format!("/todos/{}/dependencies/{}", fence, paint))
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, remove()).await.status(), StatusCode::NO_CONTENT);
+    assert_eq!(test::call_service(&app, remove()).await.status(), StatusCode::NOT_FOUND);
+}
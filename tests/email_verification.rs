@@ -0,0 +1,109 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use chrono::Utc;
+use serde_json::{json, Value};
+use todo_backend::auth::email_verification::sign_email_token;
+use todo_backend::configure_routes;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn register_rejects_a_malformed_email() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ctx.pool.clone()))
+            .configure(configure_routes),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/register")
+        .set_json(json!({ "name": "ada", "password": "correct horse battery", "email": "not-an-address" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body["fields"]["email"].is_array());
+
+    let req = test::TestRequest::post()
+        .uri("/register")
+        .set_json(json!({ "name": "ada", "password": "correct horse battery", "email": "ada@example.com" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let verified: (Option<String>, bool) = sqlx::query_as(r#"SELECT email, email_verified FROM "Users" WHERE name = 'ada'"#)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(verified, (Some("ada@example.com".to_string()), false));
+}
+
+#[actix_web::test]
+async fn verified_email_unlocks_webhooks() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+
+    let create_webhook = || {
+        test::TestRequest::post()
+            .uri("/webhooks")
+            .insert_header(("Authorization", token.clone()))
+            .set_json(json!({ "url": "http://localhost:1/hook", "events": ["todo.created"], "secret": "s3cret" }))
+            .to_request()
+    };
+    let send = |token: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/users/{}/email/verify/send", user_id))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+    let confirm = |verification_token: String| {
+        test::TestRequest::post()
+            .uri(&format!("/users/{}/email/verify/confirm", user_id))
+            .insert_header(("Authorization", token.clone()))
+            .set_json(json!({ "token": verification_token }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, create_webhook()).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["type"], "https://api.example.com/errors/email-not-verified");
+
+    let resp = test::call_service(&app, send(&token)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    sqlx::query(r#"UPDATE "Users" SET email = 'me@example.com' WHERE id = $1"#)
+        .bind(user_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let resp = test::call_service(&app, send(&other_token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, send(&token)).await;
+    assert_eq!(resp.status(), StatusCode::ACCEPTED);
+
+    // Tokens are bound to the address and expire
+    let now = Utc::now().timestamp();
+    let resp = test::call_service(&app, confirm(sign_email_token(user_id, "old@example.com", now))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, confirm(sign_email_token(user_id, "me@example.com", now - 2 * 24 * 60 * 60))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, confirm("garbage".to_string())).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, confirm(sign_email_token(user_id, "me@example.com", now))).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = test::call_service(&app, send(&token)).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let resp = test::call_service(&app, create_webhook()).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+}
@@ -0,0 +1,47 @@
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App, HttpResponse};
+use todo_backend::error::AppError;
+use todo_backend::middleware::error_reporting::SentryContext;
+use todo_backend::middleware::request_id::RequestIdMiddleware;
+
+async fn failing() -> Result<HttpResponse, AppError> {
+    Err(AppError::DatabaseError(sqlx::Error::PoolTimedOut))
+}
+
+async fn missing() -> Result<HttpResponse, AppError> {
+    Err(AppError::NotFound("Todo not found".to_string()))
+}
+
+#[test]
+fn database_errors_are_reported_once_with_the_request_id() {
+    let events = sentry::test::with_captured_events(|| {
+        actix_web::rt::System::new().block_on(async {
+            // The middleware starts from the main hub unless told otherwise, the test one is current
+            let sentry = sentry_actix::Sentry::builder()
+                .with_hub(sentry::Hub::current())
+                .capture_server_errors(false)
+                .finish();
+            let app = init_service(
+                App::new()
+                    .wrap(SentryContext)
+                    .wrap(sentry)
+                    .wrap(RequestIdMiddleware)
+                    .route("/failing", web::get().to(failing))
+                    .route("/missing", web::get().to(missing)),
+            )
+            .await;
+
+            for uri in ["/failing", "/missing"] {
+                let req = TestRequest::get()
+                    .uri(uri)
+                    .insert_header(("X-Request-Id", "sentry-test"))
+                    .to_request();
+                call_service(&app, req).await;
+            }
+        })
+    });
+
+    // Client errors aren't worth an event
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].tags.get("request_id").map(String::as_str), Some("sentry-test"));
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use actix_web::body::MessageBody;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use serde_json::{json, Value};
+use std::future::poll_fn;
+use std::pin::Pin;
+use todo_backend::auth::jwt::issue_token;
+use todo_backend::events::{self, TodoEvent};
+use todo_backend::models::Role;
+use tokio::sync::broadcast;
+
+use common::{build_app, create_user, TestContext};
+
+// The JSON of the next `data:` event on the stream
+async fn next_event<B: MessageBody>(body: &mut Pin<Box<B>>) -> Value {
+    let chunk = poll_fn(|cx| body.as_mut().poll_next(cx))
+        .await
+        .expect("stream ended")
+        .ok()
+        .unwrap();
+    let text = std::str::from_utf8(&chunk).unwrap();
+    let data = text.strip_prefix("data: ").unwrap().trim_end();
+    serde_json::from_str(data).unwrap()
+}
+
+#[actix_web::test]
+async fn event_stream_sends_snapshot_then_own_changes() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool).app_data(web::Data::new(events::channel()))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Already there" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::get()
+        .uri("/todos/events")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "text/event-stream");
+    let mut body = Box::pin(resp.into_body());
+
+    let snapshot = next_event(&mut body).await;
+    assert_eq!(snapshot["type"], "snapshot");
+    assert_eq!(snapshot["todos"][0]["title"], "Already there");
+
+    // Another user's change isn't sent to this stream
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", other_token.as_str()))
+        .set_json(json!({ "title": "Not yours" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Fresh" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "created");
+    assert_eq!(event["todo_id"], todo["id"]);
+    assert_eq!(event["todo"]["title"], "Fresh");
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Fresher" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "updated");
+    assert_eq!(event["todo"]["title"], "Fresher");
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "deleted");
+    assert_eq!(event["todo_id"], todo["id"]);
+    assert!(event.get("todo").is_none());
+}
+
+#[actix_web::test]
+async fn batch_operations_are_sent_to_the_stream_once_applied() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool).app_data(web::Data::new(events::channel()))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Old" }))
+        .to_request();
+    let old: Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get()
+        .uri("/todos/events")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let mut body = Box::pin(test::call_service(&app, req).await.into_body());
+    assert_eq!(next_event(&mut body).await["type"], "snapshot");
+
+    // The failed update sends nothing, the others arrive in order
+    let req = test::TestRequest::post()
+        .uri("/batch")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "operations": [
+            { "method": "POST", "path": "/todos", "body": { "title": "New" } },
+            { "method": "PATCH", "path": "/todos/999999", "body": { "title": "Missing" } },
+            { "method": "PATCH", "path": format!("/todos/{}", old["id"]), "body": { "title": "Renamed" } },
+            { "method": "DELETE", "path": format!("/todos/{}", old["id"]) }
+        ] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "created");
+    assert_eq!(event["todo"]["title"], "New");
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "updated");
+    assert_eq!(event["todo"]["title"], "Renamed");
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "deleted");
+    assert_eq!(event["todo_id"], old["id"]);
+}
+
+#[actix_web::test]
+async fn archive_restore_purge_and_import_are_sent_to_the_stream() {
+    let ctx = TestContext::setup().await;
+    let (admin_id, _) = create_user(&ctx.pool).await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+    let app = test::init_service(build_app(&ctx.pool).app_data(web::Data::new(events::channel()))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Keep" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    let req = test::TestRequest::get()
+        .uri("/todos/events")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let mut body = Box::pin(test::call_service(&app, req).await.into_body());
+    assert_eq!(next_event(&mut body).await["type"], "snapshot");
+
+    let call = |method: &str, uri: String, token: &str, body: Value| {
+        test::TestRequest::default()
+            .method(method.parse().unwrap())
+            .uri(&uri)
+            .insert_header(("Authorization", token.to_string()))
+            .set_json(body)
+            .to_request()
+    };
+    for (method, path, payload) in [
+        ("POST", "archive", Value::Null),
+        ("POST", "unarchive", Value::Null),
+        ("PATCH", "recurrence", json!({ "rule": "FREQ=DAILY" })),
+    ] {
+        let req = call(method, format!("/todos/{}/{}", todo["id"], path), &token, payload);
+        assert!(test::call_service(&app, req).await.status().is_success());
+        let event = next_event(&mut body).await;
+        assert_eq!(event["type"], "updated", "after {}", path);
+        assert_eq!(event["todo_id"], todo["id"]);
+    }
+
+    test::call_service(&app, call("DELETE", format!("/todos/{}", todo["id"]), &token, Value::Null)).await;
+    assert_eq!(next_event(&mut body).await["type"], "deleted");
+    let resp = test::call_service(&app, call("POST", format!("/todos/{}/restore", todo["id"]), &token, Value::Null)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "created");
+    assert_eq!(event["todo"]["title"], "Keep");
+
+    let resp = test::call_service(&app, call("DELETE", format!("/todos/{}/permanent", todo["id"]), &admin_token, Value::Null)).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "deleted");
+    assert_eq!(event["todo_id"], todo["id"]);
+
+    let req = test::TestRequest::post()
+        .uri("/todos/import")
+        .insert_header(("Authorization", token.as_str()))
+        .insert_header(("Content-Type", "multipart/form-data; boundary=BOUNDARY"))
+        .set_payload(
+            "--BOUNDARY\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"todos.csv\"\r\n\r\n\
+             title\nImported\r\n\
+             --BOUNDARY--\r\n",
+        )
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    let event = next_event(&mut body).await;
+    assert_eq!(event["type"], "created");
+    assert_eq!(event["todo"]["title"], "Imported");
+}
+
+#[actix_web::test]
+async fn a_stream_that_falls_behind_is_closed() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    // Room for only two events that haven't been read yet
+    let sender: broadcast::Sender<TodoEvent> = broadcast::channel(2).0;
+    let app = test::init_service(build_app(&ctx.pool).app_data(web::Data::new(sender))).await;
+
+    let req = test::TestRequest::get()
+        .uri("/todos/events")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let mut body = Box::pin(test::call_service(&app, req).await.into_body());
+    assert_eq!(next_event(&mut body).await["type"], "snapshot");
+
+    for title in ["One", "Two", "Three"] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    }
+
+    // The client has to reconnect for a snapshot instead of silently missing "One"
+    let next = poll_fn(|cx| body.as_mut().poll_next(cx)).await;
+    assert!(next.is_none());
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use todo_backend::configure_routes;
+use todo_backend::middleware::metrics::RequestMetrics;
+
+use common::TestContext;
+
+#[actix_web::test]
+async fn metrics_are_served_to_internal_addresses_only() {
+    let app = test::init_service(App::new().wrap(RequestMetrics).configure(configure_routes)).await;
+
+    let req = test::TestRequest::get().uri("/").to_request();
+    test::call_service(&app, req).await;
+
+    let req = test::TestRequest::get()
+        .uri("/metrics")
+        .peer_addr("10.0.3.7:51234".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert!(body.contains(r#"http_requests_total{method="GET",path="/",status="200"} 1"#));
+    assert!(body.contains(r#"http_request_duration_seconds_count{method="GET",path="/"} 1"#));
+    assert!(body.contains("todos_created_total 0"));
+
+    let req = test::TestRequest::get()
+        .uri("/metrics")
+        .peer_addr("203.0.113.9:51234".parse().unwrap())
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn metrics_report_the_database_pool() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ctx.pool.clone()))
+            .configure(configure_routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/metrics")
+        .peer_addr("127.0.0.1:51234".parse().unwrap())
+        .to_request();
+    let body = String::from_utf8(test::read_body(test::call_service(&app, req).await).await.to_vec()).unwrap();
+
+    let idle_line = body.lines().find(|line| line.starts_with(r#"db_pool_connections{state="idle"}"#));
+    assert!(idle_line.is_some(), "{}", body);
+    assert!(body.contains(r#"db_pool_connections{state="active"}"#));
+    assert!(body.contains("db_pool_max_connections "));
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn notes_are_upserted_and_flagged_on_the_todo() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "title": "Plan the trip" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(todo["has_notes"], false);
+    let uri = format!("/todos/{}/notes", todo["id"]);
+
+    let req = test::TestRequest::get()
+        .uri(&uri)
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let note: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(note, json!({ "content": "", "updated_at": null }));
+
+    let put = |token: &str, content: String| {
+        test::TestRequest::put()
+            .uri(&uri)
+            .insert_header(("Authorization", token.to_string()))
+            .set_json(json!({ "content": content }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, put(&other_token, "Mine now".to_string())).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, put(&token, "x".repeat(50_001))).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let note: Value = test::call_and_read_body_json(&app, put(&token, "Book the train".to_string())).await;
+    assert_eq!(note["content"], "Book the train");
+    assert!(note["updated_at"].is_string());
+
+    let note: Value = test::call_and_read_body_json(&app, put(&token, "Book the train\nPack".to_string())).await;
+    assert_eq!(note["content"], "Book the train\nPack");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(todo["has_notes"], true);
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use chrono::{Days, Utc};
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn notifications_list_todos_due_soon_until_dismissed() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let today = Utc::now().date_naive();
+    let mut ids = Vec::new();
+    for (title, due_date, completed) in [
+        ("Due today", today, false),
+        ("Done today", today, true),
+        ("Due tomorrow", today + Days::new(1), false),
+        ("Overdue", today - Days::new(1), false),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title, "due_date": due_date, "completed": completed }))
+            .to_request();
+        let todo: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(todo["id"].as_i64().unwrap());
+    }
+
+    let list = || {
+        test::TestRequest::get()
+            .uri("/notifications")
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+
+    let notifications: Value = test::call_and_read_body_json(&app, list()).await;
+    let notifications = notifications.as_array().unwrap();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0]["todo_id"], ids[0]);
+    assert_eq!(notifications[0]["title"], "Due today");
+    let minutes = notifications[0]["minutes_until_due"].as_i64().unwrap();
+    assert!((0..=24 * 60).contains(&minutes));
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/notifications/{}/dismiss", ids[0]))
+        .insert_header(("Authorization", other_token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/notifications/{}/dismiss", ids[0]))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let notifications: Value = test::call_and_read_body_json(&app, list()).await;
+    assert_eq!(notifications, json!([]));
+
+    // Once the dismissal is a day old the reminder is back
+    sqlx::query("UPDATE notifications_dismissed SET dismissed_at = NOW() - INTERVAL '25 hours'")
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    let notifications: Value = test::call_and_read_body_json(&app, list()).await;
+    assert_eq!(notifications.as_array().unwrap().len(), 1);
+}
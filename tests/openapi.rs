@@ -0,0 +1,34 @@
+use actix_web::{test, App};
+use serde_json::Value;
+use todo_backend::configure_routes;
+
+#[actix_web::test]
+async fn openapi_json_documents_the_routes_and_bearer_auth() {
+    let app = test::init_service(App::new().configure(configure_routes)).await;
+
+    let req = test::TestRequest::get().uri("/openapi.json").to_request();
+    let spec: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(spec["components"]["securitySchemes"]["BearerAuth"]["scheme"], "bearer");
+
+    let create = &spec["paths"]["/todos"]["post"];
+    assert_eq!(create["security"][0]["BearerAuth"], Value::Array(vec![]));
+    assert!(create["responses"]["201"].is_object());
+    assert!(create["responses"]["422"].is_object());
+
+    // An update answers 422 for invalid fields and for a blocked completion
+    let update = &spec["paths"]["/todos/{todo_id}"]["patch"]["responses"]["422"]["content"]["application/json"]["schema"];
+    assert_eq!(update["$ref"], "#/components/schemas/UnprocessableTodoResponse");
+    let refs: Vec<&str> = spec["components"]["schemas"]["UnprocessableTodoResponse"]["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|schema| schema["$ref"].as_str().unwrap())
+        .collect();
+    assert_eq!(refs, ["#/components/schemas/BlockedResponse", "#/components/schemas/ValidationErrorResponse"]);
+
+    let new_todo = &spec["components"]["schemas"]["NewTodo"]["properties"];
+    assert_eq!(new_todo["description"]["maxLength"], 2000);
+
+    assert!(spec["paths"]["/login"]["post"]["security"].is_null());
+}
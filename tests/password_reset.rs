@@ -0,0 +1,73 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::json;
+use todo_backend::auth::refresh::hash_refresh_token;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn reset_token_sets_a_new_password_once() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, _) = create_user(&ctx.pool).await;
+    let name: String = sqlx::query_scalar(r#"SELECT name FROM "Users" WHERE id = $1"#)
+        .bind(user_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+
+    // Known and unknown names get the same answer
+    for name in [name.as_str(), "nobody"] {
+        let req = test::TestRequest::post()
+            .uri("/password-reset/request")
+            .set_json(json!({ "name": name }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::ACCEPTED);
+    }
+    let issued: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM password_reset_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(issued, 1);
+
+    // The issued token only goes to the log, so plant one whose plaintext is known
+    sqlx::query(
+        "INSERT INTO password_reset_tokens (user_id, token_hash, expires_at) VALUES
+             ($1, $2, NOW() + INTERVAL '1 hour'),
+             ($1, $3, NOW() - INTERVAL '1 minute')",
+    )
+        .bind(user_id)
+        .bind(hash_refresh_token("fresh-token"))
+        .bind(hash_refresh_token("stale-token"))
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let confirm = |token: &str| {
+        test::TestRequest::post()
+            .uri("/password-reset/confirm")
+            .set_json(json!({ "token": token, "new_password": "a brand new secret" }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, confirm("stale-token")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, confirm("fresh-token")).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = test::call_service(&app, confirm("fresh-token")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(json!({ "name": name, "password": "a brand new secret" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
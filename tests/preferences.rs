@@ -0,0 +1,58 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn preferences_are_merge_patched() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let uri = format!("/users/{}/preferences", user_id);
+
+    let get = |token: &str| {
+        test::TestRequest::get()
+            .uri(&uri)
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+    let patch = |body: Value| {
+        test::TestRequest::patch()
+            .uri(&uri)
+            .insert_header(("Authorization", token.clone()))
+            .insert_header(("Content-Type", "application/merge-patch+json"))
+            .set_payload(body.to_string())
+            .to_request()
+    };
+
+    let preferences: Value = test::call_and_read_body_json(&app, get(&token)).await;
+    assert_eq!(preferences, json!({}));
+
+    let resp = test::call_service(&app, get(&other_token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // Unknown keys are dropped
+    let req = patch(json!({ "theme": "dark", "default_per_page": 50, "font": "Comic Sans" }));
+    let preferences: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(preferences, json!({ "theme": "dark", "default_per_page": 50 }));
+
+    // Merged into what's stored, null removes a key
+    let req = patch(json!({ "show_completed": false, "default_per_page": null }));
+    let preferences: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(preferences, json!({ "theme": "dark", "show_completed": false }));
+
+    let resp = test::call_service(&app, patch(json!({ "theme": "blue", "default_per_page": 500, "default_sort_by": "title" }))).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body["fields"]["theme"].is_array());
+    assert!(body["fields"]["default_per_page"].is_array());
+    assert!(body["fields"].get("default_sort_by").is_none());
+
+    let preferences: Value = test::call_and_read_body_json(&app, get(&token)).await;
+    assert_eq!(preferences, json!({ "theme": "dark", "show_completed": false }));
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App};
+use serde_json::json;
+use todo_backend::configure_routes;
+use todo_backend::middleware::rate_limit::{RateLimit, RateLimitStore, RateLimiter};
+
+use common::TestContext;
+
+#[actix_web::test]
+async fn register_is_limited_to_five_requests_per_minute() {
+    let ctx = TestContext::setup().await;
+    let store = web::Data::new(RateLimitStore::new(
+        RateLimit { max_requests: 60, window_seconds: 60 },
+        RateLimit { max_requests: 5, window_seconds: 60 },
+    ));
+    let app = test::init_service(
+        App::new()
+            .wrap(RateLimiter::new(store))
+            .app_data(web::Data::new(ctx.pool.clone()))
+            .configure(configure_routes),
+    )
+    .await;
+
+    let mut statuses = Vec::new();
+    for i in 0..10 {
+        let req = test::TestRequest::post()
+            .uri("/register")
+            .peer_addr("10.0.0.1:4000".parse().unwrap())
+            .set_json(json!({ "name": format!("flood{}", i), "password": "password123" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+            assert!(resp.headers().contains_key("Retry-After"));
+        }
+        statuses.push(resp.status());
+    }
+
+    assert!(statuses[..5].iter().all(|status| *status == StatusCode::CREATED));
+    assert_eq!(statuses[5], StatusCode::TOO_MANY_REQUESTS);
+}
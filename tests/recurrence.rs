@@ -0,0 +1,90 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{json, Value};
+use todo_backend::recurrence::create_due_occurrences;
+
+use common::{build_app, create_user, TestContext};
+
+// id, title, completed, recurrence_rule, next_occurrence_at
+type RecurringRow = (i32, String, bool, Option<String>, Option<DateTime<Utc>>);
+
+#[actix_web::test]
+async fn completed_recurring_todo_is_copied_when_due() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants", "priority": "high" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    let todo_id = todo["id"].as_i64().unwrap();
+
+    for rule in ["FREQ=HOURLY", "INTERVAL=2", "FREQ=DAILY;UNTIL=tomorrow", "FREQ=DAILY;UNTIL=20000101"] {
+        let req = test::TestRequest::patch()
+            .uri(&format!("/todos/{}/recurrence", todo_id))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "rule": rule }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", rule);
+    }
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}/recurrence", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "rule": "FREQ=WEEKLY;INTERVAL=2" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let todo: Value = test::read_body_json(resp).await;
+    assert_eq!(todo["recurrence_rule"], "FREQ=WEEKLY;INTERVAL=2");
+    let next: DateTime<Utc> = todo["next_occurrence_at"].as_str().unwrap().parse().unwrap();
+    assert!((next - (Utc::now() + Duration::weeks(2))).num_minutes().abs() < 1);
+
+    // Not due yet, and an open todo isn't copied even once it is
+    assert_eq!(create_due_occurrences(&ctx.pool, None).await.unwrap(), 0);
+    sqlx::query("UPDATE todos SET next_occurrence_at = NOW() - INTERVAL '1 day' WHERE id = $1")
+        .bind(todo_id as i32)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(create_due_occurrences(&ctx.pool, None).await.unwrap(), 0);
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants", "priority": "high", "completed": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    assert_eq!(create_due_occurrences(&ctx.pool, None).await.unwrap(), 1);
+
+    // The copy is open and carries the schedule on, the completed original keeps none
+    let rows: Vec<RecurringRow> = sqlx::query_as(
+        "SELECT id, title, completed, recurrence_rule, next_occurrence_at FROM todos WHERE user_id = $1 ORDER BY id",
+    )
+        .bind(user_id)
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].3, None);
+    let (copy_id, title, completed, rule, next) = rows[1].clone();
+    assert_eq!((title.as_str(), completed, rule.as_deref()), ("Water plants", false, Some("FREQ=WEEKLY;INTERVAL=2")));
+    assert!(next.unwrap() > Utc::now() + Duration::weeks(1));
+
+    // Clearing the rule stops the series
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}/recurrence", copy_id))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "rule": null }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(todo["recurrence_rule"], Value::Null);
+    assert_eq!(todo["next_occurrence_at"], Value::Null);
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use actix_web::test;
+use serde_json::Value;
+use todo_backend::middleware::request_id::RequestIdMiddleware;
+use uuid::Uuid;
+
+use common::{build_app, TestContext};
+
+#[actix_web::test]
+async fn request_id_is_echoed_and_included_in_error_bodies() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool).wrap(RequestIdMiddleware)).await;
+
+    let req = test::TestRequest::get()
+        .uri("/todos")
+        .insert_header(("X-Request-Id", "support-ticket-42"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+    assert_eq!(resp.headers().get("X-Request-Id").unwrap(), "support-ticket-42");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["request_id"], "support-ticket-42");
+    assert_eq!(body["instance"], "/todos");
+
+    // Without one (or with one too long to log) the server makes up a UUID
+    for req in [
+        test::TestRequest::get().uri("/todos"),
+        test::TestRequest::get().uri("/todos").insert_header(("X-Request-Id", "x".repeat(500))),
+    ] {
+        let resp = test::call_service(&app, req.to_request()).await;
+        let header = resp.headers().get("X-Request-Id").unwrap().to_str().unwrap().to_string();
+        assert!(Uuid::parse_str(&header).is_ok());
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["request_id"], header);
+    }
+}
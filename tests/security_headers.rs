@@ -0,0 +1,50 @@
+use actix_web::{test, App};
+use todo_backend::configure_routes;
+use todo_backend::middleware::security_headers::SecurityHeadersMiddleware;
+
+#[actix_web::test]
+async fn every_response_carries_the_security_headers() {
+    let app = test::init_service(
+        App::new()
+            .wrap(SecurityHeadersMiddleware::new(Some(31536000)))
+            .configure(configure_routes),
+    )
+    .await;
+
+    // Errors get them too
+    for uri in ["/health", "/no-such-route"] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let resp = test::call_service(&app, req).await;
+
+        let headers = resp.headers();
+        assert_eq!(headers.get("X-Content-Type-Options").unwrap(), "nosniff");
+        assert_eq!(headers.get("X-Frame-Options").unwrap(), "DENY");
+        assert_eq!(headers.get("X-XSS-Protection").unwrap(), "1; mode=block");
+        assert_eq!(headers.get("Referrer-Policy").unwrap(), "no-referrer");
+        assert!(headers
+            .get("Content-Security-Policy")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("default-src 'none'"));
+        assert_eq!(
+            headers.get("Strict-Transport-Security").unwrap(),
+            "max-age=31536000; includeSubDomains"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn strict_transport_security_is_only_sent_when_configured() {
+    let app = test::init_service(
+        App::new()
+            .wrap(SecurityHeadersMiddleware::new(None))
+            .configure(configure_routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/health").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.headers().get("X-Frame-Options").is_some());
+    assert!(resp.headers().get("Strict-Transport-Security").is_none());
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use actix_web::test;
+use chrono::{Days, Utc};
+use serde_json::{json, Value};
+use todo_backend::auth::jwt::issue_token;
+use todo_backend::models::Role;
+use todo_backend::snapshots::take_snapshots;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn stats_summarise_the_callers_todos() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/tags")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "name": "work" }))
+        .to_request();
+    let tag: Value = test::call_and_read_body_json(&app, req).await;
+
+    let today = Utc::now().date_naive();
+    for (token, body) in [
+        (&token, json!({ "title": "Done", "completed": true, "priority": "high", "tag_ids": [tag["id"]] })),
+        (&token, json!({ "title": "Late", "due_date": today - Days::new(2), "priority": "high", "tag_ids": [tag["id"]] })),
+        (&token, json!({ "title": "Today", "due_date": today, "priority": "low" })),
+        (&token, json!({ "title": "Someday" })),
+        (&other_token, json!({ "title": "Not counted", "completed": true })),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(body)
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+
+    let get_stats = || {
+        test::TestRequest::get()
+            .uri("/todos/stats")
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+    let stats: Value = test::call_and_read_body_json(&app, get_stats()).await;
+    assert_eq!(stats["total"], 4);
+    assert_eq!(stats["completed"], 1);
+    assert_eq!(stats["overdue"], 1);
+    assert_eq!(stats["due_today"], 1);
+    assert_eq!(stats["completion_rate"], 0.25);
+    assert!(stats["avg_completion_days"].as_f64().unwrap() < 1.0);
+    assert_eq!(stats["by_priority"], json!({ "low": 1, "medium": 1, "high": 2, "critical": 0 }));
+    assert_eq!(stats["by_tag"], json!([{ "tag": "work", "count": 2 }]));
+
+    // Served from the cache for a minute
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "One more" }))
+        .to_request();
+    test::call_service(&app, req).await;
+    let stats: Value = test::call_and_read_body_json(&app, get_stats()).await;
+    assert_eq!(stats["total"], 4);
+}
+
+#[actix_web::test]
+async fn daily_snapshots_make_up_the_history_until_reset() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (admin_id, other_token) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    sqlx::query(
+        "INSERT INTO todos (title, description, user_id, completed, deleted_at) VALUES
+             ('Done', '', $1, true, NULL),
+             ('Open', '', $1, false, NULL),
+             ('Binned', '', $1, false, NOW())",
+    )
+        .bind(user_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let today = Utc::now().date_naive();
+    take_snapshots(&ctx.pool, today).await.unwrap();
+
+    let history_uri = format!("/users/{}/stats/history?start={}&end={}", user_id, today - Days::new(7), today);
+    let req = test::TestRequest::get()
+        .uri(&history_uri)
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        history,
+        json!([{
+            "snapshot_date": today.to_string(),
+            "total_todos": 2,
+            "completed_todos": 1,
+            "created_todos": 3,
+            "deleted_todos": 1,
+        }])
+    );
+
+    let req = test::TestRequest::get()
+        .uri(&history_uri)
+        .insert_header(("Authorization", other_token.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}/stats/history?start={}&end={}", user_id, today, today - Days::new(1)))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+
+    let reset_uri = format!("/users/{}/stats/reset", user_id);
+    let req = test::TestRequest::post()
+        .uri(&reset_uri)
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+
+    let req = test::TestRequest::post()
+        .uri(&reset_uri)
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+
+    let req = test::TestRequest::get()
+        .uri(&history_uri)
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history, json!([]));
+
+    // What happened before the reset no longer counts, the todos themselves are still there
+    take_snapshots(&ctx.pool, today).await.unwrap();
+    let req = test::TestRequest::get()
+        .uri(&history_uri)
+        .insert_header(("Authorization", token))
+        .to_request();
+    let history: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(history[0]["total_todos"], 2);
+    assert_eq!(history[0]["created_todos"], 0);
+    assert_eq!(history[0]["deleted_todos"], 0);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn template_is_instantiated_with_its_variables_and_subtasks() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+
+    let template = json!({
+        "name": "Weekly review",
+        "title_template": "Weekly review {{ week }}",
+        "description_template": "Look back at {{week}}",
+        "priority": "high",
+        "subtask_templates": ["Inbox zero", "Plan {{next_week}}"]
+    });
+    let req = test::TestRequest::post()
+        .uri("/templates")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(&template)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let created: Value = test::read_body_json(resp).await;
+    assert_eq!(created["subtask_templates"], json!(["Inbox zero", "Plan {{next_week}}"]));
+
+    // Names are unique per user
+    let req = test::TestRequest::post()
+        .uri("/templates")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(&template)
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let instantiate = |token: &str, variables: Value| {
+        test::TestRequest::post()
+            .uri(&format!("/templates/{}/instantiate", created["id"]))
+            .insert_header(("Authorization", token.to_string()))
+            .set_json(json!({ "variables": variables }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, instantiate(&other_token, json!({}))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = test::call_service(&app, instantiate(&token, json!({ "week": "42" }))).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["fields"]["variables"][0], "missing next_week");
+
+    let resp = test::call_service(&app, instantiate(&token, json!({ "week": "42", "next_week": "43" }))).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let todo: Value = test::read_body_json(resp).await;
+    assert_eq!(todo["title"], "Weekly review 42");
+    assert_eq!(todo["description"], "Look back at 42");
+    assert_eq!(todo["priority"], "high");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}/subtasks", todo["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let subtasks: Value = test::call_and_read_body_json(&app, req).await;
+    let titles: Vec<&str> = subtasks.as_array().unwrap().iter().map(|s| s["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Inbox zero", "Plan 43"]);
+
+    // Without placeholders no body is needed
+    let req = test::TestRequest::patch()
+        .uri(&format!("/templates/{}", created["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "title_template": "Plain", "description_template": "", "subtask_templates": [] }))
+        .to_request();
+    let updated: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated["name"], "Weekly review");
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/templates/{}/instantiate", created["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/templates/{}", created["id"]))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::get()
+        .uri("/templates")
+        .insert_header(("Authorization", token))
+        .to_request();
+    let templates: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(templates, json!([]));
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn timer_start_stop_and_totals() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Write report" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    let todo_id = todo["id"].as_i64().unwrap();
+    assert_eq!(todo["total_tracked_seconds"], 0);
+
+    // Half an hour logged earlier
+    sqlx::query(
+        "INSERT INTO time_entries (todo_id, user_id, started_at, ended_at)
+         VALUES ($1, $2, NOW() - INTERVAL '2 hours', NOW() - INTERVAL '90 minutes')",
+    )
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let start = |token: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/todos/{}/time/start", todo_id))
+            .insert_header(("Authorization", token))
+            .to_request()
+    };
+    let stop = || {
+        test::TestRequest::post()
+            .uri(&format!("/todos/{}/time/stop", todo_id))
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+
+    assert_eq!(test::call_service(&app, start(&other_token)).await.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(&app, start(&token)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let entry: Value = test::read_body_json(resp).await;
+    assert_eq!(entry["ended_at"], Value::Null);
+    assert_eq!(test::call_service(&app, start(&token)).await.status(), StatusCode::CONFLICT);
+
+    let resp = test::call_service(&app, stop()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let stopped: Value = test::read_body_json(resp).await;
+    let duration = stopped["duration_seconds"].as_i64().unwrap();
+    assert!((0..5).contains(&duration));
+    assert_eq!(test::call_service(&app, stop()).await.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}/time", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let report: Value = test::call_and_read_body_json(&app, req).await;
+    let entries = report["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["duration_seconds"], 1800);
+    assert_eq!(entries[1]["duration_seconds"], duration);
+    assert_eq!(report["total_seconds"], 1800 + duration);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(todo["total_tracked_seconds"], 1800 + duration);
+}
@@ -0,0 +1,59 @@
+use actix_web::body::to_bytes;
+use actix_web::dev::Service;
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App, HttpResponse};
+use serde_json::Value;
+use std::time::Duration;
+use todo_backend::middleware::timeout::RequestTimeout;
+
+async fn slow() -> HttpResponse {
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    HttpResponse::Ok().finish()
+}
+
+// The timeout surfaces as an error the server turns into a response, so do the same here
+async fn call_slow(peer: &str, timeout_header: Option<&str>) -> HttpResponse {
+    let app = test::init_service(
+        App::new()
+            .wrap(RequestTimeout::new(Duration::from_secs(1)))
+            .route("/slow", web::get().to(slow)),
+    )
+    .await;
+
+    let mut req = test::TestRequest::get()
+        .uri("/slow")
+        .peer_addr(peer.parse().unwrap())
+        .insert_header(("X-Request-Id", "timeout-test"));
+    if let Some(secs) = timeout_header {
+        req = req.insert_header(("X-Request-Timeout-Secs", secs));
+    }
+    match app.call(req.to_request()).await {
+        Ok(resp) => resp.into_parts().1.map_into_boxed_body(),
+        Err(e) => e.error_response(),
+    }
+}
+
+#[actix_web::test]
+async fn slow_request_gets_408() {
+    let resp = call_slow("203.0.113.9:51234", None).await;
+    assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+
+    let body: Value = serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(body["type"], "https://api.example.com/errors/request-timeout");
+    assert_eq!(body["status"], 408);
+}
+
+#[actix_web::test]
+async fn internal_proxy_can_raise_the_timeout() {
+    let resp = call_slow("10.0.0.5:51234", Some("5")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn timeout_header_is_ignored_from_outside_or_above_the_maximum() {
+    let resp = call_slow("203.0.113.9:51234", Some("5")).await;
+    assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+
+    let resp = call_slow("10.0.0.5:51234", Some("100000")).await;
+    assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+}
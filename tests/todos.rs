@@ -0,0 +1,976 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use serde_json::{json, Value};
+use todo_backend::auth::jwt::issue_token;
+use todo_backend::models::Role;
+use todo_backend::todo_cache::TodoCache;
+use todo_backend::todo_titles::enforce_unique_titles;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn get_todos_filters_by_completed_status() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let mut ids = Vec::new();
+    for title in ["Buy milk", "Walk the dog"] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let todo: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(todo["id"].as_i64().unwrap());
+    }
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", ids[0]))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Buy milk", "completed": true }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let req = test::TestRequest::get()
+        .uri("/todos?completed=true")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("X-Total-Count").unwrap(), "1");
+    let body: Value = test::read_body_json(resp).await;
+    let items = body["items"].as_array().unwrap();
+
+    assert_eq!(body["total"], 1);
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["id"].as_i64(), Some(ids[0]));
+    assert_eq!(items[0]["completed"], true);
+}
+
+#[actix_web::test]
+async fn todo_with_null_description_is_served() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    // description is nullable, rows written outside the API may leave it NULL
+    let todo_id: i32 =
+        sqlx::query_scalar("INSERT INTO todos (title, description, user_id) VALUES ('Legacy', NULL, $1) RETURNING id")
+            .bind(user_id)
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to insert todo");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let todo: Value = test::read_body_json(resp).await;
+    assert_eq!(todo["description"], "");
+
+    let req = test::TestRequest::get()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["items"][0]["id"], todo_id);
+    assert_eq!(body["items"][0]["description"], Value::Null);
+}
+
+#[actix_web::test]
+async fn create_todo_replays_the_response_for_a_repeated_idempotency_key() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let key = "6f1c2a7e-3b0d-4c8e-9a55-2d7b1e0f4c31";
+    let mut created = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .insert_header(("Idempotency-Key", key))
+            .set_json(json!({ "title": "Pay rent" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let todo: Value = test::read_body_json(resp).await;
+        created.push(todo);
+    }
+    assert_eq!(created[0], created[1]);
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos")
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    // A key whose first request hasn't finished yet
+    let pending = "0b9e4d2c-7f1a-4e36-8c0d-5a2f9b7e1d48";
+    sqlx::query("INSERT INTO idempotency_cache (key, user_id) VALUES ($1, $2)")
+        .bind(pending)
+        .bind(user_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .insert_header(("Idempotency-Key", pending))
+        .set_json(json!({ "title": "Pay rent" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn duplicate_todo_copies_fields_and_subtasks() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Pack", "description": "For the trip", "priority": "high", "completed": true }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    let todo_id = todo["id"].as_i64().unwrap();
+
+    for title in ["Socks", "Charger"] {
+        let req = test::TestRequest::post()
+            .uri(&format!("/todos/{}/subtasks", todo_id))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title, "completed": true }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+    }
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/todos/{}/duplicate", todo_id))
+        .insert_header(("Authorization", other_token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::post()
+        .uri("/todos/999999/duplicate")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/todos/{}/duplicate", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let copy: Value = test::read_body_json(resp).await;
+    assert_ne!(copy["id"], todo["id"]);
+    assert_eq!(copy["title"], "Copy of Pack");
+    assert_eq!(copy["description"], "For the trip");
+    assert_eq!(copy["priority"], "high");
+    assert_eq!(copy["completed"], false);
+    assert_eq!(copy["subtask_count"], 2);
+    assert_eq!(copy["completed_subtask_count"], 0);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}/subtasks", copy["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let subtasks: Value = test::call_and_read_body_json(&app, req).await;
+    let titles: Vec<&str> = subtasks.as_array().unwrap().iter().map(|s| s["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["Socks", "Charger"]);
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/todos/{}/duplicate", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "with_subtasks": false }))
+        .to_request();
+    let copy: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(copy["subtask_count"], 0);
+}
+
+#[actix_web::test]
+async fn bulk_update_changes_only_the_callers_todos() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let mut ids = Vec::new();
+    for (title, token) in [("One", &token), ("Two", &token), ("Theirs", &other_token)] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let todo: Value = test::call_and_read_body_json(&app, req).await;
+        ids.push(todo["id"].as_i64().unwrap());
+    }
+
+    let bulk = |body: Value| {
+        test::TestRequest::patch()
+            .uri("/todos/bulk")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(body)
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, bulk(json!({ "ids": [], "update": { "completed": true } }))).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let too_many: Vec<i64> = (1..=101).collect();
+    let resp = test::call_service(&app, bulk(json!({ "ids": too_many, "update": { "completed": true } }))).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let resp = test::call_service(&app, bulk(json!({ "ids": [ids[0]], "update": {} }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, bulk(json!({ "ids": [ids[0], 999999], "update": { "completed": true } }))).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // One foreign id fails the whole request
+    let resp = test::call_service(&app, bulk(json!({ "ids": ids, "update": { "completed": true } }))).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(&app, bulk(json!({ "ids": [ids[0], ids[1]], "update": { "completed": true } }))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body, json!({ "updated": 2 }));
+
+    let completed: Vec<(i32, bool)> = sqlx::query_as("SELECT id, completed FROM todos ORDER BY id")
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(
+        completed.iter().map(|(_, completed)| *completed).collect::<Vec<_>>(),
+        [true, true, false]
+    );
+}
+
+#[actix_web::test]
+async fn update_with_stale_version_is_a_conflict() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Draft" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(todo["version"], 1);
+
+    let update = |title: &str, version: Option<i64>| {
+        test::TestRequest::patch()
+            .uri(&format!("/todos/{}", todo["id"]))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title, "version": version }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, update("First edit", Some(1))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let updated: Value = test::read_body_json(resp).await;
+    assert_eq!(updated["version"], 2);
+
+    // A second client still holding version 1 is refused
+    let resp = test::call_service(&app, update("Second edit", Some(1))).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["type"], "https://api.example.com/errors/version-conflict");
+    assert_eq!(body["current_version"], 2);
+
+    // Without a version the update isn't checked
+    let resp = test::call_service(&app, update("Last edit", None)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let current: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(current["title"], "Last edit");
+    assert_eq!(current["version"], 3);
+}
+
+#[actix_web::test]
+async fn get_todo_speaks_json_api_when_asked() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let todo_id: i32 =
+        sqlx::query_scalar("INSERT INTO todos (title, description, user_id) VALUES ('Water plants', '', $1) RETURNING id")
+            .bind(user_id)
+            .fetch_one(&ctx.pool)
+            .await
+            .expect("Failed to insert todo");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "application/json");
+    let todo: Value = test::read_body_json(resp).await;
+    assert_eq!(todo["id"], todo_id);
+    assert_eq!(todo["title"], "Water plants");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/todos/{}", todo_id))
+        .insert_header(("Authorization", token.as_str()))
+        .insert_header(("Accept", "application/vnd.api+json"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "application/vnd.api+json");
+    assert!(resp.headers().contains_key("ETag"));
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["data"]["type"], "todos");
+    assert_eq!(body["data"]["id"], todo_id.to_string());
+    assert_eq!(body["data"]["attributes"]["title"], "Water plants");
+    assert!(body["data"]["attributes"].get("id").is_none());
+}
+
+#[actix_web::test]
+async fn ics_export_has_an_event_per_due_todo() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    for todo in [
+        json!({ "title": "File taxes", "description": "Before the deadline", "due_date": "2030-04-15", "priority": "critical" }),
+        json!({ "title": "Renew passport", "due_date": "2030-06-01", "completed": true }),
+        json!({ "title": "Someday" }),
+    ] {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(todo)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/todos/export.ics")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "text/calendar; charset=utf-8");
+    assert_eq!(resp.headers().get("Content-Disposition").unwrap(), "attachment; filename=\"todos.ics\"");
+    let body = String::from_utf8(test::read_body(resp).await.to_vec()).unwrap();
+    assert_eq!(body.matches("BEGIN:VEVENT").count(), 2);
+    assert!(body.contains("SUMMARY:File taxes"));
+    assert!(body.contains("DTSTART;VALUE=DATE:20300415"));
+    assert!(body.contains("DTEND;VALUE=DATE:20300416"));
+    assert!(body.contains("PRIORITY:1"));
+    assert!(body.contains("STATUS:COMPLETED"));
+    assert!(!body.contains("Someday"));
+
+    // Same filters as GET /todos
+    let req = test::TestRequest::get()
+        .uri("/todos/export.ics?completed=false")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let body = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+    assert_eq!(body.matches("BEGIN:VEVENT").count(), 1);
+    assert!(body.contains("STATUS:NEEDS-ACTION"));
+}
+
+#[actix_web::test]
+async fn get_todo_is_served_from_the_cache_until_it_changes() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let cache = web::Data::new(TodoCache::new());
+    let app = test::init_service(build_app(&ctx.pool).app_data(cache.clone())).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    let get = || {
+        test::TestRequest::get()
+            .uri(&format!("/todos/{}", todo["id"]))
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+    let first: Value = test::call_and_read_body_json(&app, get()).await;
+    let second: Value = test::call_and_read_body_json(&app, get()).await;
+    assert_eq!(first, second);
+    // Only the first read queried the database
+    assert_eq!(cache.loads(), 1);
+
+    // An update drops the cached copy, so the next read sees it
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water the plants" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let updated: Value = test::call_and_read_body_json(&app, get()).await;
+    assert_eq!(updated["title"], "Water the plants");
+    assert_eq!(cache.loads(), 2);
+
+    // And a deleted todo isn't served from it either
+    let req = test::TestRequest::delete()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    test::call_service(&app, req).await;
+    let resp = test::call_service(&app, get()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(cache.loads(), 2);
+
+    // Nothing is kept for a todo the query didn't find, each lookup asks the database again
+    for _ in 0..2 {
+        let todo = cache.get_or_load(999, || async { Ok(None) }).await.unwrap();
+        assert!(todo.is_none());
+    }
+    assert_eq!(cache.loads(), 4);
+}
+
+#[actix_web::test]
+async fn etag_after_archiving_a_cached_todo_is_accepted_by_if_match() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool).app_data(web::Data::new(TodoCache::new()))).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "File taxes" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    let get = || {
+        test::TestRequest::get()
+            .uri(&format!("/todos/{}", todo["id"]))
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+    // Puts the unarchived version in the cache
+    test::call_service(&app, get()).await;
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/todos/{}/archive", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+
+    let resp = test::call_service(&app, get()).await;
+    let etag = resp.headers().get("ETag").unwrap().to_str().unwrap().to_string();
+    let archived: Value = test::read_body_json(resp).await;
+    assert_eq!(archived["archived"], true);
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .insert_header(("If-Match", etag.as_str()))
+        .set_json(json!({ "title": "File the taxes" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn complete_all_and_incomplete_all_flip_every_todo() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    for (token, title) in (1..=5).map(|n| (&token, format!("Chore {}", n))).chain([(&other_token, "Not mine".to_string())]) {
+        let req = test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+
+    let post = |uri: &str, token: &str| {
+        test::TestRequest::post()
+            .uri(uri)
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+    let list = |uri: &str, token: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+
+    let body: Value = test::call_and_read_body_json(&app, post("/todos/complete-all", &token)).await;
+    assert_eq!(body, json!({ "updated": 5 }));
+    let body: Value = test::call_and_read_body_json(&app, list("/todos?completed=false", &token)).await;
+    assert_eq!(body["items"], json!([]));
+
+    // Nothing left to complete, and nobody else's todos were touched
+    let body: Value = test::call_and_read_body_json(&app, post("/todos/complete-all", &token)).await;
+    assert_eq!(body, json!({ "updated": 0 }));
+    let body: Value = test::call_and_read_body_json(&app, list("/todos?completed=false", &other_token)).await;
+    assert_eq!(body["total"], 1);
+
+    let body: Value = test::call_and_read_body_json(&app, post("/todos/incomplete-all", &token)).await;
+    assert_eq!(body, json!({ "updated": 5 }));
+    let body: Value = test::call_and_read_body_json(&app, list("/todos?completed=true", &token)).await;
+    assert_eq!(body["items"], json!([]));
+}
+
+#[actix_web::test]
+async fn clear_completed_trashes_only_completed_todos_and_audits_them_once() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    for completed in [true, true, true, false, false] {
+        sqlx::query("INSERT INTO todos (title, description, completed, user_id) VALUES ('Chore', '', $1, $2)")
+            .bind(completed)
+            .bind(user_id)
+            .execute(&ctx.pool)
+            .await
+            .expect("Failed to insert todo");
+    }
+
+    let clear = |uri: &str| {
+        test::TestRequest::delete()
+            .uri(uri)
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+
+    // Nothing happens without the confirmation
+    let resp = test::call_service(&app, clear("/todos/completed")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let body: Value = test::call_and_read_body_json(&app, clear("/todos/completed?confirm=true")).await;
+    assert_eq!(body, json!({ "deleted": 3 }));
+
+    let req = test::TestRequest::get()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["total"], 2);
+
+    // The seeded rows have no actor, so the user's entries are the clearing alone
+    let entries: Vec<(String, Option<Value>)> =
+        sqlx::query_as("SELECT action, meta FROM audit_log WHERE actor_user_id = $1")
+            .bind(user_id)
+            .fetch_all(&ctx.pool)
+            .await
+            .unwrap();
+    assert_eq!(entries, [("bulk_delete".to_string(), Some(json!({ "count": 3, "permanent": false })))]);
+}
+
+#[actix_web::test]
+async fn complete_and_incomplete_only_touch_the_completed_flag() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants", "description": "Twice a week", "priority": "high" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    let post = |action: &str, token: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/todos/{}/{}", todo["id"], action))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+
+    let completed: Value = test::call_and_read_body_json(&app, post("complete", &token)).await;
+    assert_eq!(completed["completed"], true);
+    assert_eq!(completed["title"], "Water plants");
+    assert_eq!(completed["description"], "Twice a week");
+    assert_eq!(completed["priority"], "high");
+    assert_eq!(completed["version"], todo["version"].as_i64().unwrap() + 1);
+
+    let reopened: Value = test::call_and_read_body_json(&app, post("incomplete", &token)).await;
+    assert_eq!(reopened["completed"], false);
+    assert_eq!(reopened["priority"], "high");
+
+    let resp = test::call_service(&app, post("complete", &other_token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn todo_lifecycle_covers_the_basic_status_codes() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let create = |title: &str| {
+        test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": title }))
+            .to_request()
+    };
+    let resp = test::call_service(&app, create("Water plants")).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let todo: Value = test::read_body_json(resp).await;
+    assert_eq!(todo["title"], "Water plants");
+    assert_eq!(todo["completed"], false);
+
+    let resp = test::call_service(&app, create("")).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = test::TestRequest::get()
+        .uri("/todos/999999")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+    let delete = || {
+        test::TestRequest::delete()
+            .uri(&format!("/todos/{}", todo["id"]))
+            .insert_header(("Authorization", token.as_str()))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, delete()).await.status(), StatusCode::NO_CONTENT);
+    assert_eq!(test::call_service(&app, delete()).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn patch_changes_only_the_fields_in_the_body() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants", "description": "Twice a week", "due_date": "2030-01-01" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    let patch = |body: Value| {
+        test::TestRequest::patch()
+            .uri(&format!("/todos/{}", todo["id"]))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(body)
+            .to_request()
+    };
+
+    let updated: Value = test::call_and_read_body_json(&app, patch(json!({ "completed": true }))).await;
+    assert_eq!(updated["completed"], true);
+    assert_eq!(updated["title"], "Water plants");
+    assert_eq!(updated["description"], "Twice a week");
+    assert_eq!(updated["due_date"], "2030-01-01");
+
+    // null clears the due date, leaving it out kept it
+    let updated: Value = test::call_and_read_body_json(&app, patch(json!({ "due_date": null }))).await;
+    assert!(updated["due_date"].is_null());
+    assert_eq!(updated["completed"], true);
+
+    let resp = test::call_service(&app, patch(json!({}))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn admins_move_a_todo_to_another_user_with_its_tags() {
+    let ctx = TestContext::setup().await;
+    let (owner_id, owner_token) = create_user(&ctx.pool).await;
+    let (target_id, target_token) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(owner_id, Role::Admin));
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let todo_id: i32 = sqlx::query_scalar(
+        "INSERT INTO todos (title, description, user_id) VALUES ('Renew passport', '', $1) RETURNING id",
+    )
+        .bind(owner_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    // The target already has "errands", "urgent" has to be created for them
+    for (user_id, name) in [(owner_id, "errands"), (owner_id, "urgent"), (target_id, "errands")] {
+        sqlx::query("INSERT INTO tags (user_id, name) VALUES ($1, $2)")
+            .bind(user_id)
+            .bind(name)
+            .execute(&ctx.pool)
+            .await
+            .unwrap();
+    }
+    sqlx::query("INSERT INTO todo_tags (todo_id, tag_id) SELECT $1, id FROM tags WHERE user_id = $2")
+        .bind(todo_id)
+        .bind(owner_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let move_to = |target_user_id: i32, token: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/todos/{}/move-to-user", todo_id))
+            .insert_header(("Authorization", token.to_string()))
+            .set_json(json!({ "target_user_id": target_user_id }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, move_to(target_id, &owner_token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, move_to(9999, &admin_token)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = test::call_service(&app, move_to(target_id, &admin_token)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let todo: Value = test::read_body_json(resp).await;
+    assert_eq!(todo["user_id"], target_id);
+    assert_eq!(todo["tags"], json!(["errands", "urgent"]));
+
+    let tag_owners: Vec<i32> = sqlx::query_scalar(
+        "SELECT tags.user_id FROM todo_tags JOIN tags ON tags.id = todo_tags.tag_id WHERE todo_id = $1",
+    )
+        .bind(todo_id)
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(tag_owners, [target_id, target_id]);
+
+    let get = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/todos/{}", todo_id))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, get(&target_token)).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, get(&owner_token)).await.status(), StatusCode::FORBIDDEN);
+
+    let actors: Vec<Option<i32>> =
+        sqlx::query_scalar("SELECT actor_user_id FROM audit_log WHERE table_name = 'todos' AND action = 'update'")
+            .fetch_all(&ctx.pool)
+            .await
+            .unwrap();
+    assert_eq!(actors, [Some(owner_id)]);
+}
+
+#[actix_web::test]
+async fn created_todos_can_be_fetched_from_their_location() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    // The location keeps the version prefix of the request
+    for (collection, prefix) in [("/todos", ""), ("/v1/todos", "/v1")] {
+        let req = test::TestRequest::post()
+            .uri(collection)
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "title": "Book dentist", "priority": "high" }))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let location = resp.headers().get("Location").unwrap().to_str().unwrap().to_string();
+        let created: Value = test::read_body_json(resp).await;
+        assert_eq!(location, format!("{}/todos/{}", prefix, created["id"]));
+
+        let req = test::TestRequest::get()
+            .uri(&location)
+            .insert_header(("Authorization", token.as_str()))
+            .to_request();
+        let fetched: Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(fetched, created);
+    }
+}
+
+#[actix_web::test]
+async fn snoozing_moves_the_due_date_back() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let mut todo_ids = Vec::new();
+    for due_date in [Some(chrono::NaiveDate::from_ymd_opt(2025, 1, 31).unwrap()), None] {
+        let todo_id: i32 = sqlx::query_scalar(
+            "INSERT INTO todos (title, description, due_date, user_id) VALUES ('Call plumber', '', $1, $2) RETURNING id",
+        )
+            .bind(due_date)
+            .bind(user_id)
+            .fetch_one(&ctx.pool)
+            .await
+            .unwrap();
+        todo_ids.push(todo_id);
+    }
+
+    let snooze = |todo_id: i32, duration: &str| {
+        test::TestRequest::post()
+            .uri(&format!("/todos/{}/snooze", todo_id))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(json!({ "duration": duration }))
+            .to_request()
+    };
+
+    // A month is a calendar month
+    let todo: Value = test::call_and_read_body_json(&app, snooze(todo_ids[0], "1m")).await;
+    assert_eq!(todo["due_date"], "2025-02-28");
+    let todo: Value = test::call_and_read_body_json(&app, snooze(todo_ids[0], "1w")).await;
+    assert_eq!(todo["due_date"], "2025-03-07");
+
+    // Without a due date it's counted from today
+    let todo: Value = test::call_and_read_body_json(&app, snooze(todo_ids[1], "3d")).await;
+    let expected = chrono::Utc::now().date_naive() + chrono::Days::new(3);
+    assert_eq!(todo["due_date"], expected.to_string());
+
+    let resp = test::call_service(&app, snooze(todo_ids[1], "5y")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let actions: Vec<String> =
+        sqlx::query_scalar("SELECT action::text FROM activity_log WHERE entity_id = $1 ORDER BY id")
+            .bind(todo_ids[0])
+            .fetch_all(&ctx.pool)
+            .await
+            .unwrap();
+    assert_eq!(actions, ["snoozed", "snoozed"]);
+}
+
+#[actix_web::test]
+async fn duplicate_titles_are_refused_when_prevented() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let create = |title: &str, token: &str| {
+        test::TestRequest::post()
+            .uri("/todos")
+            .insert_header(("Authorization", token.to_string()))
+            .set_json(json!({ "title": title }))
+            .to_request()
+    };
+
+    // Off by default
+    for _ in 0..2 {
+        let resp = test::call_service(&app, create("Water plants", &token)).await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+    }
+    // Turning it on fails while duplicates exist
+    assert!(enforce_unique_titles(&ctx.pool, true).await.is_err());
+    sqlx::query("DELETE FROM todos").execute(&ctx.pool).await.unwrap();
+
+    enforce_unique_titles(&ctx.pool, true).await.unwrap();
+
+    let resp = test::call_service(&app, create("Pay rent", &token)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let todo: Value = test::read_body_json(resp).await;
+
+    let resp = test::call_service(&app, create("pay RENT", &token)).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["type"], "https://api.example.com/errors/duplicate-title");
+    assert_eq!(error["detail"], "You already have a todo with that title");
+
+    // Other users and trashed todos don't count
+    let resp = test::call_service(&app, create("Pay rent", &other_token)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let req = test::TestRequest::delete()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+    let resp = test::call_service(&app, create("Pay rent", &token)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+}
+
+#[actix_web::test]
+async fn import_skips_duplicate_titles_row_by_row_when_prevented() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+    enforce_unique_titles(&ctx.pool, true).await.unwrap();
+
+    let import = |partial: bool| {
+        let body = "--BOUNDARY\r\n\
+                    Content-Disposition: form-data; name=\"file\"; filename=\"todos.csv\"\r\n\
+                    Content-Type: text/csv\r\n\r\n\
+                    title\nPay rent\npay RENT\nBuy milk\r\n\
+                    --BOUNDARY--\r\n";
+        test::TestRequest::post()
+            .uri(&format!("/todos/import?partial={}", partial))
+            .insert_header(("Authorization", token.as_str()))
+            .insert_header(("Content-Type", "multipart/form-data; boundary=BOUNDARY"))
+            .set_payload(body)
+            .to_request()
+    };
+
+    // The duplicate row is reported like any other bad row instead of failing the upload
+    let resp = test::call_service(&app, import(false)).await;
+    assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let report: Value = test::read_body_json(resp).await;
+    assert_eq!(report["errors"], json!([{ "row": 2, "reason": "You already have a todo with that title" }]));
+
+    let resp = test::call_service(&app, import(true)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let report: Value = test::read_body_json(resp).await;
+    assert_eq!(report["imported"], 2);
+    assert_eq!(report["skipped"], 1);
+
+    let titles: Vec<String> = sqlx::query_scalar("SELECT title FROM todos ORDER BY id")
+        .fetch_all(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(titles, ["Pay rent", "Buy milk"]);
+}
+
+#[actix_web::test]
+async fn updates_are_validated_like_new_todos() {
+    let ctx = TestContext::setup().await;
+    let (_, token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    for body in [json!({ "title": "" }), json!({ "description": "x".repeat(2001) })] {
+        let req = test::TestRequest::patch()
+            .uri(&format!("/todos/{}", todo["id"]))
+            .insert_header(("Authorization", token.as_str()))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    // The same rules hold for an update inside a batch
+    let req = test::TestRequest::post()
+        .uri("/batch")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "operations": [
+            { "method": "PATCH", "path": format!("/todos/{}", todo["id"]), "body": { "title": "" } }
+        ] }))
+        .to_request();
+    let batch: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(batch["results"][0]["status"], 422);
+
+    let title: String = sqlx::query_scalar("SELECT title FROM todos WHERE id = $1")
+        .bind(todo["id"].as_i64().unwrap() as i32)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(title, "Water plants");
+}
@@ -0,0 +1,33 @@
+use actix_web::{test, App};
+use actix_web_opentelemetry::RequestTracing;
+use opentelemetry::global;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use todo_backend::configure_routes;
+use todo_backend::middleware::tracing::TraceContext;
+
+#[actix_web::test]
+async fn inbound_traceparent_continues_into_the_response() {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(SdkTracerProvider::builder().build());
+
+    let app = test::init_service(
+        App::new()
+            .wrap(TraceContext)
+            .wrap(RequestTracing::new())
+            .configure(configure_routes),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("traceparent", "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+
+    let traceparent = resp.headers().get("traceparent").unwrap().to_str().unwrap();
+    let parts: Vec<&str> = traceparent.split('-').collect();
+    assert_eq!(parts[1], "4bf92f3577b34da6a3ce929d0e0e4736");
+    // The server span is a child, so it has its own span id
+    assert_ne!(parts[2], "00f067aa0ba902b7");
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+use totp_rs::TOTP;
+
+use common::{build_app, create_user, TestContext};
+
+#[actix_web::test]
+async fn two_factor_setup_login_and_disable() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (other_id, _) = create_user(&ctx.pool).await;
+    let name: String = sqlx::query_scalar(r#"SELECT name FROM "Users" WHERE id = $1"#)
+        .bind(user_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+
+    let post = |uri: String, body: Value| {
+        test::TestRequest::post()
+            .uri(&uri)
+            .insert_header(("Authorization", token.clone()))
+            .set_json(body)
+            .to_request()
+    };
+    let login = |code: Option<&str>| {
+        test::TestRequest::post()
+            .uri("/login")
+            .set_json(json!({ "name": name, "password": "correct horse battery", "totp_code": code }))
+            .to_request()
+    };
+
+    // Only for oneself
+    let resp = test::call_service(&app, post(format!("/users/{}/2fa/setup", other_id), json!({}))).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let resp = test::call_service(&app, post(format!("/users/{}/2fa/setup", user_id), json!({}))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let setup: Value = test::read_body_json(resp).await;
+    let uri = setup["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/todo_backend:"), "{}", uri);
+    assert!(!setup["qr_code_png"].as_str().unwrap().is_empty());
+    let backup_codes: Vec<String> = serde_json::from_value(setup["backup_codes"].clone()).unwrap();
+    assert_eq!(backup_codes.len(), 10);
+    let totp = TOTP::from_url(uri).unwrap();
+
+    // Not enforced until verified
+    let resp = test::call_service(&app, login(None)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let verify = |code: String| post(format!("/users/{}/2fa/verify", user_id), json!({ "code": code }));
+    let resp = test::call_service(&app, verify("abcdef".to_string())).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, verify(totp.generate_current().unwrap())).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let resp = test::call_service(&app, post(format!("/users/{}/2fa/setup", user_id), json!({}))).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    let resp = test::call_service(&app, login(None)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["detail"], "Two-factor code required");
+
+    let resp = test::call_service(&app, login(Some("abcdef"))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = test::call_service(&app, login(Some(&totp.generate_current().unwrap()))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // A backup code works once
+    let resp = test::call_service(&app, login(Some(&backup_codes[0]))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let resp = test::call_service(&app, login(Some(&backup_codes[0]))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // Disabling needs the password and a code
+    let disable = |password: &str, code: String| {
+        post(format!("/users/{}/2fa/disable", user_id), json!({ "password": password, "code": code }))
+    };
+    let resp = test::call_service(&app, disable("wrong password", totp.generate_current().unwrap())).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, disable("correct horse battery", "abcdef".to_string())).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let resp = test::call_service(&app, disable("correct horse battery", totp.generate_current().unwrap())).await;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+
+    let resp = test::call_service(&app, login(None)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
@@ -0,0 +1,397 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::test;
+use serde_json::{json, Value};
+use todo_backend::auth::admin::seed_admin;
+use todo_backend::auth::jwt::issue_token;
+use todo_backend::models::Role;
+
+use common::{build_app, create_user, verify_email, TestContext};
+
+#[actix_web::test]
+async fn register_rejects_a_taken_name_with_conflict() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let body = json!({ "name": "alice", "password": "correct horse battery" });
+
+    let req = test::TestRequest::post().uri("/register").set_json(&body).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let location = resp.headers().get("Location").unwrap().to_str().unwrap().to_string();
+    let user: Value = test::read_body_json(resp).await;
+    assert_eq!(location, format!("/users/{}", user["id"]));
+
+    let req = test::TestRequest::post().uri("/register").set_json(&body).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+    assert_eq!(resp.headers().get("Content-Type").unwrap(), "application/problem+json");
+
+    let error: Value = test::read_body_json(resp).await;
+    assert_eq!(error["type"], "https://api.example.com/errors/conflict");
+    assert_eq!(error["title"], "Conflict");
+    assert_eq!(error["status"], 409);
+    assert_eq!(error["detail"], "A user with that name already exists");
+}
+
+#[actix_web::test]
+async fn renaming_a_user_takes_the_user_or_an_admin_and_never_the_password() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let (admin_id, _) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+
+    let rename = |token: Option<&str>, body: Value| {
+        let mut req = test::TestRequest::patch().uri(&format!("/user/{}", user_id)).set_json(body);
+        if let Some(token) = token {
+            req = req.insert_header(("Authorization", token.to_string()));
+        }
+        req.to_request()
+    };
+
+    let resp = test::call_service(&app, rename(None, json!({ "name": "mallory" }))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = test::call_service(&app, rename(Some(&other_token), json!({ "name": "mallory" }))).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    // Passwords only change through change-password
+    let resp = test::call_service(&app, rename(Some(&token), json!({ "password": "hunter2hunter2" }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let user: Value = test::call_and_read_body_json(&app, rename(Some(&token), json!({ "name": "carol" }))).await;
+    assert_eq!(user["name"], "carol");
+    assert!(user.get("password").is_none());
+    let user: Value = test::call_and_read_body_json(&app, rename(Some(&admin_token), json!({ "name": "dave" }))).await;
+    assert_eq!(user["name"], "dave");
+
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(json!({ "name": "dave", "password": "correct horse battery" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn the_email_is_only_shown_to_the_user_and_admins() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+    let (admin_id, _) = create_user(&ctx.pool).await;
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+    verify_email(&ctx.pool, user_id).await;
+
+    let profile = |token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/users/{}", user_id))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+
+    for token in [&token, &admin_token] {
+        let user: Value = test::call_and_read_body_json(&app, profile(token)).await;
+        assert_eq!(user["email"], format!("user{}@example.com", user_id));
+        assert_eq!(user["email_verified"], true);
+    }
+
+    let user: Value = test::call_and_read_body_json(&app, profile(&other_token)).await;
+    assert_eq!(user["id"], user_id);
+    assert!(user.get("email").is_none());
+    assert!(user.get("email_verified").is_none());
+}
+
+#[actix_web::test]
+async fn user_admin_routes_require_the_admin_role() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, user_token) = create_user(&ctx.pool).await;
+    let admin_id = seed_admin(&ctx.pool, "root", "correct horse battery")
+        .await
+        .unwrap()
+        .expect("no admin existed yet");
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+
+    let req = test::TestRequest::delete().uri(&format!("/users/{}", user_id)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    for req in [
+        test::TestRequest::get().uri("/users"),
+        test::TestRequest::delete().uri(&format!("/users/{}", admin_id)),
+    ] {
+        let req = req.insert_header(("Authorization", user_token.clone())).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    let req = test::TestRequest::get()
+        .uri("/users")
+        .insert_header(("Authorization", admin_token.clone()))
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 2);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/users/{}", user_id))
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn admin_is_only_seeded_when_none_exists() {
+    let ctx = TestContext::setup().await;
+
+    // The name is taken by a regular account, which must not be promoted
+    sqlx::query(r#"INSERT INTO "Users" (name, password) VALUES ('root', 'x')"#)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+    assert!(seed_admin(&ctx.pool, "root", "correct horse battery").await.is_err());
+
+    assert!(seed_admin(&ctx.pool, "admin", "correct horse battery").await.unwrap().is_some());
+    assert!(seed_admin(&ctx.pool, "other-admin", "correct horse battery").await.unwrap().is_none());
+
+    let admins: i64 = sqlx::query_scalar(r#"SELECT COUNT(*) FROM "Users" WHERE role = 'admin'"#)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(admins, 1);
+}
+
+#[actix_web::test]
+async fn erasing_data_deletes_everything_and_anonymises_the_user() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (other_id, other_token) = create_user(&ctx.pool).await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.clone()))
+        .set_json(json!({ "title": "Private plans" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+    // A comment on someone else's todo goes too
+    let other_todo: i32 = sqlx::query_scalar("INSERT INTO todos (title, description, user_id) VALUES ('Theirs', '', $1) RETURNING id")
+        .bind(other_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO comments (todo_id, user_id, body) VALUES ($1, $2, 'Nice')")
+        .bind(other_todo)
+        .bind(user_id)
+        .execute(&ctx.pool)
+        .await
+        .unwrap();
+
+    let erase = |token: &str, password: &str| {
+        test::TestRequest::delete()
+            .uri(&format!("/users/{}/data", user_id))
+            .insert_header(("Authorization", token.to_string()))
+            .peer_addr("203.0.113.7:4000".parse().unwrap())
+            .set_json(json!({ "password": password }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, erase(&other_token, "correct horse battery")).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    let resp = test::call_service(&app, erase(&token, "wrong password")).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+    let resp = test::call_service(&app, erase(&token, "correct horse battery")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body, json!({ "message": "All data erased" }));
+
+    let todos: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM todos WHERE user_id = $1").bind(user_id).fetch_one(&ctx.pool).await.unwrap();
+    assert_eq!(todos, 0);
+    let comments: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM comments WHERE user_id = $1").bind(user_id).fetch_one(&ctx.pool).await.unwrap();
+    assert_eq!(comments, 0);
+    let user: (String, String, bool) =
+        sqlx::query_as(r#"SELECT name, password, deleted_at IS NOT NULL FROM "Users" WHERE id = $1"#)
+            .bind(user_id)
+            .fetch_one(&ctx.pool)
+            .await
+            .unwrap();
+    assert_eq!(user, (format!("deleted_user_{}", user_id), "REDACTED".to_string(), true));
+
+    let actor_ip: Option<String> = sqlx::query_scalar(
+        "SELECT actor_ip FROM audit_log WHERE table_name = 'todos' AND row_id = $1 AND action = 'delete'",
+    )
+        .bind(todo["id"].as_i64().unwrap() as i32)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(actor_ip.as_deref(), Some("203.0.113.7"));
+
+    // Nor does the audit trail still hold their name or what their todos said
+    let leftovers: i64 = sqlx::query_scalar(
+        r#"SELECT COUNT(*) FROM audit_log
+           WHERE (table_name = 'Users' AND row_id = $1 AND (old_data IS NOT NULL OR new_data IS NOT NULL))
+              OR COALESCE(old_data::TEXT, '') LIKE '%Private plans%'
+              OR COALESCE(new_data::TEXT, '') LIKE '%Private plans%'"#,
+    )
+        .bind(user_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(leftovers, 0);
+    let theirs: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM audit_log WHERE table_name = 'todos' AND new_data ->> 'title' = 'Theirs'",
+    )
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(theirs, 1);
+
+    // The token used for the erasure is revoked, and so is any other the user still holds
+    for token in [token, format!("Bearer {}", issue_token(user_id, Role::User))] {
+        let req = test::TestRequest::get()
+            .uri("/todos")
+            .insert_header(("Authorization", token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    // Nobody gets back in through the anonymised name
+    let erased_name = format!("deleted_user_{}", user_id);
+    let req = test::TestRequest::post()
+        .uri("/password-reset/request")
+        .set_json(json!({ "name": erased_name }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::ACCEPTED);
+    let reset_tokens: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM password_reset_tokens WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(reset_tokens, 0);
+    let req = test::TestRequest::post()
+        .uri("/login")
+        .set_json(json!({ "name": erased_name, "password": "REDACTED" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    // And the name can't be registered by someone else
+    let req = test::TestRequest::post()
+        .uri("/register")
+        .set_json(json!({ "name": format!("Deleted_User_{}", user_id + 1), "password": "correct horse battery" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // The account is gone from the user lookups too
+    let req = test::TestRequest::get()
+        .uri(&format!("/users/{}", user_id))
+        .insert_header(("Authorization", other_token.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    let admin_token = format!("Bearer {}", issue_token(other_id, Role::Admin));
+    let req = test::TestRequest::get()
+        .uri("/users")
+        .insert_header(("Authorization", admin_token))
+        .to_request();
+    let page: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+    assert_eq!(page["items"][0]["id"], other_id);
+}
+
+#[actix_web::test]
+async fn admins_search_users_by_part_of_the_name() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (_, user_token) = create_user(&ctx.pool).await;
+    let admin_id = seed_admin(&ctx.pool, "root", "correct horse battery")
+        .await
+        .unwrap()
+        .expect("no admin existed yet");
+    let admin_token = format!("Bearer {}", issue_token(admin_id, Role::Admin));
+    for name in ["Alice", "malice", "bob", "al_ex"] {
+        sqlx::query(r#"INSERT INTO "Users" (name, password) VALUES ($1, 'x')"#)
+            .bind(name)
+            .execute(&ctx.pool)
+            .await
+            .unwrap();
+    }
+
+    let search = |query: &str, token: &str| {
+        test::TestRequest::get()
+            .uri(&format!("/users/search?{}", query))
+            .insert_header(("Authorization", token.to_string()))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, search("q=lic", &user_token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let users: Vec<Value> = test::call_and_read_body_json(&app, search("q=LIC", &admin_token)).await;
+    let names: Vec<&str> = users.iter().map(|u| u["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["Alice", "malice"]);
+
+    // The header counts every match, not just the ones returned
+    let resp = test::call_service(&app, search("q=lic&limit=1", &admin_token)).await;
+    assert_eq!(resp.headers().get("X-Total-Count").unwrap(), "2");
+    let users: Vec<Value> = test::read_body_json(resp).await;
+    assert_eq!(users.len(), 1);
+
+    // _ is not a wildcard
+    let users: Vec<Value> = test::call_and_read_body_json(&app, search("q=l_", &admin_token)).await;
+    let names: Vec<&str> = users.iter().map(|u| u["name"].as_str().unwrap()).collect();
+    assert_eq!(names, ["al_ex"]);
+
+    let resp = test::call_service(&app, search("q=a", &admin_token)).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn users_set_and_remove_their_avatar() {
+    let ctx = TestContext::setup().await;
+    let app = test::init_service(build_app(&ctx.pool)).await;
+
+    let (user_id, token) = create_user(&ctx.pool).await;
+    let (_, other_token) = create_user(&ctx.pool).await;
+
+    let set_avatar = |avatar_url: &str, token: &str| {
+        test::TestRequest::patch()
+            .uri(&format!("/users/{}/avatar", user_id))
+            .insert_header(("Authorization", token.to_string()))
+            .set_json(json!({ "avatar_url": avatar_url }))
+            .to_request()
+    };
+
+    let avatar_url = "https://secure.gravatar.com/avatar/205e460b479e2e5b48aec07710c08d50";
+    let resp = test::call_service(&app, set_avatar(avatar_url, &other_token)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+    let long_url = format!("https://gravatar.com/{}", "a".repeat(2048));
+    for rejected in [
+        "http://gravatar.com/avatar/1",
+        "https://evil.example/avatar.png",
+        "https://notgravatar.com/avatar.png",
+        "not a url",
+        long_url.as_str(),
+    ] {
+        let resp = test::call_service(&app, set_avatar(rejected, &token)).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY, "{}", rejected);
+    }
+
+    let user: Value = test::call_and_read_body_json(&app, set_avatar(avatar_url, &token)).await;
+    assert_eq!(user["avatar_url"], avatar_url);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/users/{}/avatar", user_id))
+        .insert_header(("Authorization", token.clone()))
+        .to_request();
+    let user: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(user["avatar_url"], Value::Null);
+}
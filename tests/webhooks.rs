@@ -0,0 +1,222 @@
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, web, App, HttpRequest, HttpResponse, HttpServer};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::net::TcpListener;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use todo_backend::webhooks::{self, signature, EVENT_HEADER, SIGNATURE_HEADER};
+
+use common::{build_app, create_user, verify_email, TestContext};
+
+// A request as the receiver saw it: event header, signature header and body
+type Received = (String, String, Vec<u8>);
+
+// Start a receiver on a free local port that answers every request with `status`.
+// Returns its URL and the requests it got.
+fn start_receiver(status: StatusCode) -> (String, UnboundedReceiver<Received>) {
+    let (sender, receiver) = unbounded_channel();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::new(sender.clone()))
+            .default_service(web::to(move |req: HttpRequest, body: web::Bytes, sender: web::Data<UnboundedSender<Received>>| async move {
+                let header = |name: &str| {
+                    req.headers().get(name).and_then(|value| value.to_str().ok()).unwrap_or_default().to_string()
+                };
+                sender.send((header(EVENT_HEADER), header(SIGNATURE_HEADER), body.to_vec())).ok();
+                HttpResponse::build(status).finish()
+            }))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    tokio::spawn(server);
+
+    (url, receiver)
+}
+
+// Wait until the webhook's deliveries settle on `status`, returning (status, attempts) of each
+async fn wait_for_deliveries(pool: &PgPool, webhook_id: i64, status: &str, count: usize) -> Vec<(String, i32)> {
+    for _ in 0..100 {
+        let rows: Vec<(String, i32)> =
+            sqlx::query_as("SELECT status, attempts FROM webhook_deliveries WHERE webhook_id = $1 ORDER BY id")
+                .bind(webhook_id as i32)
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        if rows.len() == count && rows.iter().all(|(s, _)| s == status) {
+            return rows;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("deliveries of webhook {} never became {}", webhook_id, status);
+}
+
+#[actix_web::test]
+async fn subscribed_events_are_delivered_signed() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    verify_email(&ctx.pool, user_id).await;
+    webhooks::allow_private_targets(true);
+    let app = test::init_service(build_app(&ctx.pool)).await;
+    let (url, mut received) = start_receiver(StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/webhooks")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "url": url, "events": ["todo.created", "todo.completed"], "secret": "s3cret" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let webhook: Value = test::read_body_json(resp).await;
+    assert!(webhook.get("secret").is_none());
+    let webhook_id = webhook["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants" }))
+        .to_request();
+    let todo: Value = test::call_and_read_body_json(&app, req).await;
+
+    let (event, signature_header, body) = received.recv().await.unwrap();
+    assert_eq!(event, "todo.created");
+    assert_eq!(signature_header, signature("s3cret", &body));
+    let payload: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(payload["event"], "todo.created");
+    assert_eq!(payload["data"]["id"], todo["id"]);
+
+    // Not subscribed to todo.updated, so only the completion goes out
+    let req = test::TestRequest::patch()
+        .uri(&format!("/todos/{}", todo["id"]))
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Water plants", "completed": true }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let (event, _, _) = received.recv().await.unwrap();
+    assert_eq!(event, "todo.completed");
+    assert_eq!(
+        wait_for_deliveries(&ctx.pool, webhook_id, "delivered", 2).await,
+        vec![("delivered".to_string(), 1), ("delivered".to_string(), 1)]
+    );
+
+    let req = test::TestRequest::get()
+        .uri("/webhooks")
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    let webhooks: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(webhooks.as_array().unwrap().len(), 1);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/webhooks/{}", webhook_id))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NO_CONTENT);
+
+    let req = test::TestRequest::delete()
+        .uri(&format!("/webhooks/{}", webhook_id))
+        .insert_header(("Authorization", token.as_str()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn failing_receiver_is_retried_then_marked_failed() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    verify_email(&ctx.pool, user_id).await;
+    webhooks::allow_private_targets(true);
+    let app = test::init_service(build_app(&ctx.pool)).await;
+    let (url, mut received) = start_receiver(StatusCode::INTERNAL_SERVER_ERROR);
+
+    // Unknown events and non-HTTP URLs are refused
+    let req = test::TestRequest::post()
+        .uri("/webhooks")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "url": "ftp://example.com", "events": ["todo.exploded"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let req = test::TestRequest::post()
+        .uri("/webhooks")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "url": url, "events": ["todo.created"] }))
+        .to_request();
+    let webhook: Value = test::call_and_read_body_json(&app, req).await;
+    let webhook_id = webhook["id"].as_i64().unwrap();
+
+    let req = test::TestRequest::post()
+        .uri("/todos")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "title": "Unlucky" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+    // Without a secret there's no signature
+    let (_, signature_header, _) = received.recv().await.unwrap();
+    assert_eq!(signature_header, "");
+
+    assert_eq!(
+        wait_for_deliveries(&ctx.pool, webhook_id, "failed", 1).await,
+        vec![("failed".to_string(), 3)]
+    );
+    for _ in 0..2 {
+        received.recv().await.unwrap();
+    }
+}
+
+#[actix_web::test]
+async fn private_addresses_are_refused() {
+    let ctx = TestContext::setup().await;
+    let (user_id, token) = create_user(&ctx.pool).await;
+    verify_email(&ctx.pool, user_id).await;
+    webhooks::allow_private_targets(false);
+    let app = test::init_service(build_app(&ctx.pool)).await;
+    let (url, mut received) = start_receiver(StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/webhooks")
+        .insert_header(("Authorization", token.as_str()))
+        .set_json(json!({ "url": url, "events": ["todo.created"] }))
+        .to_request();
+    let webhook: Value = test::call_and_read_body_json(&app, req).await;
+    let webhook_id = webhook["id"].as_i64().unwrap();
+
+    // Imported todos fire todo.created like any other
+    let req = test::TestRequest::post()
+        .uri("/todos/import")
+        .insert_header(("Authorization", token.as_str()))
+        .insert_header(("Content-Type", "multipart/form-data; boundary=BOUNDARY"))
+        .set_payload(
+            "--BOUNDARY\r\nContent-Disposition: form-data; name=\"file\"; filename=\"todos.csv\"\r\nContent-Type: text/csv\r\n\r\ntitle\nPay rent\r\n--BOUNDARY--\r\n",
+        )
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // Given up on after the first attempt, without contacting the receiver
+    assert_eq!(
+        wait_for_deliveries(&ctx.pool, webhook_id, "failed", 1).await,
+        vec![("failed".to_string(), 1)]
+    );
+    let error: String = sqlx::query_scalar("SELECT last_error FROM webhook_deliveries WHERE webhook_id = $1")
+        .bind(webhook_id as i32)
+        .fetch_one(&ctx.pool)
+        .await
+        .unwrap();
+    assert_eq!(error, "refusing to deliver to non-public address 127.0.0.1");
+    assert!(received.try_recv().is_err());
+
+    for ip in ["127.0.0.1", "10.1.2.3", "169.254.169.254", "192.168.0.1", "100.64.0.1", "::1", "fe80::1", "fd00::1", "::ffff:127.0.0.1"] {
+        assert!(!webhooks::is_public_address(ip.parse().unwrap()), "{}", ip);
+    }
+    for ip in ["93.184.216.34", "2606:2800:220:1::1"] {
+        assert!(webhooks::is_public_address(ip.parse().unwrap()), "{}", ip);
+    }
+}